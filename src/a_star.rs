@@ -45,11 +45,15 @@ fn get_neighbors(
             position.y + dir_offset[1],
             position.z + dir_offset[2],
         );
-        if chunk.get_block(
-            neighbor_position.x,
-            neighbor_position.y,
-            neighbor_position.z,
-        ) {
+        // Paths can go through doors, since ais can open the ones that aren't locked.
+        if !chunk
+            .block_at(
+                neighbor_position.x,
+                neighbor_position.y,
+                neighbor_position.z,
+            )
+            .is_walkable()
+        {
             continue;
         }
 
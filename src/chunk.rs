@@ -1,19 +1,32 @@
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
 
-use crate::direction::{dir_outward_component, dir_to_offset, index_to_dir, Direction};
-use crate::gfx::cube_mesh::{CUBE_INDICES, CUBE_VERTICES};
-use crate::gfx::instance::Instance;
-use crate::gfx::model::Model;
-use crate::gfx::vertex::Vertex;
+use crate::entities::item::{ItemKind, KeyKind};
+use crate::fov::shadowcast;
+use crate::light::{LightMap, MAX_LIGHT};
 use crate::math::round_vec_to_i32;
 use crate::rng::Rng;
 use cgmath::prelude::*;
 
 pub const BLOCK_SIZE: i32 = 3;
 pub const BLOCK_SIZE_F: f32 = BLOCK_SIZE as f32;
-const CHUNK_SIZE: usize = 32;
-const CHUNK_HEIGHT: usize = 8;
-const CHUNK_LEN: usize = CHUNK_SIZE * CHUNK_HEIGHT * CHUNK_SIZE;
+pub const CHUNK_SIZE: usize = 32;
+pub const CHUNK_HEIGHT: usize = 8;
+pub const CHUNK_LEN: usize = CHUNK_SIZE * CHUNK_HEIGHT * CHUNK_SIZE;
+const CHUNK_AREA: usize = CHUNK_SIZE * CHUNK_SIZE;
+// Chunks are meshed in sections, columns of blocks that go the full height of the chunk.
+pub const SECTION_SIZE: usize = 8;
+const SECTIONS_PER_SIDE: usize = CHUNK_SIZE / SECTION_SIZE;
+pub const SECTION_COUNT: usize = SECTIONS_PER_SIDE * SECTIONS_PER_SIDE;
+// How bright blocks are with no light reaching them.
+const AMBIENT_LIGHT: f32 = 0.08;
+// The layer that entities walk on, just above the floor.
+pub const GROUND_LAYER: i32 = 1;
+const VAULT_SIZE: i32 = 5;
+const VAULT_HEIGHT: i32 = 2;
+const VAULT_ATTEMPTS: u32 = 20;
+const WALL_BREAK_TIME: f32 = 1.5;
+// How many block edits are remembered, for checking whether paths cross them.
+const EDIT_HISTORY_LEN: usize = 64;
 const INV_BLOCK_SIZE: f32 = 1.0 / BLOCK_SIZE as f32;
 
 pub struct RaycastHit {
@@ -22,20 +35,104 @@ pub struct RaycastHit {
     pub last_position: cgmath::Vector3<i32>,
 }
 
-struct VertexNeighbors {
-    side1: bool,
-    side2: bool,
-    corner: bool,
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Door {
+    pub is_open: bool,
+    pub lock: Option<KeyKind>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Block {
+    Air,
+    Wall,
+    Door(Door),
+}
+
+impl Block {
+    pub fn is_solid(&self) -> bool {
+        match self {
+            Block::Air => false,
+            Block::Wall => true,
+            Block::Door(door) => !door.is_open,
+        }
+    }
+
+    // How long the block takes to break with a mining power of 1, or None if it can't be broken.
+    pub fn break_time(&self) -> Option<f32> {
+        match self {
+            Block::Air => None,
+            Block::Wall => Some(WALL_BREAK_TIME),
+            Block::Door(_) => None,
+        }
+    }
+
+    // Whether an ai can path through the block, opening it if needed.
+    pub fn is_walkable(&self) -> bool {
+        match self {
+            Block::Air => true,
+            Block::Wall => false,
+            Block::Door(door) => door.is_open || door.lock.is_none(),
+        }
+    }
+}
+
+// The area inside of a vault's walls, along with the door that leads into it.
+struct Vault {
+    x: i32,
+    z: i32,
+    door: cgmath::Vector3<i32>,
+    door_front: cgmath::Vector3<i32>,
+}
+
+// A copy of everything that meshing reads from a chunk, small enough to take every time that the
+// chunk is meshed on another thread.
+pub struct ChunkSnapshot {
+    blocks: Box<[Block; CHUNK_LEN]>,
+    light: Vec<u8>,
+    visible: [bool; CHUNK_AREA],
+    explored: [bool; CHUNK_AREA],
+}
+
+impl ChunkSnapshot {
+    pub fn block_at(&self, x: i32, y: i32, z: i32) -> Block {
+        Chunk::block_in(&self.blocks, x, y, z)
+    }
+
+    // Whether the block is solid.
+    pub fn get_block(&self, x: i32, y: i32, z: i32) -> bool {
+        self.block_at(x, y, z).is_solid()
+    }
+
+    pub fn light_at(&self, x: i32, y: i32, z: i32) -> f32 {
+        light_brightness(Chunk::block_index(x, y, z).map_or(0, |i| self.light[i]))
+    }
+
+    pub fn is_visible(&self, x: i32, z: i32) -> bool {
+        Chunk::column_index(x, z).is_some_and(|i| self.visible[i])
+    }
+
+    pub fn is_explored(&self, x: i32, z: i32) -> bool {
+        Chunk::column_index(x, z).is_some_and(|i| self.explored[i])
+    }
 }
 
 pub struct Chunk {
-    model: Option<Model>,
-    blocks: [bool; CHUNK_LEN],
+    blocks: [Block; CHUNK_LEN],
+    // Incremented whenever the blocks change, so that anything derived from them can be updated.
+    revision: u32,
+    // The most recently changed blocks, oldest first. There is one entry for each revision.
+    edit_history: VecDeque<cgmath::Vector3<i32>>,
+    // Vaults can't be dug into or built in, otherwise their keys could be skipped.
+    vaults: Vec<Vault>,
+    // Columns on the ground layer that are reachable without going through any locked doors.
+    spawn_area: [bool; CHUNK_AREA],
+    light: LightMap,
     entities_on_blocks: Vec<HashSet<usize>>,
-    vertices: Vec<Vertex>,
-    indices: Vec<u32>,
-    ao_buffer: [i32; 4],
-    is_dirty: bool,
+    // Visibility is tracked per column of blocks.
+    visible: [bool; CHUNK_AREA],
+    explored: [bool; CHUNK_AREA],
+    // The sections whose meshes are out of date.
+    dirty_sections: [bool; SECTION_COUNT],
 }
 
 impl Chunk {
@@ -47,17 +144,21 @@ impl Chunk {
         }
 
         Self {
-            blocks: [false; CHUNK_LEN],
+            blocks: [Block::Air; CHUNK_LEN],
+            revision: 0,
+            edit_history: VecDeque::new(),
+            vaults: Vec::new(),
+            spawn_area: [false; CHUNK_AREA],
+            light: LightMap::new(),
             entities_on_blocks,
-            model: None,
-            vertices: Vec::new(),
-            indices: Vec::new(),
-            ao_buffer: [0; 4],
-            is_dirty: false,
+            visible: [false; CHUNK_AREA],
+            explored: [false; CHUNK_AREA],
+            dirty_sections: [true; SECTION_COUNT],
         }
     }
 
-    pub fn generate_blocks(&mut self, rng: &mut Rng) {
+    // Returns the items that were placed in the level as part of its layout, such as keys.
+    pub fn generate_blocks(&mut self, rng: &mut Rng) -> Vec<(ItemKind, cgmath::Vector3<f32>)> {
         // for z in 0..CHUNK_SIZE {
         //     for y in 0..CHUNK_HEIGHT {
         //         for x in 0..CHUNK_SIZE {
@@ -82,103 +183,274 @@ impl Chunk {
                     continue;
                 }
 
-                self.set_block(true, x as i32, 1, z as i32);
+                self.set_block(true, x as i32, GROUND_LAYER, z as i32);
             }
         }
+
+        let vaults = self.generate_vaults(rng);
+        self.update_spawn_area(&vaults);
+        let level_items = self.place_keys(&vaults, rng);
+        self.vaults = vaults;
+
+        level_items
     }
 
-    pub fn update_mesh(&mut self, device: &wgpu::Device) {
-        if self.is_dirty {
-            self.generate_mesh(device);
+    // Place walled off rooms that are each locked with a different kind of key.
+    fn generate_vaults(&mut self, rng: &mut Rng) -> Vec<Vault> {
+        let mut vaults = Vec::<Vault>::new();
+        let position_range = (CHUNK_SIZE as i32 - VAULT_SIZE - 1) as u32;
+
+        for key in KeyKind::ALL {
+            for _ in 0..VAULT_ATTEMPTS {
+                let x = 1 + rng.range(position_range) as i32;
+                let z = 1 + rng.range(position_range) as i32;
+
+                // Leave a gap between vaults so that there is always a way around them.
+                if vaults
+                    .iter()
+                    .any(|v| (v.x - x).abs() < VAULT_SIZE + 2 && (v.z - z).abs() < VAULT_SIZE + 2)
+                {
+                    continue;
+                }
+
+                vaults.push(self.build_vault(x, z, key, rng));
+                break;
+            }
         }
+
+        vaults
     }
 
-    pub fn generate_mesh(&mut self, device: &wgpu::Device) {
-        self.is_dirty = false;
+    fn build_vault(&mut self, x: i32, z: i32, key: KeyKind, rng: &mut Rng) -> Vault {
+        for y in GROUND_LAYER..GROUND_LAYER + VAULT_HEIGHT {
+            for offset_z in 0..VAULT_SIZE {
+                for offset_x in 0..VAULT_SIZE {
+                    let is_edge = offset_x == 0
+                        || offset_z == 0
+                        || offset_x == VAULT_SIZE - 1
+                        || offset_z == VAULT_SIZE - 1;
 
-        self.vertices.clear();
-        self.indices.clear();
+                    self.set_block(is_edge, x + offset_x, y, z + offset_z);
+                }
+            }
+        }
 
-        for z in 0..CHUNK_SIZE {
-            let iz = z as i32;
-            for y in 0..CHUNK_HEIGHT {
-                let iy = y as i32;
-                for x in 0..CHUNK_SIZE {
-                    let ix = x as i32;
+        let middle = VAULT_SIZE / 2;
+        let (door_offset, front_offset) = match rng.range(4) {
+            0 => (cgmath::vec3(middle, 0, 0), cgmath::vec3(0, 0, -1)),
+            1 => (
+                cgmath::vec3(middle, 0, VAULT_SIZE - 1),
+                cgmath::vec3(0, 0, 1),
+            ),
+            2 => (cgmath::vec3(0, 0, middle), cgmath::vec3(-1, 0, 0)),
+            _ => (
+                cgmath::vec3(VAULT_SIZE - 1, 0, middle),
+                cgmath::vec3(1, 0, 0),
+            ),
+        };
+
+        let door = cgmath::vec3(x, GROUND_LAYER, z) + door_offset;
+        let door_front = door + front_offset;
+
+        self.set_block_at(
+            Block::Door(Door {
+                is_open: false,
+                lock: Some(key),
+            }),
+            door.x,
+            door.y,
+            door.z,
+        );
+        self.set_block(false, door_front.x, door_front.y, door_front.z);
+
+        Vault {
+            x,
+            z,
+            door,
+            door_front,
+        }
+    }
 
-                    let block = self.get_block(ix, iy, iz);
+    // Find the largest open area on the ground layer, then connect every vault's door to it.
+    fn update_spawn_area(&mut self, vaults: &[Vault]) {
+        let mut visited = [false; CHUNK_AREA];
+        let mut region = [false; CHUNK_AREA];
+        let mut largest_size = 0;
 
-                    if !block {
-                        continue;
-                    }
+        for i in 0..CHUNK_AREA {
+            if visited[i] {
+                continue;
+            }
 
-                    for dir_i in 0..6 {
-                        let dir = index_to_dir(dir_i);
-                        let dir_offset = dir_to_offset(dir);
-                        if self.get_block(
-                            ix + dir_offset[0],
-                            iy + dir_offset[1],
-                            iz + dir_offset[2],
-                        ) {
-                            continue;
-                        }
-
-                        let vert_count = self.vertices.len() as u32;
-
-                        for vert_i in 0..4 {
-                            let mut vert = CUBE_VERTICES[dir_i][vert_i];
-                            vert.position[0] = (vert.position[0] + ix as f32) * BLOCK_SIZE_F;
-                            vert.position[1] = (vert.position[1] + iy as f32) * BLOCK_SIZE_F;
-                            vert.position[2] = (vert.position[2] + iz as f32) * BLOCK_SIZE_F;
-
-                            let neighbors = self.check_vertex_neighbors(
-                                [ix, iy, iz],
-                                [
-                                    CUBE_VERTICES[dir_i][vert_i].position[0] as i32,
-                                    CUBE_VERTICES[dir_i][vert_i].position[1] as i32,
-                                    CUBE_VERTICES[dir_i][vert_i].position[2] as i32,
-                                ],
-                                dir,
-                            );
-                            let ao = Chunk::calculate_ao_level(neighbors);
-                            self.ao_buffer[vert_i] = ao;
-                            let ao_light_value = ao as f32 * 0.33;
-                            vert.color[0] = ao_light_value;
-                            vert.color[1] = ao_light_value;
-                            vert.color[2] = ao_light_value;
-
-                            self.vertices.push(vert);
-                        }
-
-                        for ind_i in 0..6 {
-                            self.indices.push(CUBE_INDICES[dir_i][ind_i] + vert_count);
-                        }
-
-                        self.orient_last_face();
-                    }
+            region.fill(false);
+            let size = self.flood_ground(i, &mut region);
+
+            for (visited, is_in_region) in visited.iter_mut().zip(region) {
+                *visited |= is_in_region;
+            }
+
+            if size > largest_size {
+                largest_size = size;
+                self.spawn_area = region;
+            }
+        }
+
+        for vault in vaults {
+            if let Some(i) = Self::column_index(vault.door_front.x, vault.door_front.z) {
+                if !self.spawn_area[i] {
+                    self.carve_path_to_spawn_area(i, vaults);
+                }
+            }
+        }
+    }
+
+    // Mark every open block on the ground layer that can be reached from the start column.
+    fn flood_ground(&self, start: usize, area: &mut [bool; CHUNK_AREA]) -> usize {
+        let is_open = |i: usize| {
+            self.block_at(
+                (i % CHUNK_SIZE) as i32,
+                GROUND_LAYER,
+                (i / CHUNK_SIZE) as i32,
+            ) == Block::Air
+        };
+
+        if area[start] || !is_open(start) {
+            return 0;
+        }
+
+        let mut size = 0;
+        let mut frontier = VecDeque::new();
+        area[start] = true;
+        frontier.push_back(start);
+
+        while let Some(i) = frontier.pop_front() {
+            size += 1;
+
+            for next in Self::column_neighbors(i).into_iter().flatten() {
+                if !area[next] && is_open(next) {
+                    area[next] = true;
+                    frontier.push_back(next);
+                }
+            }
+        }
+
+        size
+    }
+
+    // Clear the shortest path of walls between a column and the spawn area, without breaking
+    // into any vaults.
+    fn carve_path_to_spawn_area(&mut self, start: usize, vaults: &[Vault]) {
+        let is_in_vault = |i: usize| {
+            let x = (i % CHUNK_SIZE) as i32;
+            let z = (i / CHUNK_SIZE) as i32;
+            vaults
+                .iter()
+                .any(|v| x >= v.x && x < v.x + VAULT_SIZE && z >= v.z && z < v.z + VAULT_SIZE)
+        };
+
+        let mut came_from = [None; CHUNK_AREA];
+        let mut frontier = VecDeque::new();
+        came_from[start] = Some(start);
+        frontier.push_back(start);
+
+        let mut end = None;
+
+        while let Some(i) = frontier.pop_front() {
+            if self.spawn_area[i] {
+                end = Some(i);
+                break;
+            }
+
+            for next in Self::column_neighbors(i).into_iter().flatten() {
+                if came_from[next].is_none() && !is_in_vault(next) {
+                    came_from[next] = Some(i);
+                    frontier.push_back(next);
                 }
             }
         }
 
-        self.model = Some(Model::new(device, &self.vertices, &self.indices));
-
-        if let Some(model) = &mut self.model {
-            model.update_instances(
-                device,
-                &vec![Instance {
-                    position: cgmath::Vector3 {
-                        x: 0.0,
-                        y: 0.0,
-                        z: 0.0,
-                    },
-                    rotation: cgmath::Quaternion::zero(),
-                    tex_index: 0,
-                }],
+        let mut current = match end {
+            Some(e) => e,
+            None => return,
+        };
+
+        while current != start {
+            current = came_from[current].unwrap();
+            self.set_block(
+                false,
+                (current % CHUNK_SIZE) as i32,
+                GROUND_LAYER,
+                (current / CHUNK_SIZE) as i32,
             );
         }
+
+        let mut spawn_area = self.spawn_area;
+        self.flood_ground(start, &mut spawn_area);
+        self.spawn_area = spawn_area;
+    }
+
+    // Each vault's key is placed where it can be reached before the vault is opened: the first
+    // in the open, and the rest inside the vault before them. The last vault holds a reward.
+    fn place_keys(&self, vaults: &[Vault], rng: &mut Rng) -> Vec<(ItemKind, cgmath::Vector3<f32>)> {
+        let mut items = Vec::new();
+
+        for (i, vault) in vaults.iter().enumerate() {
+            let key = match self.block_at(vault.door.x, vault.door.y, vault.door.z) {
+                Block::Door(Door {
+                    lock: Some(key), ..
+                }) => key,
+                _ => continue,
+            };
+
+            let position = if i == 0 {
+                self.get_spawn_position(rng)
+            } else {
+                Some(Self::get_vault_position(&vaults[i - 1], rng))
+            };
+
+            if let Some(position) = position {
+                items.push((ItemKind::Key(key), position));
+            }
+        }
+
+        if let Some(vault) = vaults.last() {
+            let kind = ItemKind::ALL[rng.range(ItemKind::ALL.len() as u32) as usize];
+            items.push((kind, Self::get_vault_position(vault, rng)));
+        }
+
+        items
+    }
+
+    fn get_vault_position(vault: &Vault, rng: &mut Rng) -> cgmath::Vector3<f32> {
+        let interior_size = (VAULT_SIZE - 2) as u32;
+        let x = vault.x + 1 + rng.range(interior_size) as i32;
+        let z = vault.z + 1 + rng.range(interior_size) as i32;
+
+        cgmath::vec3(
+            (x as f32 + 0.5) * BLOCK_SIZE_F,
+            (GROUND_LAYER as f32 + 0.5) * BLOCK_SIZE_F,
+            (z as f32 + 0.5) * BLOCK_SIZE_F,
+        )
+    }
+
+    fn column_neighbors(i: usize) -> [Option<usize>; 4] {
+        let x = (i % CHUNK_SIZE) as i32;
+        let z = (i / CHUNK_SIZE) as i32;
+
+        [
+            Self::column_index(x + 1, z),
+            Self::column_index(x - 1, z),
+            Self::column_index(x, z + 1),
+            Self::column_index(x, z - 1),
+        ]
     }
 
     pub fn set_block(&mut self, solid: bool, x: i32, y: i32, z: i32) {
+        let block = if solid { Block::Wall } else { Block::Air };
+        self.set_block_at(block, x, y, z);
+    }
+
+    pub fn set_block_at(&mut self, block: Block, x: i32, y: i32, z: i32) {
         let i_chunk_size = CHUNK_SIZE as i32;
         let i_chunk_height = CHUNK_HEIGHT as i32;
         if x < 0 || x >= i_chunk_size || y < 0 || y >= i_chunk_height || z < 0 || z >= i_chunk_size
@@ -190,23 +462,187 @@ impl Chunk {
         let uy = y as usize;
         let uz = z as usize;
 
-        self.blocks[ux + uy * CHUNK_SIZE + uz * CHUNK_SIZE * CHUNK_HEIGHT] = solid;
-        self.is_dirty = true;
+        self.blocks[ux + uy * CHUNK_SIZE + uz * CHUNK_SIZE * CHUNK_HEIGHT] = block;
+        self.revision = self.revision.wrapping_add(1);
+        self.mark_block_dirty(x, z);
+
+        if self.edit_history.len() == EDIT_HISTORY_LEN {
+            self.edit_history.pop_front();
+        }
+        self.edit_history.push_back(cgmath::vec3(x, y, z));
+
+        let blocks = &self.blocks;
+        self.light.update_block(cgmath::vec3(x, y, z), &|x, y, z| {
+            Self::block_in(blocks, x, y, z).is_solid()
+        });
+        self.mark_light_changes_dirty();
     }
 
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    // Replace every block at once, such as when loading a level. The blocks must be in the same
+    // order as the ones returned by blocks.
+    pub fn load_blocks(&mut self, blocks: &[Block]) {
+        if blocks.len() != CHUNK_LEN {
+            return;
+        }
+
+        self.blocks.copy_from_slice(blocks);
+        self.revision = self.revision.wrapping_add(1);
+        self.edit_history.clear();
+        self.vaults.clear();
+        self.visible = [false; CHUNK_AREA];
+        self.explored = [false; CHUNK_AREA];
+        self.dirty_sections = [true; SECTION_COUNT];
+
+        let blocks = &self.blocks;
+        self.light
+            .recalculate(&|x, y, z| Self::block_in(blocks, x, y, z).is_solid());
+
+        self.update_spawn_area(&[]);
+    }
+
+    // Whether the block is solid.
     pub fn get_block(&self, x: i32, y: i32, z: i32) -> bool {
+        self.block_at(x, y, z).is_solid()
+    }
+
+    pub fn block_at(&self, x: i32, y: i32, z: i32) -> Block {
+        Self::block_in(&self.blocks, x, y, z)
+    }
+
+    fn block_in(blocks: &[Block; CHUNK_LEN], x: i32, y: i32, z: i32) -> Block {
         let i_chunk_size = CHUNK_SIZE as i32;
         let i_chunk_height = CHUNK_HEIGHT as i32;
         if x < 0 || x >= i_chunk_size || y < 0 || y >= i_chunk_height || z < 0 || z >= i_chunk_size
         {
-            return true;
+            return Block::Wall;
         }
 
         let ux = x as usize;
         let uy = y as usize;
         let uz = z as usize;
 
-        self.blocks[ux + uy * CHUNK_SIZE + uz * CHUNK_SIZE * CHUNK_HEIGHT]
+        blocks[ux + uy * CHUNK_SIZE + uz * CHUNK_SIZE * CHUNK_HEIGHT]
+    }
+
+    // Add a light source, or move it if it already exists. The id is whatever owns the light.
+    pub fn set_light_source(&mut self, id: usize, position: cgmath::Vector3<i32>, level: u8) {
+        let blocks = &self.blocks;
+        self.light.set_source(id, position, level, &|x, y, z| {
+            Self::block_in(blocks, x, y, z).is_solid()
+        });
+        self.mark_light_changes_dirty();
+    }
+
+    pub fn remove_light_source(&mut self, id: usize) {
+        let blocks = &self.blocks;
+        self.light
+            .remove_source(id, &|x, y, z| Self::block_in(blocks, x, y, z).is_solid());
+        self.mark_light_changes_dirty();
+    }
+
+    // How brightly lit a block is, from 0 to 1.
+    pub fn light_at(&self, x: i32, y: i32, z: i32) -> f32 {
+        light_brightness(self.light.get(x, y, z))
+    }
+
+    pub fn snapshot(&self) -> ChunkSnapshot {
+        ChunkSnapshot {
+            blocks: Box::new(self.blocks),
+            light: self.light.levels().to_vec(),
+            visible: self.visible,
+            explored: self.explored,
+        }
+    }
+
+    // The sections where anything that shows in the mesh has changed since they were last built.
+    pub fn dirty_sections(&self) -> Vec<usize> {
+        (0..SECTION_COUNT)
+            .filter(|s| self.dirty_sections[*s])
+            .collect()
+    }
+
+    pub fn mark_meshed(&mut self, section: usize) {
+        self.dirty_sections[section] = false;
+    }
+
+    // The lowest corner of a section, in blocks.
+    pub fn section_origin(section: usize) -> cgmath::Vector3<i32> {
+        cgmath::vec3(
+            (section % SECTIONS_PER_SIDE * SECTION_SIZE) as i32,
+            0,
+            (section / SECTIONS_PER_SIDE * SECTION_SIZE) as i32,
+        )
+    }
+
+    fn mark_section_dirty(&mut self, x: i32, z: i32) {
+        let i_chunk_size = CHUNK_SIZE as i32;
+        if x < 0 || x >= i_chunk_size || z < 0 || z >= i_chunk_size {
+            return;
+        }
+
+        let section = x as usize / SECTION_SIZE + z as usize / SECTION_SIZE * SECTIONS_PER_SIDE;
+        self.dirty_sections[section] = true;
+    }
+
+    // The faces around a block depend on it through culling, ambient occlusion and light, so
+    // changing a block on the edge of a section changes the meshes of the sections next to it.
+    fn mark_block_dirty(&mut self, x: i32, z: i32) {
+        for dz in -1..=1 {
+            for dx in -1..=1 {
+                self.mark_section_dirty(x + dx, z + dz);
+            }
+        }
+    }
+
+    fn mark_light_changes_dirty(&mut self) {
+        for position in self.light.take_changes() {
+            self.mark_block_dirty(position.x, position.z);
+        }
+    }
+
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
+
+    // The blocks that were changed after the given revision. Returns None if the history doesn't
+    // go back that far.
+    pub fn edits_since(
+        &self,
+        revision: u32,
+    ) -> Option<impl Iterator<Item = &cgmath::Vector3<i32>>> {
+        let edit_count = self.revision.wrapping_sub(revision) as usize;
+
+        if edit_count > self.edit_history.len() {
+            return None;
+        }
+
+        Some(
+            self.edit_history
+                .iter()
+                .skip(self.edit_history.len() - edit_count),
+        )
+    }
+
+    pub fn is_in_bounds(&self, x: i32, y: i32, z: i32) -> bool {
+        let i_chunk_size = CHUNK_SIZE as i32;
+        let i_chunk_height = CHUNK_HEIGHT as i32;
+        x >= 0 && x < i_chunk_size && y >= 0 && y < i_chunk_height && z >= 0 && z < i_chunk_size
+    }
+
+    // Whether the block can be dug out or built on. The floor and vaults are left alone.
+    pub fn is_editable(&self, x: i32, y: i32, z: i32) -> bool {
+        if !self.is_in_bounds(x, y, z) || y == 0 {
+            return false;
+        }
+
+        !self
+            .vaults
+            .iter()
+            .any(|v| x >= v.x && x < v.x + VAULT_SIZE && z >= v.z && z < v.z + VAULT_SIZE)
     }
 
     pub fn get_block_collision(
@@ -319,35 +755,140 @@ impl Chunk {
         }
     }
 
+    // Spawn positions are on the ground layer, somewhere that isn't locked away.
     pub fn get_spawn_position(&self, rng: &mut Rng) -> Option<cgmath::Vector3<f32>> {
-        let i_chunk_len = CHUNK_LEN as i32;
-        let i_chunk_size = CHUNK_SIZE as i32;
-        let i_chunk_height = CHUNK_HEIGHT as i32;
-
-        let start_i = rng.range(CHUNK_LEN as u32) as i32;
+        let start_i = rng.range(CHUNK_AREA as u32) as usize;
 
-        for offset_i in 0..i_chunk_len {
-            let i = (start_i + offset_i) % i_chunk_len;
-            let x = i % i_chunk_size;
-            let y = (i / i_chunk_size) % i_chunk_height;
-            let z = i / (i_chunk_size * i_chunk_height);
+        for offset_i in 0..CHUNK_AREA {
+            let i = (start_i + offset_i) % CHUNK_AREA;
 
-            if self.get_block(x, y, z) {
+            if !self.spawn_area[i] {
                 continue;
             }
 
-            return Some(cgmath::vec3(
-                (x as f32 + 0.5) * BLOCK_SIZE_F,
-                (y as f32 + 0.5) * BLOCK_SIZE_F,
-                (z as f32 + 0.5) * BLOCK_SIZE_F,
-            ));
+            return Some(Self::column_spawn_position(i));
         }
 
         None
     }
 
-    pub fn model(&self) -> &Option<Model> {
-        &self.model
+    // A random spawn position within range blocks of a position on the x/z plane, other than in
+    // the position's own column.
+    pub fn get_spawn_position_near(
+        &self,
+        rng: &mut Rng,
+        position: cgmath::Vector3<f32>,
+        range: i32,
+    ) -> Option<cgmath::Vector3<f32>> {
+        let center = round_vec_to_i32(position / BLOCK_SIZE_F);
+        let mut columns = Vec::new();
+
+        for z in center.z - range..=center.z + range {
+            for x in center.x - range..=center.x + range {
+                if (x, z) == (center.x, center.z) {
+                    continue;
+                }
+
+                if let Some(i) = Self::column_index(x, z).filter(|i| self.spawn_area[*i]) {
+                    columns.push(i);
+                }
+            }
+        }
+
+        if columns.is_empty() {
+            return None;
+        }
+
+        let i = columns[rng.range(columns.len() as u32) as usize];
+        Some(Self::column_spawn_position(i))
+    }
+
+    fn column_spawn_position(i: usize) -> cgmath::Vector3<f32> {
+        let x = i % CHUNK_SIZE;
+        let z = i / CHUNK_SIZE;
+
+        cgmath::vec3(
+            (x as f32 + 0.5) * BLOCK_SIZE_F,
+            (GROUND_LAYER as f32 + 0.5) * BLOCK_SIZE_F,
+            (z as f32 + 0.5) * BLOCK_SIZE_F,
+        )
+    }
+
+    // Recalculate which columns can be seen from the origin block, looking across the layer of
+    // blocks that the origin is in.
+    pub fn update_visibility(&mut self, origin: cgmath::Vector3<i32>, radius: i32) {
+        let mut visible = [false; CHUNK_AREA];
+
+        shadowcast(
+            origin.x,
+            origin.z,
+            radius,
+            &|x, z| self.get_block(x, origin.y, z),
+            &mut |x, z| {
+                if let Some(i) = Self::column_index(x, z) {
+                    visible[i] = true;
+                }
+            },
+        );
+
+        if visible == self.visible {
+            return;
+        }
+
+        // Faces are only hidden by the visibility of their own column.
+        let changed_columns = (0..CHUNK_AREA)
+            .filter(|i| self.visible[*i] != visible[*i] || (visible[*i] && !self.explored[*i]))
+            .collect::<Vec<_>>();
+
+        for i in changed_columns {
+            self.mark_section_dirty((i % CHUNK_SIZE) as i32, (i / CHUNK_SIZE) as i32);
+        }
+
+        for (explored, visible) in self.explored.iter_mut().zip(visible) {
+            *explored |= visible;
+        }
+
+        self.visible = visible;
+    }
+
+    // Make the whole chunk visible, for when it is being looked at from outside of the game.
+    pub fn reveal_all(&mut self) {
+        if self.visible.iter().all(|v| *v) && self.explored.iter().all(|e| *e) {
+            return;
+        }
+
+        self.visible = [true; CHUNK_AREA];
+        self.explored = [true; CHUNK_AREA];
+        self.dirty_sections = [true; SECTION_COUNT];
+    }
+
+    pub fn is_visible(&self, x: i32, z: i32) -> bool {
+        Self::column_index(x, z).is_some_and(|i| self.visible[i])
+    }
+
+    pub fn is_explored(&self, x: i32, z: i32) -> bool {
+        Self::column_index(x, z).is_some_and(|i| self.explored[i])
+    }
+
+    // The index of a block in the slice returned by blocks.
+    pub fn block_index(x: i32, y: i32, z: i32) -> Option<usize> {
+        let i_chunk_size = CHUNK_SIZE as i32;
+        let i_chunk_height = CHUNK_HEIGHT as i32;
+        if x < 0 || x >= i_chunk_size || y < 0 || y >= i_chunk_height || z < 0 || z >= i_chunk_size
+        {
+            return None;
+        }
+
+        Some(x as usize + y as usize * CHUNK_SIZE + z as usize * CHUNK_SIZE * CHUNK_HEIGHT)
+    }
+
+    fn column_index(x: i32, z: i32) -> Option<usize> {
+        let i_chunk_size = CHUNK_SIZE as i32;
+        if x < 0 || x >= i_chunk_size || z < 0 || z >= i_chunk_size {
+            return None;
+        }
+
+        Some(x as usize + z as usize * CHUNK_SIZE)
     }
 
     pub fn add_entity_to_block(&mut self, entity: usize, x: i32, z: i32) {
@@ -389,84 +930,98 @@ impl Chunk {
 
         Some(self.entities_on_blocks[ux + uz * CHUNK_SIZE].iter())
     }
+}
 
-    fn calculate_ao_level(neighbors: VertexNeighbors) -> i32 {
-        if neighbors.side1 && neighbors.side2 {
-            return 0;
-        }
-
-        let mut occupied = 0;
-
-        if neighbors.side1 {
-            occupied += 1;
-        }
+// How brightly lit a block with a light level is, from 0 to 1.
+fn light_brightness(level: u8) -> f32 {
+    AMBIENT_LIGHT + (1.0 - AMBIENT_LIGHT) * level as f32 / MAX_LIGHT as f32
+}
 
-        if neighbors.side2 {
-            occupied += 1;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every column on the ground layer that can be walked to from the start, going through
+    // unlocked doors and the ones that have keys.
+    fn reachable_columns(chunk: &Chunk, start: usize, keys: &[KeyKind]) -> [bool; CHUNK_AREA] {
+        let is_open = |i: usize| match chunk.block_at(
+            (i % CHUNK_SIZE) as i32,
+            GROUND_LAYER,
+            (i / CHUNK_SIZE) as i32,
+        ) {
+            Block::Air => true,
+            Block::Wall => false,
+            Block::Door(door) => door.lock.map_or(true, |k| keys.contains(&k)),
+        };
+
+        let mut reachable = [false; CHUNK_AREA];
+        let mut frontier = VecDeque::from([start]);
+        reachable[start] = true;
+
+        while let Some(i) = frontier.pop_front() {
+            for next in Chunk::column_neighbors(i).into_iter().flatten() {
+                if !reachable[next] && is_open(next) {
+                    reachable[next] = true;
+                    frontier.push_back(next);
+                }
+            }
         }
 
-        if neighbors.corner {
-            occupied += 1;
-        }
+        reachable
+    }
 
-        3 - occupied
+    fn item_column(position: cgmath::Vector3<f32>) -> usize {
+        let block = round_vec_to_i32(position / BLOCK_SIZE_F);
+        Chunk::column_index(block.x, block.z).unwrap()
     }
 
-    fn check_vertex_neighbors(
-        &self,
-        world_position: [i32; 3],
-        vertex_position: [i32; 3],
-        direction: Direction,
-    ) -> VertexNeighbors {
-        let direction_corner = [
-            vertex_position[0] * 2 - 1,
-            vertex_position[1] * 2 - 1,
-            vertex_position[2] * 2 - 1,
-        ];
-
-        let outward_component = dir_outward_component(direction);
-        let mut direction_side1 = direction_corner;
-        direction_side1[((outward_component + 2) % 3) as usize] = 0;
-        let mut direction_side2 = direction_corner;
-        direction_side2[((outward_component + 1) % 3) as usize] = 0;
-
-        let side1_position = [
-            world_position[0] + direction_side1[0],
-            world_position[1] + direction_side1[1],
-            world_position[2] + direction_side1[2],
-        ];
-        let side2_position = [
-            world_position[0] + direction_side2[0],
-            world_position[1] + direction_side2[1],
-            world_position[2] + direction_side2[2],
-        ];
-        let corner_position = [
-            world_position[0] + direction_corner[0],
-            world_position[1] + direction_corner[1],
-            world_position[2] + direction_corner[2],
-        ];
-
-        VertexNeighbors {
-            side1: self.get_block(side1_position[0], side1_position[1], side1_position[2]),
-            side2: self.get_block(side2_position[0], side2_position[1], side2_position[2]),
-            corner: self.get_block(corner_position[0], corner_position[1], corner_position[2]),
-        }
-    }
-
-    fn orient_last_face(&mut self) {
-        let face_start = self.vertices.len() - 4;
-        let v0 = self.vertices[face_start];
-        let v1 = self.vertices[face_start + 1];
-        let v2 = self.vertices[face_start + 2];
-        let v3 = self.vertices[face_start + 3];
-
-        if self.ao_buffer[0] + self.ao_buffer[2] > self.ao_buffer[1] + self.ao_buffer[3] {
-            return;
-        }
+    #[test]
+    fn every_vault_can_be_unlocked_in_order() {
+        for seed in 1..=200 {
+            let mut rng = Rng::new(seed);
+            let mut chunk = Chunk::new();
+            let items = chunk.generate_blocks(&mut rng);
+            let start = item_column(chunk.get_spawn_position(&mut rng).unwrap());
+
+            let key_items = items
+                .iter()
+                .filter_map(|(kind, position)| match kind {
+                    ItemKind::Key(key) => Some((*key, *position)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(key_items.len(), chunk.vaults.len(), "seed {}", seed);
+
+            // Each key has to be reachable with the keys before it, then opens its own door.
+            let mut keys = Vec::new();
+            for (key, position) in key_items {
+                let reachable = reachable_columns(&chunk, start, &keys);
+                assert!(reachable[item_column(position)], "seed {}", seed);
+
+                let vault = chunk
+                    .vaults
+                    .iter()
+                    .find(|v| {
+                        chunk.block_at(v.door.x, v.door.y, v.door.z)
+                            == Block::Door(Door {
+                                is_open: false,
+                                lock: Some(key),
+                            })
+                    })
+                    .unwrap();
+                let door_front = Chunk::column_index(vault.door_front.x, vault.door_front.z);
+                assert!(reachable[door_front.unwrap()], "seed {}", seed);
+
+                keys.push(key);
+            }
 
-        self.vertices[face_start] = v3;
-        self.vertices[face_start + 1] = v0;
-        self.vertices[face_start + 2] = v1;
-        self.vertices[face_start + 3] = v2;
+            // With every key, the reward in the last vault can be reached.
+            let reachable = reachable_columns(&chunk, start, &keys);
+            assert!(
+                items.iter().all(|(_, p)| reachable[item_column(*p)]),
+                "seed {}",
+                seed
+            );
+        }
     }
 }
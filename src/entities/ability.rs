@@ -0,0 +1,383 @@
+use cgmath::prelude::*;
+use std::{
+    borrow::{Borrow, BorrowMut},
+    collections::HashSet,
+};
+
+use winit::event::VirtualKeyCode;
+
+use crate::{
+    chunk::{Chunk, BLOCK_SIZE_F},
+    gfx::{
+        camera::{get_look_direction, Camera},
+        gui::Gui,
+    },
+    input::Input,
+};
+
+use super::{
+    actor::Actor,
+    ai::Ai,
+    damage::{DamageInfo, DamageType},
+    ecs::{CommandQueue, ComponentStore, Ecs, System},
+    health::Health,
+    perception::{Noise, ATTACK_NOISE},
+    player::Player,
+    projectile::{Projectile, ProjectileKind},
+};
+
+// Keys for casting the player's abilities, in the order that the abilities were given.
+pub const ABILITY_KEYS: [VirtualKeyCode; 4] = [
+    VirtualKeyCode::Q,
+    VirtualKeyCode::E,
+    VirtualKeyCode::F,
+    VirtualKeyCode::G,
+];
+pub const ABILITY_KEY_NAMES: [char; 4] = ['Q', 'E', 'F', 'G'];
+
+const BLAST_RADIUS: f32 = BLOCK_SIZE_F * 2.0;
+const BLAST_DAMAGE: i32 = 30;
+const BLAST_KNOCKBACK: f32 = 12.0;
+const BLINK_RANGE: f32 = BLOCK_SIZE_F * 4.0;
+const HEAL_AMOUNT: i32 = 30;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum AbilityKind {
+    Bolt,
+    Blast,
+    Blink,
+    Heal,
+}
+
+impl AbilityKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AbilityKind::Bolt => "Bolt",
+            AbilityKind::Blast => "Blast",
+            AbilityKind::Blink => "Blink",
+            AbilityKind::Heal => "Heal",
+        }
+    }
+
+    pub fn mana_cost(&self) -> f32 {
+        match self {
+            AbilityKind::Bolt => 10.0,
+            AbilityKind::Blast => 25.0,
+            AbilityKind::Blink => 15.0,
+            AbilityKind::Heal => 20.0,
+        }
+    }
+
+    pub fn cooldown(&self) -> f32 {
+        match self {
+            AbilityKind::Bolt => 1.0,
+            AbilityKind::Blast => 5.0,
+            AbilityKind::Blink => 3.0,
+            AbilityKind::Heal => 8.0,
+        }
+    }
+}
+
+pub struct Mana {
+    amount: f32,
+    max_amount: f32,
+    // Mana regained per second.
+    regeneration: f32,
+}
+
+impl Mana {
+    pub fn new(max_amount: f32, regeneration: f32) -> Self {
+        Self {
+            amount: max_amount,
+            max_amount,
+            regeneration,
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.amount = (self.amount + self.regeneration * delta_time).min(self.max_amount);
+    }
+
+    // Returns false without spending anything if there isn't enough mana.
+    pub fn spend(&mut self, cost: f32) -> bool {
+        if self.amount < cost {
+            return false;
+        }
+
+        self.amount -= cost;
+        true
+    }
+
+    pub fn amount(&self) -> f32 {
+        self.amount
+    }
+
+    pub fn max_amount(&self) -> f32 {
+        self.max_amount
+    }
+}
+
+pub struct Abilities {
+    abilities: Vec<AbilityKind>,
+    cooldown_timers: Vec<f32>,
+}
+
+impl Abilities {
+    pub fn new(abilities: Vec<AbilityKind>) -> Self {
+        let cooldown_timers = vec![0.0; abilities.len()];
+
+        Self {
+            abilities,
+            cooldown_timers,
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        for cooldown_timer in &mut self.cooldown_timers {
+            *cooldown_timer = (*cooldown_timer - delta_time).max(0.0);
+        }
+    }
+
+    pub fn is_ready(&self, kind: AbilityKind) -> bool {
+        self.index_of(kind)
+            .is_some_and(|i| self.cooldown_timers[i] <= 0.0)
+    }
+
+    pub fn abilities(&self) -> &Vec<AbilityKind> {
+        &self.abilities
+    }
+
+    pub fn cooldown_timer(&self, index: usize) -> f32 {
+        self.cooldown_timers[index]
+    }
+
+    fn start_cooldown(&mut self, kind: AbilityKind) {
+        if let Some(i) = self.index_of(kind) {
+            self.cooldown_timers[i] = kind.cooldown();
+        }
+    }
+
+    fn index_of(&self, kind: AbilityKind) -> Option<usize> {
+        self.abilities.iter().position(|a| *a == kind)
+    }
+}
+
+pub struct AbilitySystem {
+    nearby_entities: HashSet<usize>,
+}
+
+impl AbilitySystem {
+    pub fn new() -> Self {
+        Self {
+            nearby_entities: HashSet::new(),
+        }
+    }
+
+    // Damage and push away everything with health around the caster that isn't behind a wall.
+    fn blast(
+        &mut self,
+        entity: usize,
+        chunk: &Chunk,
+        queue: &mut CommandQueue,
+        actors: &ComponentStore<Actor>,
+        healths: &ComponentStore<Health>,
+    ) {
+        let actor = actors.get(entity).unwrap();
+        let position = actor.position();
+
+        actor.get_entities_in_range(chunk, BLAST_RADIUS, &mut self.nearby_entities);
+
+        for nearby_entity in &self.nearby_entities {
+            if *nearby_entity == entity || !healths.has(*nearby_entity) {
+                continue;
+            }
+
+            let offset = match actors.get(*nearby_entity) {
+                Some(a) => a.position() - position,
+                None => continue,
+            };
+            let distance = offset.magnitude();
+
+            if distance > BLAST_RADIUS || distance <= f32::EPSILON {
+                continue;
+            }
+
+            let block_distance = distance / BLOCK_SIZE_F;
+            if let Some(hit) = chunk.raycast(
+                position / BLOCK_SIZE_F,
+                offset / distance,
+                block_distance,
+                None,
+            ) {
+                if hit.distance < block_distance {
+                    continue;
+                }
+            }
+
+            let mut damage = DamageInfo::new(Some(entity), BLAST_DAMAGE, DamageType::Magic);
+            damage.knockback = offset / distance * BLAST_KNOCKBACK;
+            queue.deal_damage(*nearby_entity, damage);
+        }
+    }
+
+    // Teleport as far as possible along a direction, stopping short of walls.
+    // Returns false if there was nowhere to go.
+    fn blink(
+        entity: usize,
+        dir: cgmath::Vector3<f32>,
+        chunk: &mut Chunk,
+        actor: &mut Actor,
+    ) -> bool {
+        let dir = cgmath::vec3(dir.x, 0.0, dir.z);
+
+        if dir.magnitude() <= f32::EPSILON {
+            return false;
+        }
+
+        let dir = dir.normalize();
+        let position = actor.position();
+
+        let wall_distance = chunk
+            .raycast(
+                position / BLOCK_SIZE_F,
+                dir,
+                BLINK_RANGE / BLOCK_SIZE_F,
+                None,
+            )
+            .map(|hit| hit.distance * BLOCK_SIZE_F)
+            .unwrap_or(BLINK_RANGE);
+        let distance = wall_distance.min(BLINK_RANGE) - actor.size().x;
+
+        if distance <= 0.0 {
+            return false;
+        }
+
+        let new_position = position + dir * distance;
+
+        if chunk
+            .get_block_collision(new_position, actor.size())
+            .is_some()
+        {
+            return false;
+        }
+
+        actor.teleport(entity, new_position, chunk);
+
+        true
+    }
+}
+
+impl System for AbilitySystem {
+    fn update(
+        &mut self,
+        ecs: &mut Ecs,
+        chunk: &mut Chunk,
+        input: &mut Input,
+        _gui: &mut Gui,
+        delta_time: f32,
+    ) {
+        let Ecs {
+            manager,
+            entity_cache,
+            queue,
+        } = ecs;
+
+        if !manager.get_entities_with_both::<Abilities, Actor>(entity_cache) {
+            return;
+        }
+
+        let mut abilities = manager.borrow_components::<Abilities>().unwrap();
+        let mut actors = manager.borrow_components::<Actor>().unwrap();
+        let mut manas = manager.borrow_components::<Mana>();
+        let mut healths = manager.borrow_components::<Health>();
+        let players = manager.borrow_components::<Player>();
+        let ais = manager.borrow_components::<Ai>();
+
+        for entity in entity_cache {
+            let abilities = abilities.borrow_mut().get_mut(*entity).unwrap();
+            let mut mana = manas.as_mut().and_then(|m| m.get_mut(*entity));
+
+            abilities.update(delta_time);
+
+            if let Some(ref mut mana) = mana {
+                mana.update(delta_time);
+            }
+
+            // Players cast with hotkeys, ais when their behaviour asks to.
+            let ai = ais.as_ref().and_then(|ais| ais.get(*entity));
+            let kind = if players.as_ref().is_some_and(|p| p.has(*entity)) {
+                abilities
+                    .abilities()
+                    .iter()
+                    .zip(ABILITY_KEYS)
+                    .find(|(_, key)| input.was_key_pressed(*key))
+                    .map(|(kind, _)| *kind)
+            } else {
+                ai.and_then(|ai| ai.ability_request())
+            };
+
+            let kind = match kind {
+                Some(k) if abilities.is_ready(k) => k,
+                _ => continue,
+            };
+
+            let mana = match mana {
+                Some(m) if m.amount() >= kind.mana_cost() => m,
+                _ => continue,
+            };
+
+            let actor = actors.borrow().get(*entity).unwrap();
+            let position = actor.position();
+
+            // Ais aim at their target, players along their view.
+            let target = ai.and_then(|ai| ai.ability_target());
+            let dir = match target {
+                Some(target) => target - actor.head_position(),
+                None => get_look_direction(actor.look_x(), actor.look_y()),
+            };
+
+            let was_cast = match kind {
+                AbilityKind::Bolt => {
+                    queue.spawn_projectile(
+                        Projectile::new(*entity, ProjectileKind::Bolt, dir),
+                        actor.head_position(),
+                    );
+                    true
+                }
+                AbilityKind::Blast => match healths {
+                    Some(ref healths) => {
+                        self.blast(*entity, chunk, queue, &actors, healths);
+                        true
+                    }
+                    None => false,
+                },
+                AbilityKind::Blink => {
+                    // Ais blink away from their target to keep their distance.
+                    let dir = match target {
+                        Some(_) => -dir,
+                        None => Camera::get_direction_vec(actor.look_y()),
+                    };
+
+                    let actor = actors.borrow_mut().get_mut(*entity).unwrap();
+                    Self::blink(*entity, dir, chunk, actor)
+                }
+                AbilityKind::Heal => healths
+                    .as_mut()
+                    .and_then(|h| h.get_mut(*entity))
+                    .is_some_and(|h| h.heal(HEAL_AMOUNT) > 0),
+            };
+
+            if !was_cast {
+                continue;
+            }
+
+            mana.spend(kind.mana_cost());
+            abilities.start_cooldown(kind);
+            queue.make_noise(Noise {
+                source: Some(*entity),
+                position,
+                loudness: ATTACK_NOISE,
+            });
+        }
+    }
+}
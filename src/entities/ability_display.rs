@@ -0,0 +1,64 @@
+use std::borrow::Borrow;
+
+use crate::{chunk::Chunk, gfx::gui::Gui, input::Input};
+
+use super::{
+    ability::{Abilities, Mana, ABILITY_KEY_NAMES},
+    ecs::{Ecs, System},
+};
+
+pub struct AbilityDisplay {}
+
+pub struct AbilityDisplaySystem {}
+
+impl System for AbilityDisplaySystem {
+    fn update(
+        &mut self,
+        ecs: &mut Ecs,
+        _chunk: &mut Chunk,
+        _input: &mut Input,
+        gui: &mut Gui,
+        _delta_time: f32,
+    ) {
+        let Ecs {
+            manager,
+            entity_cache,
+            ..
+        } = ecs;
+
+        if !manager.get_entities_with_both::<Abilities, AbilityDisplay>(entity_cache) {
+            return;
+        }
+
+        let abilities = manager.borrow_components::<Abilities>().unwrap();
+        let manas = manager.borrow_components::<Mana>();
+
+        for entity in entity_cache {
+            let abilities = abilities.borrow().get(*entity).unwrap();
+
+            for (i, kind) in abilities.abilities().iter().enumerate().rev() {
+                let key = ABILITY_KEY_NAMES.get(i).copied().unwrap_or(' ');
+                let cooldown_timer = abilities.cooldown_timer(i);
+
+                if cooldown_timer > 0.0 {
+                    gui.write(&format!(
+                        "{}: {} ({:.1}s)",
+                        key,
+                        kind.name(),
+                        cooldown_timer
+                    ));
+                } else {
+                    gui.write(&format!("{}: {}", key, kind.name()));
+                }
+            }
+
+            if let Some(mana) = manas.as_ref().and_then(|m| m.get(*entity)) {
+                gui.write(&format!(
+                    "Mana: {}/{}",
+                    mana.amount().floor(),
+                    mana.max_amount()
+                ));
+            }
+        }
+    }
+}
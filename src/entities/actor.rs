@@ -1,3 +1,4 @@
+use cgmath::prelude::*;
 use std::{borrow::BorrowMut, collections::HashSet};
 
 use crate::{
@@ -8,18 +9,23 @@ use crate::{
 
 use super::ecs::{Ecs, System};
 
-const GRAVITY: f32 = 30.0;
+pub const GRAVITY: f32 = 30.0;
 const JUMP_FORCE: f32 = 9.0;
 const GROUNDED_DISTANCE: f32 = 0.1;
+const KNOCKBACK_FRICTION: f32 = 8.0;
+const MIN_KNOCKBACK_SPEED: f32 = 0.1;
 
 pub struct Actor {
     speed: f32,
+    speed_multiplier: f32,
     size: cgmath::Vector3<f32>,
     position: cgmath::Vector3<f32>,
     look_x: f32,
     look_y: f32,
     y_velocity: f32,
+    knockback_velocity: cgmath::Vector3<f32>,
     grounded: bool,
+    has_gravity: bool,
 }
 
 impl Actor {
@@ -28,10 +34,13 @@ impl Actor {
             position,
             size,
             speed,
+            speed_multiplier: 1.0,
             look_x: 0.0,
             look_y: 0.0,
             y_velocity: 0.0,
+            knockback_velocity: cgmath::Vector3::zero(),
             grounded: false,
+            has_gravity: true,
         }
     }
 
@@ -97,13 +106,80 @@ impl Actor {
         }
     }
 
+    pub fn get_entities_in_range(
+        &self,
+        chunk: &Chunk,
+        range: f32,
+        nearby_entities: &mut HashSet<usize>,
+    ) {
+        nearby_entities.clear();
+
+        let range = cgmath::vec3(range, 0.0, range);
+        let min_block_position = (self.position - range).cast::<i32>().unwrap() / BLOCK_SIZE;
+        let max_block_position = (self.position + range).cast::<i32>().unwrap() / BLOCK_SIZE;
+
+        for z in min_block_position.z..=max_block_position.z {
+            for x in min_block_position.x..=max_block_position.x {
+                if let Some(entities_at_block) = chunk.entities_at_block(x, z) {
+                    nearby_entities.extend(entities_at_block);
+                }
+            }
+        }
+    }
+
     pub fn rotate(&mut self, delta_x: f32, delta_y: f32) {
         self.look_x += delta_x;
         self.look_y += delta_y;
         self.look_x = self.look_x.clamp(-89.0, 89.0);
     }
 
-    pub fn teleport(&mut self, position: cgmath::Vector3<f32>) {
+    // Turn to face along a direction on the xz plane.
+    pub fn face(&mut self, dir: cgmath::Vector3<f32>) {
+        if dir.x == 0.0 && dir.z == 0.0 {
+            return;
+        }
+
+        self.look_y = dir.x.atan2(dir.z).to_degrees();
+    }
+
+    // Push the actor along the xz plane, the push slows down over time due to friction.
+    pub fn apply_impulse(&mut self, impulse: cgmath::Vector3<f32>) {
+        self.knockback_velocity += cgmath::vec3(impulse.x, 0.0, impulse.z);
+    }
+
+    fn apply_knockback(&mut self, entity: usize, chunk: &mut Chunk, delta_time: f32) {
+        if self.knockback_velocity.magnitude() < MIN_KNOCKBACK_SPEED {
+            self.knockback_velocity = cgmath::Vector3::zero();
+            return;
+        }
+
+        // Move along each axis separately so that knockback slides along walls.
+        let velocity = self.knockback_velocity;
+        if !self.step(
+            entity,
+            cgmath::Vector3::unit_x(),
+            velocity.x * delta_time,
+            chunk,
+            false,
+        ) {
+            self.knockback_velocity.x = 0.0;
+        }
+
+        if !self.step(
+            entity,
+            cgmath::Vector3::unit_z(),
+            velocity.z * delta_time,
+            chunk,
+            false,
+        ) {
+            self.knockback_velocity.z = 0.0;
+        }
+
+        self.knockback_velocity *= (1.0 - KNOCKBACK_FRICTION * delta_time).max(0.0);
+    }
+
+    pub fn teleport(&mut self, entity: usize, position: cgmath::Vector3<f32>, chunk: &mut Chunk) {
+        self.update_occupied_blocks(entity, chunk, Some(position));
         self.position = position;
     }
 
@@ -150,6 +226,14 @@ impl Actor {
             && max.z >= other_min.z
     }
 
+    pub fn set_speed_multiplier(&mut self, speed_multiplier: f32) {
+        self.speed_multiplier = speed_multiplier;
+    }
+
+    pub fn set_gravity(&mut self, has_gravity: bool) {
+        self.has_gravity = has_gravity;
+    }
+
     pub fn jump(&mut self) {
         self.y_velocity = JUMP_FORCE;
     }
@@ -179,7 +263,7 @@ impl Actor {
     }
 
     pub fn speed(&self) -> f32 {
-        self.speed
+        self.speed * self.speed_multiplier
     }
 
     pub fn grounded(&self) -> bool {
@@ -213,6 +297,12 @@ impl System for ActorSystem {
         for entity in entity_cache {
             let actor = actors.borrow_mut().get_mut(*entity).unwrap();
 
+            actor.apply_knockback(*entity, chunk, delta_time);
+
+            if !actor.has_gravity {
+                continue;
+            }
+
             actor.grounded = chunk
                 .get_block_collision(
                     actor.position - cgmath::vec3(0.0, GROUNDED_DISTANCE, 0.0),
@@ -0,0 +1,289 @@
+use cgmath::prelude::*;
+use std::{
+    borrow::{Borrow, BorrowMut},
+    collections::HashSet,
+};
+
+use winit::event::VirtualKeyCode;
+
+use crate::{
+    chunk::Chunk,
+    gfx::gui::Gui,
+    input::Input,
+    rng::Rng,
+    steering::{is_blocked, steer, Neighbor},
+};
+
+use super::{
+    ability::{Abilities, AbilityKind, Mana},
+    actor::Actor,
+    behaviour::{AiContext, Behaviour, Facing},
+    ecs::{Ecs, System},
+    health::Health,
+    perception::Perception,
+    player::Player,
+};
+
+const NEIGHBOR_RANGE: f32 = 3.0;
+
+// Everything the behaviour tree remembers between frames.
+pub struct AiState {
+    pub home: cgmath::Vector3<f32>,
+    pub active_state: &'static str,
+    pub path: Vec<cgmath::Vector3<f32>>,
+    pub next: Option<cgmath::Vector3<f32>>,
+    pub path_goal: Option<cgmath::Vector3<i32>>,
+    pub repath_timer: f32,
+    pub path_revision: u32,
+    pub search_timer: f32,
+    pub wait_timer: f32,
+    pub wander_target: Option<cgmath::Vector3<f32>>,
+    pub patrol_index: usize,
+    pub desired_velocity: cgmath::Vector3<f32>,
+    pub velocity: cgmath::Vector3<f32>,
+    pub facing: Facing,
+    pub attack_target: Option<cgmath::Vector3<f32>>,
+    pub ability_request: Option<AbilityKind>,
+    pub ability_target: Option<cgmath::Vector3<f32>>,
+}
+
+pub struct Ai {
+    behaviour: Behaviour,
+    state: AiState,
+}
+
+impl Ai {
+    pub fn new(behaviour: Behaviour, home: cgmath::Vector3<f32>) -> Self {
+        Self {
+            behaviour,
+            state: AiState {
+                home,
+                active_state: "None",
+                path: Vec::new(),
+                next: None,
+                path_goal: None,
+                repath_timer: 0.0,
+                path_revision: 0,
+                search_timer: 0.0,
+                wait_timer: 0.0,
+                wander_target: None,
+                patrol_index: 0,
+                desired_velocity: cgmath::Vector3::zero(),
+                velocity: cgmath::Vector3::zero(),
+                facing: Facing::Velocity,
+                attack_target: None,
+                ability_request: None,
+                ability_target: None,
+            },
+        }
+    }
+
+    pub fn active_state(&self) -> &'static str {
+        self.state.active_state
+    }
+
+    pub fn velocity(&self) -> cgmath::Vector3<f32> {
+        self.state.velocity
+    }
+
+    pub fn next_waypoint(&self) -> Option<cgmath::Vector3<f32>> {
+        self.state.next
+    }
+
+    pub fn attack_target(&self) -> Option<cgmath::Vector3<f32>> {
+        self.state.attack_target
+    }
+
+    pub fn ability_request(&self) -> Option<AbilityKind> {
+        self.state.ability_request
+    }
+
+    pub fn ability_target(&self) -> Option<cgmath::Vector3<f32>> {
+        self.state.ability_target
+    }
+}
+
+pub struct AiSystem {
+    rng: Rng,
+    nearby_entities: HashSet<usize>,
+    neighbors: Vec<Neighbor>,
+    show_debug: bool,
+}
+
+impl AiSystem {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            nearby_entities: HashSet::new(),
+            neighbors: Vec::new(),
+            show_debug: false,
+        }
+    }
+}
+
+impl System for AiSystem {
+    fn update(
+        &mut self,
+        ecs: &mut Ecs,
+        chunk: &mut Chunk,
+        input: &mut Input,
+        gui: &mut Gui,
+        delta_time: f32,
+    ) {
+        let Ecs {
+            manager,
+            entity_cache,
+            ..
+        } = ecs;
+
+        if input.was_key_pressed(VirtualKeyCode::F3) {
+            self.show_debug = !self.show_debug;
+        }
+
+        manager.get_entities_with_both::<Player, Actor>(entity_cache);
+
+        let player = match entity_cache.first() {
+            Some(p) => *p,
+            None => return,
+        };
+
+        let player_position = manager
+            .borrow_components::<Actor>()
+            .unwrap()
+            .borrow()
+            .get(player)
+            .unwrap()
+            .position();
+
+        if !manager.get_entities_with_both::<Ai, Actor>(entity_cache) {
+            return;
+        }
+
+        let mut ais = manager.borrow_components::<Ai>().unwrap();
+        let mut actors = manager.borrow_components::<Actor>().unwrap();
+        let mut perceptions = manager.borrow_components::<Perception>();
+        let healths = manager.borrow_components::<Health>();
+        let abilities = manager.borrow_components::<Abilities>();
+        let manas = manager.borrow_components::<Mana>();
+
+        for entity in entity_cache {
+            let actor = actors.borrow().get(*entity).unwrap();
+            let position = actor.position();
+            let radius = actor.size().x * 0.5;
+            let speed = actor.speed();
+
+            let ai = ais.borrow_mut().get_mut(*entity).unwrap();
+
+            ai.state.desired_velocity = cgmath::Vector3::zero();
+            ai.state.attack_target = None;
+            ai.state.ability_request = None;
+            ai.state.ability_target = None;
+            ai.state.facing = Facing::Velocity;
+
+            let mut context = AiContext {
+                state: &mut ai.state,
+                chunk,
+                rng: &mut self.rng,
+                perception: perceptions.as_mut().and_then(|p| p.get_mut(*entity)),
+                position,
+                speed,
+                health: healths
+                    .as_ref()
+                    .and_then(|h| h.get(*entity))
+                    .map(|h| h.amount()),
+                abilities: abilities.as_ref().and_then(|a| a.get(*entity)),
+                mana: manas
+                    .as_ref()
+                    .and_then(|m| m.get(*entity))
+                    .map(|m| m.amount()),
+                player_position,
+                delta_time,
+            };
+
+            ai.behaviour.tick(&mut context);
+
+            let desired_velocity = ai.state.desired_velocity;
+            let facing = ai.state.facing;
+
+            if self.show_debug {
+                gui.write(&format!("Ai {}: {}", entity, ai.active_state()));
+            }
+
+            let target_position = match perceptions.as_ref().and_then(|p| p.get(*entity)) {
+                Some(perception) => perception.last_known_position(),
+                None => Some(player_position),
+            };
+
+            // Other living actors are obstacles that should be steered around.
+            actor.get_entities_in_range(chunk, NEIGHBOR_RANGE, &mut self.nearby_entities);
+            self.neighbors.clear();
+
+            for nearby_entity in &self.nearby_entities {
+                if *nearby_entity == *entity {
+                    continue;
+                }
+
+                match healths {
+                    Some(ref healths) if healths.has(*nearby_entity) => {}
+                    _ => continue,
+                }
+
+                let nearby_actor = match actors.borrow().get(*nearby_entity) {
+                    Some(a) => a,
+                    None => continue,
+                };
+
+                let (velocity, is_agent) = match ais.borrow().get(*nearby_entity) {
+                    Some(nearby_ai) => (nearby_ai.velocity(), true),
+                    None => (cgmath::Vector3::zero(), false),
+                };
+
+                self.neighbors.push(Neighbor {
+                    entity: *nearby_entity,
+                    position: nearby_actor.position(),
+                    velocity,
+                    radius: nearby_actor.size().x * 0.5,
+                    is_agent,
+                });
+            }
+
+            let velocity = steer(
+                *entity,
+                position,
+                desired_velocity,
+                radius,
+                speed,
+                &self.neighbors,
+            );
+
+            ais.borrow_mut().get_mut(*entity).unwrap().state.velocity = velocity;
+
+            let actor = actors.borrow_mut().get_mut(*entity).unwrap();
+
+            match facing {
+                Facing::Velocity => actor.face(velocity),
+                Facing::Turn(turn_speed) => actor.rotate(0.0, turn_speed * delta_time),
+                Facing::Target => {
+                    if let Some(target_position) = target_position {
+                        actor.face(target_position - position);
+                    }
+                }
+            }
+
+            // Move along each axis separately so that the ai can slide along walls.
+            for axis_velocity in [
+                cgmath::vec3(velocity.x, 0.0, 0.0),
+                cgmath::vec3(0.0, 0.0, velocity.z),
+            ] {
+                let position = actor.position();
+                let new_position = position + axis_velocity * delta_time;
+
+                if is_blocked(position, new_position, radius, &self.neighbors) {
+                    continue;
+                }
+
+                actor.step(*entity, axis_velocity, delta_time, chunk, false);
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::borrow::Borrow;
+
+use crate::{chunk::Chunk, gfx::gui::Gui, input::Input};
+
+use super::{
+    ecs::{Ecs, System},
+    fighter::Fighter,
+    health::Health,
+};
+
+const DAMAGE_PER_STRENGTH: i32 = 3;
+const ATTACK_SPEED_PER_DEXTERITY: f32 = 0.05;
+const HEALTH_PER_VITALITY: i32 = 10;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Attribute {
+    Strength,
+    Dexterity,
+    Vitality,
+}
+
+impl Attribute {
+    pub const ALL: [Attribute; 3] = [
+        Attribute::Strength,
+        Attribute::Dexterity,
+        Attribute::Vitality,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Attribute::Strength => "Strength",
+            Attribute::Dexterity => "Dexterity",
+            Attribute::Vitality => "Vitality",
+        }
+    }
+}
+
+// Strength adds attack damage, dexterity shortens attack wind up and recovery, and vitality adds
+// max health.
+pub struct Attributes {
+    strength: u32,
+    dexterity: u32,
+    vitality: u32,
+}
+
+impl Attributes {
+    pub fn new(strength: u32, dexterity: u32, vitality: u32) -> Self {
+        Self {
+            strength,
+            dexterity,
+            vitality,
+        }
+    }
+
+    pub fn get(&self, attribute: Attribute) -> u32 {
+        match attribute {
+            Attribute::Strength => self.strength,
+            Attribute::Dexterity => self.dexterity,
+            Attribute::Vitality => self.vitality,
+        }
+    }
+
+    pub fn increase(&mut self, attribute: Attribute) {
+        match attribute {
+            Attribute::Strength => self.strength += 1,
+            Attribute::Dexterity => self.dexterity += 1,
+            Attribute::Vitality => self.vitality += 1,
+        }
+    }
+
+    pub fn bonus_damage(&self) -> i32 {
+        self.strength as i32 * DAMAGE_PER_STRENGTH
+    }
+
+    pub fn bonus_attack_speed(&self) -> f32 {
+        1.0 + self.dexterity as f32 * ATTACK_SPEED_PER_DEXTERITY
+    }
+
+    pub fn bonus_health(&self) -> i32 {
+        self.vitality as i32 * HEALTH_PER_VITALITY
+    }
+}
+
+pub struct AttributesSystem {}
+
+impl System for AttributesSystem {
+    fn update(
+        &mut self,
+        ecs: &mut Ecs,
+        _chunk: &mut Chunk,
+        _input: &mut Input,
+        _gui: &mut Gui,
+        _delta_time: f32,
+    ) {
+        let Ecs {
+            manager,
+            entity_cache,
+            ..
+        } = ecs;
+
+        if !manager.get_entities_with::<Attributes>(entity_cache) {
+            return;
+        }
+
+        let attributes = manager.borrow_components::<Attributes>().unwrap();
+        let mut fighters = manager.borrow_components::<Fighter>();
+        let mut healths = manager.borrow_components::<Health>();
+
+        for entity in entity_cache {
+            let attributes = attributes.borrow().get(*entity).unwrap();
+
+            if let Some(fighter) = fighters.as_mut().and_then(|f| f.get_mut(*entity)) {
+                fighter.set_attribute_bonuses(
+                    attributes.bonus_damage(),
+                    attributes.bonus_attack_speed(),
+                );
+            }
+
+            if let Some(health) = healths.as_mut().and_then(|h| h.get_mut(*entity)) {
+                health.set_bonus_max_amount(attributes.bonus_health());
+            }
+        }
+    }
+}
@@ -0,0 +1,454 @@
+use cgmath::prelude::*;
+use std::collections::HashMap;
+
+use crate::{
+    a_star::{a_star_search, reconstruct_path},
+    chunk::{Chunk, BLOCK_SIZE, BLOCK_SIZE_F},
+    rng::Rng,
+};
+
+use super::{
+    ability::{Abilities, AbilityKind},
+    ai::AiState,
+    perception::Perception,
+};
+
+const REPATH_TIME: f32 = 1.0;
+const WAYPOINT_DISTANCE: f32 = 0.5;
+const ARRIVE_DISTANCE: f32 = BLOCK_SIZE_F * 0.5;
+const SEARCH_TIME: f32 = 4.0;
+const WANDER_WAIT_TIME: f32 = 2.0;
+const WANDER_SPEED_SCALE: f32 = 0.5;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Status {
+    Success,
+    Failure,
+    Running,
+}
+
+// How the ai wants to turn this frame, applied after movement has been decided.
+#[derive(Copy, Clone)]
+pub enum Facing {
+    Velocity,
+    Target,
+    Turn(f32),
+}
+
+// Nodes of a behaviour tree. The tree is evaluated from the root every frame, so composites
+// don't need to remember which child was running, and higher priority branches can interrupt
+// lower priority ones as soon as their conditions are met.
+#[derive(Clone)]
+pub enum Behaviour {
+    // Run children in order until one of them doesn't fail.
+    Selector(Vec<Behaviour>),
+    // Run children in order until one of them doesn't succeed.
+    Sequence(Vec<Behaviour>),
+
+    CanSeeTarget,
+    TargetWithin(f32),
+    HealthBelow(i32),
+    AwayFromHome(f32),
+
+    Idle,
+    Wander(f32),
+    // Waypoints are offsets from the ai's home position.
+    Patrol(Vec<cgmath::Vector3<f32>>),
+    Chase,
+    Attack(f32),
+    // Keep between a minimum and maximum distance from the target while shooting at it.
+    RangedAttack(f32, f32),
+    Flee,
+    ReturnHome,
+    Search,
+    // Ask for an ability to be cast at the target, fails if it isn't ready or affordable.
+    UseAbility(AbilityKind),
+}
+
+pub struct AiContext<'a> {
+    pub state: &'a mut AiState,
+    pub chunk: &'a Chunk,
+    pub rng: &'a mut Rng,
+    pub perception: Option<&'a mut Perception>,
+    pub position: cgmath::Vector3<f32>,
+    pub speed: f32,
+    pub health: Option<i32>,
+    pub abilities: Option<&'a Abilities>,
+    pub mana: Option<f32>,
+    // Ais without perception always know where the player is.
+    pub player_position: cgmath::Vector3<f32>,
+    pub delta_time: f32,
+}
+
+impl<'a> AiContext<'a> {
+    fn target_position(&self) -> Option<cgmath::Vector3<f32>> {
+        match self.perception {
+            Some(ref perception) => perception.last_known_position(),
+            None => Some(self.player_position),
+        }
+    }
+
+    fn can_see_target(&self) -> bool {
+        match self.perception {
+            Some(ref perception) => perception.can_see_target(),
+            None => true,
+        }
+    }
+
+    fn flat_offset_to(&self, position: cgmath::Vector3<f32>) -> cgmath::Vector3<f32> {
+        cgmath::vec3(
+            position.x - self.position.x,
+            0.0,
+            position.z - self.position.z,
+        )
+    }
+
+    fn move_towards(&mut self, offset: cgmath::Vector3<f32>, speed: f32) {
+        if offset.magnitude() > f32::EPSILON {
+            self.state.desired_velocity = offset.normalize_to(speed);
+        }
+    }
+
+    // Whether any blocks have changed along the current path since it was found. Without a path
+    // any change might have opened one up.
+    fn is_path_invalidated(&mut self) -> bool {
+        if self.state.path_revision == self.chunk.revision() {
+            return false;
+        }
+
+        let is_invalidated = match self.chunk.edits_since(self.state.path_revision) {
+            Some(mut edits) if self.state.next.is_some() => edits.any(|edit| {
+                self.state
+                    .path
+                    .iter()
+                    .chain(self.state.next.iter())
+                    .any(|waypoint| {
+                        (waypoint / BLOCK_SIZE_F).map(|n| n.floor()) == edit.cast().unwrap()
+                    })
+            }),
+            _ => true,
+        };
+
+        self.state.path_revision = self.chunk.revision();
+
+        is_invalidated
+    }
+
+    // Follow a path to the goal, repathing periodically, when the goal moves to a different
+    // block, or when blocks along the path change. Returns true once the goal has been reached.
+    fn navigate_to(&mut self, goal: cgmath::Vector3<f32>, speed: f32) -> bool {
+        if self.flat_offset_to(goal).magnitude() < ARRIVE_DISTANCE {
+            self.state.path.clear();
+            self.state.next = None;
+            return true;
+        }
+
+        let start = self.position.cast::<i32>().unwrap();
+        let goal_i = goal.cast::<i32>().unwrap();
+        let goal_block = goal_i / BLOCK_SIZE;
+
+        self.state.repath_timer += self.delta_time;
+
+        if self.state.repath_timer > REPATH_TIME
+            || self.state.path_goal != Some(goal_block)
+            || self.is_path_invalidated()
+        {
+            self.state.repath_timer = 0.0;
+            self.state.path_goal = Some(goal_block);
+            self.state.path_revision = self.chunk.revision();
+
+            let mut came_from = HashMap::<cgmath::Vector3<i32>, cgmath::Vector3<i32>>::new();
+            a_star_search(self.chunk, start, goal_i, &mut came_from);
+            reconstruct_path(start, goal_i, &mut came_from, &mut self.state.path);
+            self.state.next = self.state.path.pop();
+        }
+
+        if let Some(next) = self.state.next {
+            let offset = self.flat_offset_to(next);
+
+            if offset.magnitude() < WAYPOINT_DISTANCE {
+                self.state.next = self.state.path.pop();
+            } else {
+                self.move_towards(offset, speed);
+            }
+        } else if start / BLOCK_SIZE == goal_block {
+            // Once the ai is on the goal's tile, run directly towards it.
+            self.move_towards(self.flat_offset_to(goal), speed);
+        }
+
+        false
+    }
+
+    fn pick_wander_target(&mut self, radius: f32) -> Option<cgmath::Vector3<f32>> {
+        let block_radius = (radius / BLOCK_SIZE_F).max(1.0) as i32;
+        let home_block = self.state.home.cast::<i32>().unwrap() / BLOCK_SIZE;
+        let range = (block_radius * 2 + 1) as u32;
+
+        let x = home_block.x + self.rng.range(range) as i32 - block_radius;
+        let z = home_block.z + self.rng.range(range) as i32 - block_radius;
+
+        if self.chunk.get_block(x, home_block.y, z) {
+            return None;
+        }
+
+        Some(cgmath::vec3(
+            (x as f32 + 0.5) * BLOCK_SIZE_F,
+            self.state.home.y,
+            (z as f32 + 0.5) * BLOCK_SIZE_F,
+        ))
+    }
+}
+
+impl Behaviour {
+    pub fn tick(&self, context: &mut AiContext) -> Status {
+        match self {
+            Behaviour::Selector(children) => {
+                for child in children {
+                    let status = child.tick(context);
+
+                    if status != Status::Failure {
+                        return status;
+                    }
+                }
+
+                Status::Failure
+            }
+            Behaviour::Sequence(children) => {
+                for child in children {
+                    let status = child.tick(context);
+
+                    if status != Status::Success {
+                        return status;
+                    }
+                }
+
+                Status::Success
+            }
+            Behaviour::CanSeeTarget => Self::check(context.can_see_target()),
+            Behaviour::TargetWithin(distance) => Self::check(
+                context
+                    .target_position()
+                    .map(|target| context.flat_offset_to(target).magnitude() <= *distance)
+                    .unwrap_or(false),
+            ),
+            Behaviour::HealthBelow(amount) => {
+                Self::check(context.health.map(|h| h < *amount).unwrap_or(false))
+            }
+            Behaviour::AwayFromHome(distance) => {
+                Self::check(context.flat_offset_to(context.state.home).magnitude() > *distance)
+            }
+            _ => {
+                let status = self.act(context);
+
+                if status != Status::Failure {
+                    context.state.active_state = self.name();
+                }
+
+                status
+            }
+        }
+    }
+
+    fn act(&self, context: &mut AiContext) -> Status {
+        match self {
+            Behaviour::Idle => Status::Running,
+            Behaviour::Wander(radius) => {
+                context.state.facing = Facing::Velocity;
+
+                if context.state.wait_timer > 0.0 {
+                    context.state.wait_timer -= context.delta_time;
+                    return Status::Running;
+                }
+
+                if context.state.wander_target.is_none() {
+                    context.state.wander_target = context.pick_wander_target(*radius);
+                }
+
+                if let Some(wander_target) = context.state.wander_target {
+                    let speed = context.speed * WANDER_SPEED_SCALE;
+
+                    if context.navigate_to(wander_target, speed) || context.state.next.is_none() {
+                        context.state.wander_target = None;
+                        context.state.wait_timer = WANDER_WAIT_TIME;
+                    }
+                }
+
+                Status::Running
+            }
+            Behaviour::Patrol(waypoints) => {
+                if waypoints.is_empty() {
+                    return Status::Failure;
+                }
+
+                context.state.facing = Facing::Velocity;
+
+                let index = context.state.patrol_index % waypoints.len();
+                let waypoint = context.state.home + waypoints[index];
+
+                // Skip waypoints that can't be reached.
+                if context.navigate_to(waypoint, context.speed * WANDER_SPEED_SCALE)
+                    || context.state.next.is_none()
+                {
+                    context.state.patrol_index = (index + 1) % waypoints.len();
+                }
+
+                Status::Running
+            }
+            Behaviour::Chase => {
+                let target = match context.target_position() {
+                    Some(t) => t,
+                    None => return Status::Failure,
+                };
+
+                context.state.facing = if context.can_see_target() {
+                    Facing::Target
+                } else {
+                    Facing::Velocity
+                };
+
+                if context.navigate_to(target, context.speed) {
+                    Status::Success
+                } else {
+                    Status::Running
+                }
+            }
+            Behaviour::Attack(range) => {
+                let target = match context.target_position() {
+                    Some(t) => t,
+                    None => return Status::Failure,
+                };
+
+                if !context.can_see_target() || context.flat_offset_to(target).magnitude() > *range
+                {
+                    return Status::Failure;
+                }
+
+                // Keep pressing into the target, steering stops the ai from overlapping it.
+                context.state.facing = Facing::Target;
+                context.state.attack_target = Some(target);
+                context.move_towards(context.flat_offset_to(target), context.speed);
+
+                Status::Running
+            }
+            Behaviour::RangedAttack(min_range, max_range) => {
+                let target = match context.target_position() {
+                    Some(t) => t,
+                    None => return Status::Failure,
+                };
+
+                let offset = context.flat_offset_to(target);
+
+                if !context.can_see_target() || offset.magnitude() > *max_range {
+                    return Status::Failure;
+                }
+
+                context.state.facing = Facing::Target;
+                context.state.attack_target = Some(target);
+
+                if offset.magnitude() < *min_range {
+                    context.move_towards(-offset, context.speed);
+                }
+
+                Status::Running
+            }
+            Behaviour::Flee => {
+                let target = match context.target_position() {
+                    Some(t) => t,
+                    None => return Status::Failure,
+                };
+
+                context.state.facing = Facing::Velocity;
+                context.move_towards(-context.flat_offset_to(target), context.speed);
+
+                Status::Running
+            }
+            Behaviour::ReturnHome => {
+                context.state.facing = Facing::Velocity;
+
+                if context.navigate_to(context.state.home, context.speed * WANDER_SPEED_SCALE) {
+                    Status::Success
+                } else {
+                    Status::Running
+                }
+            }
+            Behaviour::Search => {
+                let target = match context.target_position() {
+                    Some(t) => t,
+                    None => return Status::Failure,
+                };
+
+                if context.can_see_target() {
+                    context.state.search_timer = 0.0;
+                    return Status::Failure;
+                }
+
+                context.state.facing = Facing::Velocity;
+
+                // Search from the closest reachable point if the target position can't be reached.
+                if !context.navigate_to(target, context.speed) && context.state.next.is_some() {
+                    return Status::Running;
+                }
+
+                // The player isn't where they were last seen, look around for them.
+                context.state.facing = Facing::Turn(90.0);
+                context.state.search_timer += context.delta_time;
+
+                if context.state.search_timer < SEARCH_TIME {
+                    return Status::Running;
+                }
+
+                context.state.search_timer = 0.0;
+
+                if let Some(ref mut perception) = context.perception {
+                    perception.forget_target();
+                }
+
+                Status::Success
+            }
+            Behaviour::UseAbility(kind) => {
+                let is_ready = context.abilities.is_some_and(|a| a.is_ready(*kind));
+                let can_afford = context.mana.is_some_and(|m| m >= kind.mana_cost());
+
+                if !is_ready || !can_afford {
+                    return Status::Failure;
+                }
+
+                context.state.facing = Facing::Target;
+                context.state.ability_request = Some(*kind);
+                context.state.ability_target = context.target_position();
+
+                Status::Success
+            }
+            _ => Status::Failure,
+        }
+    }
+
+    fn check(condition: bool) -> Status {
+        if condition {
+            Status::Success
+        } else {
+            Status::Failure
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Behaviour::Selector(..) => "Selector",
+            Behaviour::Sequence(..) => "Sequence",
+            Behaviour::CanSeeTarget => "CanSeeTarget",
+            Behaviour::TargetWithin(..) => "TargetWithin",
+            Behaviour::HealthBelow(..) => "HealthBelow",
+            Behaviour::AwayFromHome(..) => "AwayFromHome",
+            Behaviour::Idle => "Idle",
+            Behaviour::Wander(..) => "Wander",
+            Behaviour::Patrol(..) => "Patrol",
+            Behaviour::Chase => "Chase",
+            Behaviour::Attack(..) => "Attack",
+            Behaviour::RangedAttack(..) => "RangedAttack",
+            Behaviour::Flee => "Flee",
+            Behaviour::ReturnHome => "ReturnHome",
+            Behaviour::Search => "Search",
+            Behaviour::UseAbility(..) => "UseAbility",
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use std::borrow::{Borrow, BorrowMut};
+
+use crate::{chunk::Chunk, gfx::gui::Gui, input::Input, rng::Rng};
+
+use super::{
+    actor::Actor,
+    ecs::{Ecs, System},
+    health::Health,
+    status_effect::{StatusEffect, StatusEffects},
+};
+
+const CRITICAL_MULTIPLIER: i32 = 2;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum DamageType {
+    Physical,
+    Fire,
+    Poison,
+    Magic,
+}
+
+impl DamageType {
+    pub const COUNT: usize = 4;
+}
+
+// A single hit on its way to an entity's health. Attacks fill in everything but whether the hit
+// was critical, which is rolled when the damage is applied.
+#[derive(Copy, Clone, Debug)]
+pub struct DamageInfo {
+    pub source: Option<usize>,
+    pub amount: i32,
+    pub damage_type: DamageType,
+    pub crit_chance: f32,
+    pub is_critical: bool,
+    pub knockback: cgmath::Vector3<f32>,
+    // Applied to the defender if the hit isn't dodged.
+    pub effect: Option<StatusEffect>,
+}
+
+impl DamageInfo {
+    pub fn new(source: Option<usize>, amount: i32, damage_type: DamageType) -> Self {
+        Self {
+            source,
+            amount,
+            damage_type,
+            crit_chance: 0.0,
+            is_critical: false,
+            knockback: cgmath::vec3(0.0, 0.0, 0.0),
+            effect: None,
+        }
+    }
+}
+
+// Reduces physical damage by a flat amount, and gives a chance to avoid hits entirely.
+pub struct Armor {
+    amount: i32,
+    dodge_chance: f32,
+}
+
+impl Armor {
+    pub fn new(amount: i32, dodge_chance: f32) -> Self {
+        Self {
+            amount,
+            dodge_chance,
+        }
+    }
+
+    pub fn amount(&self) -> i32 {
+        self.amount
+    }
+
+    pub fn dodge_chance(&self) -> f32 {
+        self.dodge_chance
+    }
+}
+
+// The fraction of each type of damage that is ignored, negative values are weaknesses.
+pub struct Resistances {
+    amounts: [f32; DamageType::COUNT],
+}
+
+impl Resistances {
+    pub fn new(resistances: &[(DamageType, f32)]) -> Self {
+        let mut amounts = [0.0; DamageType::COUNT];
+
+        for (damage_type, amount) in resistances {
+            amounts[*damage_type as usize] = *amount;
+        }
+
+        Self { amounts }
+    }
+
+    pub fn get(&self, damage_type: DamageType) -> f32 {
+        self.amounts[damage_type as usize]
+    }
+}
+
+pub struct DamageSystem {
+    rng: Rng,
+}
+
+impl DamageSystem {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            rng: Rng::new(seed),
+        }
+    }
+
+    // Run damage through the defender's modifiers, returning None if the hit was dodged.
+    pub fn resolve(
+        rng: &mut Rng,
+        mut damage: DamageInfo,
+        armor: Option<&Armor>,
+        resistances: Option<&Resistances>,
+    ) -> Option<DamageInfo> {
+        if armor.is_some_and(|a| rng.chance(a.dodge_chance())) {
+            return None;
+        }
+
+        if rng.chance(damage.crit_chance) {
+            damage.is_critical = true;
+            damage.amount *= CRITICAL_MULTIPLIER;
+        }
+
+        // Armor always lets a little damage through, but never more than the hit had.
+        if damage.damage_type == DamageType::Physical {
+            if let Some(armor) = armor {
+                damage.amount = (damage.amount - armor.amount()).max(damage.amount.min(1));
+            }
+        }
+
+        // Resistances above 1 block everything, rather than healing.
+        if let Some(resistances) = resistances {
+            let scale = (1.0 - resistances.get(damage.damage_type)).max(0.0);
+            damage.amount = (damage.amount as f32 * scale).round() as i32;
+        }
+
+        Some(damage)
+    }
+}
+
+impl System for DamageSystem {
+    fn update(
+        &mut self,
+        ecs: &mut Ecs,
+        _chunk: &mut Chunk,
+        _input: &mut Input,
+        _gui: &mut Gui,
+        _delta_time: f32,
+    ) {
+        let Ecs { manager, queue, .. } = ecs;
+
+        let mut healths = match manager.borrow_components::<Health>() {
+            Some(h) => h,
+            None => return,
+        };
+        let mut actors = manager.borrow_components::<Actor>();
+        let armors = manager.borrow_components::<Armor>();
+        let resistances = manager.borrow_components::<Resistances>();
+        let mut status_effects = manager.borrow_components::<StatusEffects>();
+
+        for (entity, damage) in queue.damages() {
+            let health = match healths.borrow_mut().get_mut(*entity) {
+                Some(h) => h,
+                None => continue,
+            };
+
+            // Damage without a source, such as poison, ticks through invulnerability.
+            let is_hit = damage.source.is_some();
+
+            if is_hit && health.is_invulnerable() {
+                continue;
+            }
+
+            let damage = match Self::resolve(
+                &mut self.rng,
+                *damage,
+                armors.as_ref().and_then(|a| a.borrow().get(*entity)),
+                resistances.as_ref().and_then(|r| r.borrow().get(*entity)),
+            ) {
+                Some(d) => d,
+                None => continue,
+            };
+
+            if is_hit {
+                health.take_hit(&damage);
+            } else {
+                health.take_damage(&damage);
+            }
+
+            if let Some(actor) = actors.as_mut().and_then(|a| a.get_mut(*entity)) {
+                actor.apply_impulse(damage.knockback);
+            }
+
+            if let Some(effect) = damage.effect {
+                if let Some(status_effects) =
+                    status_effects.as_mut().and_then(|s| s.get_mut(*entity))
+                {
+                    status_effects.add(effect);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve_all(seed: u32) -> Vec<Option<(i32, bool)>> {
+        let mut rng = Rng::new(seed);
+        let armor = Armor::new(2, 0.25);
+        let resistances = Resistances::new(&[(DamageType::Fire, 0.5)]);
+
+        (0..200)
+            .map(|i| {
+                let damage_type = if i % 2 == 0 {
+                    DamageType::Physical
+                } else {
+                    DamageType::Fire
+                };
+                let mut damage = DamageInfo::new(Some(1), 10, damage_type);
+                damage.crit_chance = 0.3;
+
+                DamageSystem::resolve(&mut rng, damage, Some(&armor), Some(&resistances))
+                    .map(|d| (d.amount, d.is_critical))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_gives_same_results() {
+        let results = resolve_all(1234);
+
+        assert_eq!(results, resolve_all(1234));
+        assert!(results.iter().any(|r| r.is_none()));
+        assert!(results.iter().any(|r| r.is_some_and(|(_, c)| c)));
+        assert!(results.iter().any(|r| r.is_some_and(|(_, c)| !c)));
+    }
+
+    #[test]
+    fn dodging_comes_before_critical_hits() {
+        let mut rng = Rng::new(1);
+        let mut damage = DamageInfo::new(Some(1), 10, DamageType::Physical);
+        damage.crit_chance = 1.0;
+
+        let armor = Armor::new(0, 1.0);
+        assert!(DamageSystem::resolve(&mut rng, damage, Some(&armor), None).is_none());
+    }
+
+    #[test]
+    fn critical_hits_come_before_armor() {
+        let mut rng = Rng::new(1);
+        let mut damage = DamageInfo::new(Some(1), 5, DamageType::Physical);
+        damage.crit_chance = 1.0;
+
+        let armor = Armor::new(3, 0.0);
+        let damage = DamageSystem::resolve(&mut rng, damage, Some(&armor), None).unwrap();
+
+        assert!(damage.is_critical);
+        assert_eq!(damage.amount, 5 * CRITICAL_MULTIPLIER - 3);
+    }
+
+    #[test]
+    fn armor_comes_before_resistances() {
+        let mut rng = Rng::new(1);
+        let damage = DamageInfo::new(Some(1), 10, DamageType::Physical);
+
+        let armor = Armor::new(4, 0.0);
+        let resistances = Resistances::new(&[(DamageType::Physical, 0.5)]);
+        let damage =
+            DamageSystem::resolve(&mut rng, damage, Some(&armor), Some(&resistances)).unwrap();
+
+        assert_eq!(damage.amount, 3);
+    }
+
+    #[test]
+    fn armor_only_reduces_physical_damage() {
+        let mut rng = Rng::new(1);
+        let damage = DamageInfo::new(Some(1), 10, DamageType::Fire);
+
+        let armor = Armor::new(4, 0.0);
+        let damage = DamageSystem::resolve(&mut rng, damage, Some(&armor), None).unwrap();
+
+        assert_eq!(damage.amount, 10);
+    }
+
+    #[test]
+    fn armor_lets_one_point_of_damage_through() {
+        let mut rng = Rng::new(1);
+        let armor = Armor::new(100, 0.0);
+
+        for amount in [1, 5, 50] {
+            let damage = DamageInfo::new(Some(1), amount, DamageType::Physical);
+            let damage = DamageSystem::resolve(&mut rng, damage, Some(&armor), None).unwrap();
+
+            assert_eq!(damage.amount, 1);
+        }
+
+        // Hits that do no damage don't get any from armor.
+        let damage = DamageInfo::new(Some(1), 0, DamageType::Physical);
+        let damage = DamageSystem::resolve(&mut rng, damage, Some(&armor), None).unwrap();
+        assert_eq!(damage.amount, 0);
+    }
+
+    #[test]
+    fn resistances_never_heal() {
+        let mut rng = Rng::new(1);
+        let resistances = Resistances::new(&[(DamageType::Fire, 1.5), (DamageType::Magic, -0.5)]);
+
+        let damage = DamageInfo::new(Some(1), 10, DamageType::Fire);
+        let damage = DamageSystem::resolve(&mut rng, damage, None, Some(&resistances)).unwrap();
+        assert_eq!(damage.amount, 0);
+
+        // Weaknesses still add damage.
+        let damage = DamageInfo::new(Some(1), 10, DamageType::Magic);
+        let damage = DamageSystem::resolve(&mut rng, damage, None, Some(&resistances)).unwrap();
+        assert_eq!(damage.amount, 15);
+    }
+}
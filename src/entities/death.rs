@@ -0,0 +1,145 @@
+use std::borrow::Borrow;
+
+use crate::{chunk::Chunk, gfx::gui::Gui, input::Input, rng::Rng};
+
+use super::{
+    actor::Actor,
+    damage::{DamageInfo, DamageType},
+    ecs::{Ecs, EntityManager, System},
+    player::Player,
+    prefab::Prefab,
+    trigger::Trigger,
+};
+
+const LOOT_SPREAD: f32 = 0.5;
+
+// An entity whose health ran out this frame, along with the damage that killed it.
+pub struct Death {
+    pub entity: usize,
+    pub cause: Option<DamageInfo>,
+}
+
+impl Death {
+    pub fn cause_of_death(&self, manager: &EntityManager) -> String {
+        let cause = match self.cause {
+            Some(c) => c,
+            None => return "Died".to_string(),
+        };
+
+        let killer = cause.source.and_then(|source| {
+            manager
+                .borrow_components::<Prefab>()
+                .and_then(|p| p.get(source).copied())
+        });
+
+        let trap = cause.source.and_then(|source| {
+            manager
+                .borrow_components::<Trigger>()
+                .and_then(|t| t.get(source).map(|t| t.action()))
+        });
+
+        if let Some(trap) = trap {
+            return format!("Killed by a {}", trap.name());
+        }
+
+        match (killer, cause.damage_type) {
+            (Some(killer), _) => format!("Killed by a {}", killer.name()),
+            (None, DamageType::Poison) => "Succumbed to poison".to_string(),
+            (None, DamageType::Fire) => "Burned to death".to_string(),
+            (None, _) => "Killed by something unseen".to_string(),
+        }
+    }
+}
+
+pub struct DeathSystem {
+    rng: Rng,
+    dead_entities: Vec<(usize, Option<cgmath::Vector3<f32>>, Option<Prefab>)>,
+}
+
+impl DeathSystem {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            dead_entities: Vec::new(),
+        }
+    }
+
+    fn roll_loot(rng: &mut Rng, prefab: Prefab) -> Option<Prefab> {
+        let loot_table = prefab.loot_table();
+        let total_weight = loot_table.iter().map(|(_, weight)| weight).sum::<u32>();
+
+        if total_weight == 0 {
+            return None;
+        }
+
+        let mut roll = rng.range(total_weight);
+
+        for (loot, weight) in loot_table {
+            if roll < *weight {
+                return loot.map(Prefab::Item);
+            }
+
+            roll -= weight;
+        }
+
+        None
+    }
+}
+
+impl System for DeathSystem {
+    fn update(
+        &mut self,
+        ecs: &mut Ecs,
+        _chunk: &mut Chunk,
+        _input: &mut Input,
+        _gui: &mut Gui,
+        _delta_time: f32,
+    ) {
+        let Ecs { manager, queue, .. } = ecs;
+
+        let actors = manager.borrow_components::<Actor>();
+        let players = manager.borrow_components::<Player>();
+        let prefabs = manager.borrow_components::<Prefab>();
+
+        self.dead_entities.clear();
+
+        for death in queue.deaths() {
+            // The player's death ends the game instead, which is handled by the simulation.
+            if players.as_ref().is_some_and(|p| p.has(death.entity)) {
+                continue;
+            }
+
+            let position = actors
+                .as_ref()
+                .and_then(|a| a.borrow().get(death.entity))
+                .map(|a| a.position());
+            let prefab = prefabs
+                .as_ref()
+                .and_then(|p| p.borrow().get(death.entity))
+                .copied();
+
+            self.dead_entities.push((death.entity, position, prefab));
+        }
+
+        for (entity, position, prefab) in self.dead_entities.drain(..) {
+            queue.remove_entity(entity);
+
+            let (position, prefab) = match (position, prefab) {
+                (Some(position), Some(prefab)) => (position, prefab),
+                _ => continue,
+            };
+
+            queue.spawn_prefab(Prefab::Corpse, position);
+
+            if let Some(loot) = Self::roll_loot(&mut self.rng, prefab) {
+                let offset = cgmath::vec3(
+                    self.rng.range(3) as f32 - 1.0,
+                    0.0,
+                    self.rng.range(3) as f32 - 1.0,
+                ) * LOOT_SPREAD;
+
+                queue.spawn_prefab(loot, position + offset);
+            }
+        }
+    }
+}
@@ -1,13 +1,25 @@
 pub struct Display {
     tex_index: u32,
+    is_visible: bool,
 }
 
 impl Display {
     pub fn new(tex_index: u32) -> Self {
-        Self { tex_index }
+        Self {
+            tex_index,
+            is_visible: true,
+        }
     }
 
     pub fn tex_index(&self) -> u32 {
         self.tex_index
     }
+
+    pub fn set_visible(&mut self, is_visible: bool) {
+        self.is_visible = is_visible;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.is_visible
+    }
 }
@@ -0,0 +1,183 @@
+use std::borrow::Borrow;
+
+use cgmath::prelude::*;
+use winit::event::VirtualKeyCode;
+
+use crate::{
+    chunk::{Block, Chunk, Door, BLOCK_SIZE_F},
+    gfx::{camera::get_look_direction, gui::Gui},
+    input::Input,
+    math::round_vec_to_i32,
+};
+
+use super::{
+    actor::Actor,
+    ai::Ai,
+    ecs::{CommandQueue, Ecs, System},
+    inventory::Inventory,
+    item::ItemKind,
+    perception::{Noise, DOOR_NOISE},
+    player::Player,
+};
+
+const INTERACT_KEY: VirtualKeyCode = VirtualKeyCode::X;
+// In blocks.
+const INTERACT_RANGE: f32 = 1.5;
+const INTERACT_STEP: f32 = 0.1;
+// How close an ai needs to be to a door on its path before opening it.
+const AI_OPEN_DISTANCE: f32 = BLOCK_SIZE_F * 1.5;
+
+pub struct DoorSystem {}
+
+impl DoorSystem {
+    // Find the door that is being looked at, stepping along the view so that open doors, which
+    // aren't solid, can be found too.
+    fn find_door(
+        chunk: &Chunk,
+        start: cgmath::Vector3<f32>,
+        dir: cgmath::Vector3<f32>,
+    ) -> Option<(cgmath::Vector3<i32>, Door)> {
+        let start = start / BLOCK_SIZE_F;
+        let steps = (INTERACT_RANGE / INTERACT_STEP) as i32;
+
+        for step in 0..=steps {
+            let position = round_vec_to_i32(start + dir * step as f32 * INTERACT_STEP);
+
+            match chunk.block_at(position.x, position.y, position.z) {
+                Block::Door(door) => return Some((position, door)),
+                Block::Wall => return None,
+                Block::Air => {}
+            }
+        }
+
+        None
+    }
+
+    fn set_door(
+        chunk: &mut Chunk,
+        queue: &mut CommandQueue,
+        entity: usize,
+        position: cgmath::Vector3<i32>,
+        door: Door,
+    ) {
+        chunk.set_block_at(Block::Door(door), position.x, position.y, position.z);
+        queue.make_noise(Noise {
+            source: Some(entity),
+            position: (position.cast::<f32>().unwrap() + cgmath::vec3(0.5, 0.5, 0.5))
+                * BLOCK_SIZE_F,
+            loudness: DOOR_NOISE,
+        });
+    }
+
+    // Doors can't be closed on anything standing in them.
+    fn is_doorway_clear(chunk: &Chunk, position: cgmath::Vector3<i32>) -> bool {
+        chunk
+            .entities_at_block(position.x, position.z)
+            .is_none_or(|mut entities| entities.next().is_none())
+    }
+}
+
+impl System for DoorSystem {
+    fn update(
+        &mut self,
+        ecs: &mut Ecs,
+        chunk: &mut Chunk,
+        input: &mut Input,
+        gui: &mut Gui,
+        _delta_time: f32,
+    ) {
+        let Ecs {
+            manager,
+            entity_cache,
+            queue,
+        } = ecs;
+
+        let actors = match manager.borrow_components::<Actor>() {
+            Some(a) => a,
+            None => return,
+        };
+
+        if manager.get_entities_with_both::<Player, Actor>(entity_cache) {
+            let mut inventories = manager.borrow_components::<Inventory>();
+
+            for entity in entity_cache.iter() {
+                let actor = actors.borrow().get(*entity).unwrap();
+                let look_direction = get_look_direction(actor.look_x(), actor.look_y());
+
+                let (position, mut door) =
+                    match Self::find_door(chunk, actor.head_position(), look_direction) {
+                        Some(d) => d,
+                        None => continue,
+                    };
+
+                let inventory = inventories.as_mut().and_then(|i| i.get_mut(*entity));
+                let key_index = door.lock.and_then(|key| {
+                    inventory
+                        .as_ref()
+                        .and_then(|i| i.items().iter().position(|k| *k == ItemKind::Key(key)))
+                });
+
+                match (door.lock, key_index) {
+                    (Some(key), None) => {
+                        gui.write(&format!("Locked, needs a {}", key.name()));
+                        continue;
+                    }
+                    (Some(key), Some(_)) => gui.write(&format!("X: Unlock with {}", key.name())),
+                    (None, _) if door.is_open => gui.write("X: Close door"),
+                    (None, _) => gui.write("X: Open door"),
+                };
+
+                if !input.was_key_pressed(INTERACT_KEY) {
+                    continue;
+                }
+
+                // Keys are used up when they unlock their door.
+                if let (Some(key_index), Some(inventory)) = (key_index, inventory) {
+                    inventory.remove_item(key_index);
+                    door.lock = None;
+                    door.is_open = true;
+                } else if door.is_open {
+                    if !Self::is_doorway_clear(chunk, position) {
+                        continue;
+                    }
+
+                    door.is_open = false;
+                } else {
+                    door.is_open = true;
+                }
+
+                Self::set_door(chunk, queue, *entity, position, door);
+            }
+        }
+
+        // Ais open unlocked doors that are on their path.
+        if manager.get_entities_with_both::<Ai, Actor>(entity_cache) {
+            let ais = manager.borrow_components::<Ai>().unwrap();
+
+            for entity in entity_cache.iter() {
+                let ai = ais.borrow().get(*entity).unwrap();
+                let actor = actors.borrow().get(*entity).unwrap();
+
+                let next_waypoint = match ai.next_waypoint() {
+                    Some(n) => n,
+                    None => continue,
+                };
+
+                if (next_waypoint - actor.position()).magnitude() > AI_OPEN_DISTANCE {
+                    continue;
+                }
+
+                let position = round_vec_to_i32(next_waypoint / BLOCK_SIZE_F);
+
+                if let Block::Door(mut door) = chunk.block_at(position.x, position.y, position.z) {
+                    if door.is_open || door.lock.is_some() {
+                        continue;
+                    }
+
+                    door.is_open = true;
+                    Self::set_door(chunk, queue, *entity, position, door);
+                }
+            }
+        }
+    }
+}
@@ -5,7 +5,10 @@ use std::{
 
 use crate::{chunk::Chunk, gfx::gui::Gui, input::Input};
 
-use super::actor::Actor;
+use super::{
+    actor::Actor, damage::DamageInfo, death::Death, perception::Noise, prefab::Prefab,
+    projectile::Projectile,
+};
 
 pub struct Ecs {
     pub manager: EntityManager,
@@ -15,23 +18,25 @@ pub struct Ecs {
 
 impl Ecs {
     pub fn flush_queue(&mut self, chunk: &mut Chunk) {
-        let actors = match self.manager.borrow_components::<Actor>() {
-            Some(a) => a,
-            None => return,
-        };
+        if let Some(actors) = self.manager.borrow_components::<Actor>() {
+            for entity in self.queue.entities_to_remove() {
+                // Remove actor from the map.
+                if let Some(actor) = actors.get(*entity) {
+                    actor.update_occupied_blocks(*entity, chunk, None);
+                }
+            }
+        }
 
         for entity in self.queue.entities_to_remove() {
-            // Remove actor from the map.
-            actors
-                .get(*entity)
-                .unwrap()
-                .update_occupied_blocks(*entity, chunk, None);
+            self.manager.remove_entity(*entity);
         }
 
-        drop(actors);
+        for (projectile, position) in self.queue.projectiles_to_spawn.drain(..) {
+            projectile.spawn(&mut self.manager, position);
+        }
 
-        for entity in self.queue.entities_to_remove() {
-            self.manager.remove_entity(*entity);
+        for (prefab, position) in self.queue.prefabs_to_spawn.drain(..) {
+            prefab.spawn(&mut self.manager, position);
         }
 
         self.queue.clear();
@@ -40,12 +45,24 @@ impl Ecs {
 
 pub struct CommandQueue {
     entities_to_remove: Vec<usize>,
+    projectiles_to_spawn: Vec<(Projectile, cgmath::Vector3<f32>)>,
+    prefabs_to_spawn: Vec<(Prefab, cgmath::Vector3<f32>)>,
+    noises: Vec<Noise>,
+    damages: Vec<(usize, DamageInfo)>,
+    deaths: Vec<Death>,
+    hit_stop: f32,
 }
 
 impl CommandQueue {
     pub fn new() -> Self {
         Self {
             entities_to_remove: Vec::new(),
+            projectiles_to_spawn: Vec::new(),
+            prefabs_to_spawn: Vec::new(),
+            noises: Vec::new(),
+            damages: Vec::new(),
+            deaths: Vec::new(),
+            hit_stop: 0.0,
         }
     }
 
@@ -53,13 +70,60 @@ impl CommandQueue {
         self.entities_to_remove.push(entity);
     }
 
+    pub fn spawn_projectile(&mut self, projectile: Projectile, position: cgmath::Vector3<f32>) {
+        self.projectiles_to_spawn.push((projectile, position));
+    }
+
+    pub fn spawn_prefab(&mut self, prefab: Prefab, position: cgmath::Vector3<f32>) {
+        self.prefabs_to_spawn.push((prefab, position));
+    }
+
+    pub fn make_noise(&mut self, noise: Noise) {
+        self.noises.push(noise);
+    }
+
+    pub fn deal_damage(&mut self, entity: usize, damage: DamageInfo) {
+        self.damages.push((entity, damage));
+    }
+
+    pub fn kill(&mut self, death: Death) {
+        self.deaths.push(death);
+    }
+
+    // Ask the simulation to pause briefly, longer requests override shorter ones.
+    pub fn hit_stop(&mut self, duration: f32) {
+        self.hit_stop = self.hit_stop.max(duration);
+    }
+
     pub fn clear(&mut self) {
         self.entities_to_remove.clear();
+        self.projectiles_to_spawn.clear();
+        self.prefabs_to_spawn.clear();
+        self.noises.clear();
+        self.damages.clear();
+        self.deaths.clear();
+        self.hit_stop = 0.0;
     }
 
     pub fn entities_to_remove(&self) -> &Vec<usize> {
         &self.entities_to_remove
     }
+
+    pub fn noises(&self) -> &Vec<Noise> {
+        &self.noises
+    }
+
+    pub fn damages(&self) -> &Vec<(usize, DamageInfo)> {
+        &self.damages
+    }
+
+    pub fn deaths(&self) -> &Vec<Death> {
+        &self.deaths
+    }
+
+    pub fn hit_stop_duration(&self) -> f32 {
+        self.hit_stop
+    }
 }
 
 pub struct EntityManager {
@@ -0,0 +1,599 @@
+use std::{borrow::Borrow, collections::HashSet, io};
+
+use cgmath::prelude::*;
+use winit::event::{MouseButton, VirtualKeyCode};
+
+use crate::{
+    chunk::{Block, Chunk, Door, BLOCK_SIZE_F},
+    gfx::{camera::get_look_direction, gui::Gui},
+    input::Input,
+    level::{
+        vox::{save_vox, VoxModel},
+        Level,
+    },
+};
+
+use super::{
+    actor::Actor,
+    ecs::{Ecs, System},
+    item::{ItemKind, KeyKind},
+    player::Player,
+    prefab::Prefab,
+};
+
+const BRUSH_KEY: VirtualKeyCode = VirtualKeyCode::B;
+const BLOCK_KEY: VirtualKeyCode = VirtualKeyCode::N;
+const PREVIOUS_PREFAB_KEY: VirtualKeyCode = VirtualKeyCode::LBracket;
+const NEXT_PREFAB_KEY: VirtualKeyCode = VirtualKeyCode::RBracket;
+const PLACE_PREFAB_KEY: VirtualKeyCode = VirtualKeyCode::P;
+const REMOVE_ENTITY_KEY: VirtualKeyCode = VirtualKeyCode::Delete;
+const UNDO_KEY: VirtualKeyCode = VirtualKeyCode::Z;
+const REDO_KEY: VirtualKeyCode = VirtualKeyCode::Y;
+const SAVE_KEY: VirtualKeyCode = VirtualKeyCode::F5;
+const LOAD_KEY: VirtualKeyCode = VirtualKeyCode::F9;
+const SAVE_TEXT_KEY: VirtualKeyCode = VirtualKeyCode::F6;
+const LOAD_TEXT_KEY: VirtualKeyCode = VirtualKeyCode::F10;
+pub const LEVEL_PATH: &str = "res/levels/editor.level";
+const SAVE_VOX_KEY: VirtualKeyCode = VirtualKeyCode::F7;
+const STAMP_MODEL_KEY: VirtualKeyCode = VirtualKeyCode::I;
+const ROTATE_MODEL_KEY: VirtualKeyCode = VirtualKeyCode::R;
+pub const TEXT_LEVEL_PATH: &str = "res/levels/editor.txt";
+pub const VOX_LEVEL_PATH: &str = "res/levels/editor.vox";
+// Loaded each time that it is stamped, so that changes to it show up straight away.
+pub const VOX_MODEL_PATH: &str = "res/models/stamp.vox";
+// In blocks.
+const EDIT_RANGE: f32 = 16.0;
+const MAX_UNDO_LEN: usize = 100;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Brush {
+    Single,
+    Box,
+    HollowBox,
+    Line,
+}
+
+impl Brush {
+    const ALL: [Brush; 4] = [Brush::Single, Brush::Box, Brush::HollowBox, Brush::Line];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Brush::Single => "Single",
+            Brush::Box => "Box",
+            Brush::HollowBox => "Hollow Box",
+            Brush::Line => "Line",
+        }
+    }
+
+    // Every block that the brush covers between two corners.
+    fn positions(
+        &self,
+        start: cgmath::Vector3<i32>,
+        end: cgmath::Vector3<i32>,
+    ) -> Vec<cgmath::Vector3<i32>> {
+        let min = cgmath::vec3(start.x.min(end.x), start.y.min(end.y), start.z.min(end.z));
+        let max = cgmath::vec3(start.x.max(end.x), start.y.max(end.y), start.z.max(end.z));
+        let mut positions = Vec::new();
+
+        match self {
+            Brush::Single => positions.push(end),
+            Brush::Box | Brush::HollowBox => {
+                for z in min.z..=max.z {
+                    for y in min.y..=max.y {
+                        for x in min.x..=max.x {
+                            let is_edge = x == min.x
+                                || x == max.x
+                                || y == min.y
+                                || y == max.y
+                                || z == min.z
+                                || z == max.z;
+
+                            if *self == Brush::Box || is_edge {
+                                positions.push(cgmath::vec3(x, y, z));
+                            }
+                        }
+                    }
+                }
+            }
+            Brush::Line => {
+                let offset = end - start;
+                let steps = offset.x.abs().max(offset.y.abs()).max(offset.z.abs());
+
+                for step in 0..=steps {
+                    let t = if steps == 0 {
+                        0.0
+                    } else {
+                        step as f32 / steps as f32
+                    };
+
+                    let position = start.cast::<f32>().unwrap() + offset.cast::<f32>().unwrap() * t;
+                    positions.push(position.map(|n| n.round()).cast::<i32>().unwrap());
+                }
+            }
+        }
+
+        positions
+    }
+
+    // Single blocks are painted straight away, other brushes need a start corner first.
+    fn needs_start(&self) -> bool {
+        *self != Brush::Single
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct BlockChange {
+    position: cgmath::Vector3<i32>,
+    before: Block,
+    after: Block,
+}
+
+// Spawned entities get a new id whenever they are brought back by undo or redo, so the edits that
+// refer to the old one are updated to the new one.
+#[derive(Clone, Debug)]
+enum Edit {
+    Blocks(Vec<BlockChange>),
+    Spawn {
+        prefab: Prefab,
+        position: cgmath::Vector3<f32>,
+        entity: usize,
+    },
+    Remove {
+        prefab: Prefab,
+        position: cgmath::Vector3<f32>,
+        entity: usize,
+    },
+}
+
+pub struct EditorSystem {
+    brush: usize,
+    block: usize,
+    prefab: usize,
+    blocks: Vec<Block>,
+    prefabs: Vec<Prefab>,
+    // The first corner of a brush stroke, and whether it is erasing.
+    stroke_start: Option<(cgmath::Vector3<i32>, bool)>,
+    undo_stack: Vec<Edit>,
+    redo_stack: Vec<Edit>,
+    hit_entities: HashSet<usize>,
+    model_turns: u32,
+    // The result of the last save or load.
+    message: Option<String>,
+}
+
+impl EditorSystem {
+    pub fn new() -> Self {
+        let mut blocks = vec![
+            Block::Wall,
+            Block::Door(Door {
+                is_open: false,
+                lock: None,
+            }),
+        ];
+        blocks.extend(KeyKind::ALL.map(|key| {
+            Block::Door(Door {
+                is_open: false,
+                lock: Some(key),
+            })
+        }));
+
+        let mut prefabs = vec![
+            Prefab::Chaser,
+            Prefab::Guard,
+            Prefab::Wanderer,
+            Prefab::Archer,
+            Prefab::Mage,
+            Prefab::Torch,
+        ];
+        prefabs.extend(ItemKind::ALL.map(Prefab::Item));
+        prefabs.extend(KeyKind::ALL.map(|key| Prefab::Item(ItemKind::Key(key))));
+
+        Self {
+            brush: 0,
+            block: 0,
+            prefab: 0,
+            blocks,
+            prefabs,
+            stroke_start: None,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            hit_entities: HashSet::new(),
+            model_turns: 0,
+            message: None,
+        }
+    }
+
+    fn block_name(block: Block) -> String {
+        match block {
+            Block::Air => "Air".to_string(),
+            Block::Wall => "Wall".to_string(),
+            Block::Door(Door {
+                lock: Some(key), ..
+            }) => format!("{} Door", key.name()),
+            Block::Door(_) => "Door".to_string(),
+        }
+    }
+
+    fn paint(&mut self, chunk: &mut Chunk, blocks: Vec<(cgmath::Vector3<i32>, Block)>) {
+        let changes = blocks
+            .into_iter()
+            .filter_map(|(position, block)| {
+                let before = chunk.block_at(position.x, position.y, position.z);
+
+                if before == block || !chunk.is_in_bounds(position.x, position.y, position.z) {
+                    return None;
+                }
+
+                chunk.set_block_at(block, position.x, position.y, position.z);
+
+                Some(BlockChange {
+                    position,
+                    before,
+                    after: block,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if !changes.is_empty() {
+            self.push_edit(Edit::Blocks(changes));
+        }
+    }
+
+    fn push_edit(&mut self, edit: Edit) {
+        if self.undo_stack.len() == MAX_UNDO_LEN {
+            self.undo_stack.remove(0);
+        }
+
+        self.undo_stack.push(edit);
+        self.redo_stack.clear();
+    }
+
+    // Undo or redo an edit, returning the edit that reverses it.
+    fn revert(&mut self, edit: Edit, is_undo: bool, chunk: &mut Chunk, ecs: &mut Ecs) -> Edit {
+        match edit {
+            Edit::Blocks(changes) => {
+                for change in changes.iter().rev() {
+                    let block = if is_undo { change.before } else { change.after };
+                    let position = change.position;
+                    chunk.set_block_at(block, position.x, position.y, position.z);
+                }
+
+                Edit::Blocks(changes)
+            }
+            Edit::Spawn {
+                prefab,
+                position,
+                entity,
+            } => {
+                ecs.queue.remove_entity(entity);
+
+                Edit::Remove {
+                    prefab,
+                    position,
+                    entity,
+                }
+            }
+            Edit::Remove {
+                prefab,
+                position,
+                entity,
+            } => {
+                let new_entity = prefab.spawn(&mut ecs.manager, position);
+                self.replace_entity(entity, new_entity);
+
+                Edit::Spawn {
+                    prefab,
+                    position,
+                    entity: new_entity,
+                }
+            }
+        }
+    }
+
+    fn undo(&mut self, chunk: &mut Chunk, ecs: &mut Ecs) {
+        if let Some(edit) = self.undo_stack.pop() {
+            let edit = self.revert(edit, true, chunk, ecs);
+            self.redo_stack.push(edit);
+        }
+    }
+
+    fn redo(&mut self, chunk: &mut Chunk, ecs: &mut Ecs) {
+        if let Some(edit) = self.redo_stack.pop() {
+            let edit = self.revert(edit, false, chunk, ecs);
+            self.undo_stack.push(edit);
+        }
+    }
+
+    // Point every edit in the history that refers to an entity at the one that replaced it.
+    fn replace_entity(&mut self, old_entity: usize, new_entity: usize) {
+        for edit in self.undo_stack.iter_mut().chain(self.redo_stack.iter_mut()) {
+            match edit {
+                Edit::Spawn { entity, .. } | Edit::Remove { entity, .. }
+                    if *entity == old_entity =>
+                {
+                    *entity = new_entity;
+                }
+                _ => (),
+            }
+        }
+    }
+
+    // Find the closest entity that was spawned from a prefab along the view, other than the
+    // player.
+    fn find_entity(
+        &mut self,
+        ecs: &Ecs,
+        chunk: &Chunk,
+        start: cgmath::Vector3<f32>,
+        dir: cgmath::Vector3<f32>,
+    ) -> Option<(usize, Prefab, cgmath::Vector3<f32>)> {
+        chunk.raycast(
+            start / BLOCK_SIZE_F,
+            dir,
+            EDIT_RANGE,
+            Some(&mut self.hit_entities),
+        );
+
+        let actors = ecs.manager.borrow_components::<Actor>()?;
+        let prefabs = ecs.manager.borrow_components::<Prefab>()?;
+        let players = ecs.manager.borrow_components::<Player>();
+
+        self.hit_entities
+            .iter()
+            .filter(|e| !players.as_ref().is_some_and(|p| p.has(**e)))
+            .filter_map(|e| {
+                let prefab = prefabs.get(*e)?;
+                let actor = actors.get(*e)?;
+                let offset = actor.position() - start;
+
+                // Only count entities that are roughly in front of the view.
+                if offset.normalize().dot(dir) < 0.9 {
+                    return None;
+                }
+
+                Some((*e, *prefab, actor.position(), offset.magnitude()))
+            })
+            .min_by(|a, b| a.3.total_cmp(&b.3))
+            .map(|(entity, prefab, position, _)| (entity, prefab, position))
+    }
+
+    fn report_save(&mut self, result: Result<(), io::Error>, path: &str) {
+        self.message = Some(match result {
+            Ok(_) => format!("Saved to {}", path),
+            Err(e) => format!("Failed to save: {}", e),
+        });
+    }
+
+    fn load(
+        &mut self,
+        level: Result<Level, io::Error>,
+        path: &str,
+        chunk: &mut Chunk,
+        ecs: &mut Ecs,
+        player: usize,
+    ) {
+        let level = match level {
+            Ok(l) => l,
+            Err(e) => {
+                self.message = Some(format!("Failed to load: {}", e));
+                return;
+            }
+        };
+
+        level.apply(chunk, ecs, player);
+        chunk.reveal_all();
+
+        // Entities from before loading are gone, so the history can't be used.
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.stroke_start = None;
+        self.message = Some(format!("Loaded {}", path));
+    }
+
+    fn write_help(&self, gui: &mut Gui) {
+        let stroke = match self.stroke_start {
+            Some((_, true)) => " (erasing)",
+            Some((_, false)) => " (painting)",
+            None => "",
+        };
+
+        gui.write("F6: Save text  F10: Load text  F7: Save .vox");
+        gui.write(&format!(
+            "I: Stamp model  R: Rotate model ({})",
+            self.model_turns * 90
+        ));
+        gui.write("F5: Save  F9: Load  F2: Play");
+        gui.write("Z: Undo  Y: Redo  P: Place  Del: Remove");
+        gui.write(&format!("[ ]: Prefab {}", self.prefabs[self.prefab].name()));
+        gui.write(&format!(
+            "N: Block {}",
+            Self::block_name(self.blocks[self.block])
+        ));
+        gui.write(&format!(
+            "B: Brush {}{}",
+            Brush::ALL[self.brush].name(),
+            stroke
+        ));
+        gui.write("Editor");
+
+        if let Some(ref message) = self.message {
+            gui.write(message);
+        }
+    }
+}
+
+impl System for EditorSystem {
+    fn update(
+        &mut self,
+        ecs: &mut Ecs,
+        chunk: &mut Chunk,
+        input: &mut Input,
+        gui: &mut Gui,
+        _delta_time: f32,
+    ) {
+        let player = {
+            let Ecs {
+                manager,
+                entity_cache,
+                ..
+            } = ecs;
+
+            if !manager.get_entities_with_both::<Player, Actor>(entity_cache) {
+                return;
+            }
+
+            entity_cache[0]
+        };
+
+        if input.was_key_pressed(BRUSH_KEY) {
+            self.brush = (self.brush + 1) % Brush::ALL.len();
+            self.stroke_start = None;
+        }
+
+        if input.was_key_pressed(BLOCK_KEY) {
+            self.block = (self.block + 1) % self.blocks.len();
+        }
+
+        if input.was_key_pressed(NEXT_PREFAB_KEY) {
+            self.prefab = (self.prefab + 1) % self.prefabs.len();
+        }
+
+        if input.was_key_pressed(PREVIOUS_PREFAB_KEY) {
+            self.prefab = (self.prefab + self.prefabs.len() - 1) % self.prefabs.len();
+        }
+
+        if input.was_key_pressed(UNDO_KEY) {
+            self.undo(chunk, ecs);
+        }
+
+        if input.was_key_pressed(REDO_KEY) {
+            self.redo(chunk, ecs);
+        }
+
+        if input.was_key_pressed(SAVE_KEY) {
+            let result = Level::capture(chunk, ecs).save(LEVEL_PATH);
+            self.report_save(result, LEVEL_PATH);
+        }
+
+        if input.was_key_pressed(SAVE_TEXT_KEY) {
+            let result = Level::capture(chunk, ecs).save_text(TEXT_LEVEL_PATH);
+            self.report_save(result, TEXT_LEVEL_PATH);
+        }
+
+        if input.was_key_pressed(SAVE_VOX_KEY) {
+            self.report_save(save_vox(chunk, VOX_LEVEL_PATH), VOX_LEVEL_PATH);
+        }
+
+        if input.was_key_pressed(ROTATE_MODEL_KEY) {
+            self.model_turns = (self.model_turns + 1) % 4;
+        }
+
+        if input.was_key_pressed(LOAD_KEY) {
+            self.load(Level::load(LEVEL_PATH), LEVEL_PATH, chunk, ecs, player);
+        }
+
+        if input.was_key_pressed(LOAD_TEXT_KEY) {
+            let level = Level::load_text(TEXT_LEVEL_PATH);
+            self.load(level, TEXT_LEVEL_PATH, chunk, ecs, player);
+        }
+
+        self.write_help(gui);
+
+        let (head_position, look_direction) = {
+            let actors = ecs.manager.borrow_components::<Actor>().unwrap();
+            let actor = actors.borrow().get(player).unwrap();
+
+            (
+                actor.head_position(),
+                get_look_direction(actor.look_x(), actor.look_y()),
+            )
+        };
+
+        if input.was_key_pressed(REMOVE_ENTITY_KEY) {
+            if let Some((entity, prefab, position)) =
+                self.find_entity(ecs, chunk, head_position, look_direction)
+            {
+                ecs.queue.remove_entity(entity);
+                self.push_edit(Edit::Remove {
+                    prefab,
+                    position,
+                    entity,
+                });
+            }
+        }
+
+        let hit = match chunk.raycast(
+            head_position / BLOCK_SIZE_F,
+            look_direction,
+            EDIT_RANGE,
+            None,
+        ) {
+            Some(h) => h,
+            None => return,
+        };
+
+        if input.was_key_pressed(PLACE_PREFAB_KEY) {
+            let prefab = self.prefabs[self.prefab];
+            let position = (hit.last_position.cast::<f32>().unwrap() + cgmath::vec3(0.5, 0.5, 0.5))
+                * BLOCK_SIZE_F;
+            let entity = prefab.spawn(&mut ecs.manager, position);
+
+            self.push_edit(Edit::Spawn {
+                prefab,
+                position,
+                entity,
+            });
+        }
+
+        // Models are stamped with their lowest corner in front of what is being looked at.
+        if input.was_key_pressed(STAMP_MODEL_KEY) {
+            match VoxModel::load(VOX_MODEL_PATH) {
+                Ok(model) => {
+                    let blocks = model.blocks(hit.last_position, self.model_turns).collect();
+                    self.paint(chunk, blocks);
+                }
+                Err(e) => self.message = Some(format!("Failed to load model: {}", e)),
+            }
+        }
+
+        // Painting places blocks in front of what is being looked at, erasing removes it.
+        let is_erasing = if input.was_mouse_button_pressed(MouseButton::Left) {
+            false
+        } else if input.was_mouse_button_pressed(MouseButton::Right) {
+            true
+        } else {
+            return;
+        };
+
+        let target = if is_erasing {
+            hit.position
+        } else {
+            hit.last_position
+        };
+        let brush = Brush::ALL[self.brush];
+
+        let start = match self.stroke_start {
+            Some((start, was_erasing)) if was_erasing == is_erasing => start,
+            _ if brush.needs_start() => {
+                self.stroke_start = Some((target, is_erasing));
+                return;
+            }
+            _ => target,
+        };
+
+        self.stroke_start = None;
+
+        let block = if is_erasing {
+            Block::Air
+        } else {
+            self.blocks[self.block]
+        };
+
+        let blocks = brush
+            .positions(start, target)
+            .into_iter()
+            .map(|position| (position, block))
+            .collect();
+        self.paint(chunk, blocks);
+    }
+}
@@ -1,9 +1,10 @@
 use std::borrow::{Borrow, BorrowMut};
 
 use crate::{
-    chunk::Chunk,
+    chunk::{Chunk, BLOCK_SIZE_F},
     gfx::{camera::get_look_direction, gui::Gui, instance::Instance},
     input::Input,
+    math::round_vec_to_i32,
 };
 use cgmath::prelude::*;
 
@@ -34,7 +35,7 @@ impl System for EntityInstancesSystem {
     fn update(
         &mut self,
         ecs: &mut Ecs,
-        _chunk: &mut Chunk,
+        chunk: &mut Chunk,
         _input: &mut Input,
         _gui: &mut Gui,
         _delta_time: f32,
@@ -70,10 +71,21 @@ impl System for EntityInstancesSystem {
             let display = displays.borrow_mut().get(*entity).unwrap();
             let actor = actors.borrow_mut().get(*entity).unwrap();
 
+            if !display.is_visible() {
+                continue;
+            }
+
+            // Entities are only drawn while they are in the player's field of view.
+            let block_position = round_vec_to_i32(actor.position() / BLOCK_SIZE_F);
+            if !chunk.is_visible(block_position.x, block_position.z) {
+                continue;
+            }
+
             let mut instance = Instance {
                 position: actor.position(),
                 rotation: cgmath::Quaternion::zero(),
                 tex_index: display.tex_index(),
+                light: chunk.light_at(block_position.x, block_position.y, block_position.z),
             };
 
             instance.billboard(player_look_direction);
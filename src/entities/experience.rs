@@ -0,0 +1,130 @@
+use std::borrow::BorrowMut;
+
+use crate::{chunk::Chunk, gfx::gui::Gui, input::Input};
+
+use super::ecs::{Ecs, System};
+
+const EXPERIENCE_PER_VICTIM_LEVEL: u32 = 25;
+const EXPERIENCE_PER_LEVEL: u32 = 100;
+const POINTS_PER_LEVEL: u32 = 3;
+
+pub struct Experience {
+    level: u32,
+    experience: u32,
+    unspent_points: u32,
+}
+
+impl Experience {
+    // Levels start at 1, since level 0 wouldn't need any experience to level up.
+    pub fn new(level: u32) -> Self {
+        Self {
+            level: level.max(1),
+            experience: 0,
+            unspent_points: 0,
+        }
+    }
+
+    // Gain experience, levelling up as many times as it allows.
+    pub fn add_experience(&mut self, experience: u32) {
+        self.experience += experience;
+
+        while self.experience >= self.experience_to_next_level() {
+            self.experience -= self.experience_to_next_level();
+            self.level += 1;
+            self.unspent_points += POINTS_PER_LEVEL;
+        }
+    }
+
+    pub fn experience_to_next_level(&self) -> u32 {
+        self.level * EXPERIENCE_PER_LEVEL
+    }
+
+    // The experience given to whoever kills this entity.
+    pub fn experience_reward(&self) -> u32 {
+        self.level * EXPERIENCE_PER_VICTIM_LEVEL
+    }
+
+    // Returns false if there are no points left to spend.
+    pub fn spend_point(&mut self) -> bool {
+        if self.unspent_points == 0 {
+            return false;
+        }
+
+        self.unspent_points -= 1;
+        true
+    }
+
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    pub fn experience(&self) -> u32 {
+        self.experience
+    }
+
+    pub fn unspent_points(&self) -> u32 {
+        self.unspent_points
+    }
+}
+
+pub struct ExperienceSystem {}
+
+impl System for ExperienceSystem {
+    fn update(
+        &mut self,
+        ecs: &mut Ecs,
+        _chunk: &mut Chunk,
+        _input: &mut Input,
+        _gui: &mut Gui,
+        _delta_time: f32,
+    ) {
+        let Ecs { manager, queue, .. } = ecs;
+
+        let mut experiences = match manager.borrow_components::<Experience>() {
+            Some(e) => e,
+            None => return,
+        };
+
+        for death in queue.deaths() {
+            let killer = match death.cause.and_then(|c| c.source) {
+                Some(k) if k != death.entity => k,
+                _ => continue,
+            };
+
+            let reward = match experiences.get(death.entity) {
+                Some(e) => e.experience_reward(),
+                None => continue,
+            };
+
+            if let Some(experience) = experiences.borrow_mut().get_mut(killer) {
+                experience.add_experience(reward);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levelling_up() {
+        let mut experience = Experience::new(1);
+
+        // Enough for level 2 and 3, with some left over.
+        experience.add_experience(EXPERIENCE_PER_LEVEL * 3 + 10);
+        assert_eq!(experience.level(), 3);
+        assert_eq!(experience.experience(), 10);
+        assert_eq!(experience.unspent_points(), POINTS_PER_LEVEL * 2);
+    }
+
+    #[test]
+    fn level_zero_starts_at_one() {
+        let mut experience = Experience::new(0);
+        assert_eq!(experience.level(), 1);
+        assert!(experience.experience_to_next_level() > 0);
+
+        experience.add_experience(EXPERIENCE_PER_LEVEL - 1);
+        assert_eq!(experience.level(), 1);
+    }
+}
@@ -0,0 +1,50 @@
+use std::borrow::Borrow;
+
+use crate::{
+    chunk::{Chunk, BLOCK_SIZE_F},
+    gfx::gui::Gui,
+    input::Input,
+    math::round_vec_to_i32,
+};
+
+use super::{
+    actor::Actor,
+    ecs::{Ecs, System},
+    player::Player,
+};
+
+// In blocks.
+const SIGHT_RADIUS: i32 = 12;
+
+// Updates what the player can see, and what they've explored, in the chunk.
+pub struct FieldOfViewSystem {}
+
+impl System for FieldOfViewSystem {
+    fn update(
+        &mut self,
+        ecs: &mut Ecs,
+        chunk: &mut Chunk,
+        _input: &mut Input,
+        _gui: &mut Gui,
+        _delta_time: f32,
+    ) {
+        let Ecs {
+            manager,
+            entity_cache,
+            ..
+        } = ecs;
+
+        if !manager.get_entities_with_both::<Player, Actor>(entity_cache) {
+            return;
+        }
+
+        let actors = manager.borrow_components::<Actor>().unwrap();
+
+        if let Some(player) = entity_cache.first() {
+            let actor = actors.borrow().get(*player).unwrap();
+            let origin = round_vec_to_i32(actor.head_position() / BLOCK_SIZE_F);
+
+            chunk.update_visibility(origin, SIGHT_RADIUS);
+        }
+    }
+}
@@ -1,160 +1,265 @@
 use std::{
     borrow::{Borrow, BorrowMut},
-    cell::RefMut,
     collections::HashSet,
 };
 
+use cgmath::prelude::*;
 use winit::event::MouseButton;
 
 use crate::{
     chunk::{Chunk, BLOCK_SIZE_F},
-    gfx::{camera::Camera, gui::Gui},
+    gfx::{
+        camera::{get_look_direction, Camera},
+        gui::Gui,
+    },
     input::Input,
     ray::Ray,
 };
 
 use super::{
-    actor::Actor,
+    actor::{Actor, GRAVITY},
+    ai::Ai,
+    damage::DamageInfo,
     ecs::{ComponentStore, Ecs, System},
     health::Health,
+    inventory::Inventory,
+    perception::{Noise, ATTACK_NOISE},
     player::Player,
+    projectile::{Projectile, ProjectileKind},
+    weapon::Weapon,
 };
 
+enum AttackState {
+    Ready,
+    WindUp(f32),
+    Recovery(f32),
+}
+
 pub struct Fighter {
     attack_damage: i32,
-    attack_cooldown: f32,
-    attack_timer: f32,
+    weapon: Weapon,
+    attack_state: AttackState,
+    attack_speed: f32,
+    // Bonuses from attributes, kept separate from status effects which change every frame.
+    bonus_damage: i32,
+    bonus_attack_speed: f32,
+    // The weapon used by the attack in progress, which may differ from the fighter's own weapon.
+    swing: Weapon,
 }
 
 impl Fighter {
-    pub fn new(attack_damage: i32, attack_cooldown: f32) -> Self {
+    pub fn new(attack_damage: i32, weapon: Weapon) -> Self {
         Self {
             attack_damage,
-            attack_cooldown,
-            attack_timer: 0.0,
+            weapon,
+            attack_state: AttackState::Ready,
+            attack_speed: 1.0,
+            bonus_damage: 0,
+            bonus_attack_speed: 1.0,
+            swing: weapon,
         }
     }
 
-    pub fn update(&mut self, delta_time: f32) {
-        self.attack_timer -= delta_time;
+    pub fn start_attack(&mut self, weapon: Weapon) {
+        // Fighters that can't move, such as stunned ones, can't start attacks either.
+        if self.attack_speed <= 0.0 {
+            return;
+        }
+
+        if let AttackState::Ready = self.attack_state {
+            self.swing = weapon;
+            self.attack_state = AttackState::WindUp(weapon.wind_up());
+        }
     }
 
-    pub fn get_attack(&mut self) -> i32 {
-        if self.attack_timer > 0.0 {
-            return 0;
+    // Returns the weapon to strike with on the frame that the attack's wind up finishes.
+    pub fn update(&mut self, delta_time: f32) -> Option<Weapon> {
+        let delta_time = delta_time * self.attack_speed * self.bonus_attack_speed;
+
+        match self.attack_state {
+            AttackState::Ready => None,
+            AttackState::WindUp(timer) => {
+                if timer - delta_time > 0.0 {
+                    self.attack_state = AttackState::WindUp(timer - delta_time);
+                    return None;
+                }
+
+                self.attack_state = AttackState::Recovery(self.swing.recovery());
+                Some(self.swing)
+            }
+            AttackState::Recovery(timer) => {
+                self.attack_state = if timer - delta_time > 0.0 {
+                    AttackState::Recovery(timer - delta_time)
+                } else {
+                    AttackState::Ready
+                };
+
+                None
+            }
         }
+    }
+
+    // Scales how quickly attacks wind up and recover.
+    pub fn set_attack_speed(&mut self, attack_speed: f32) {
+        self.attack_speed = attack_speed;
+    }
+
+    pub fn set_attribute_bonuses(&mut self, bonus_damage: i32, bonus_attack_speed: f32) {
+        self.bonus_damage = bonus_damage;
+        self.bonus_attack_speed = bonus_attack_speed;
+    }
 
-        self.attack_timer = self.attack_cooldown;
-        self.attack_damage
+    pub fn damage(&self, weapon: &Weapon) -> i32 {
+        self.attack_damage + self.bonus_damage + weapon.damage()
+    }
+
+    pub fn weapon(&self) -> Weapon {
+        self.weapon
     }
 }
 
 pub struct FighterSystem {
     nearby_entities: HashSet<usize>,
+    targets: Vec<usize>,
 }
 
 impl FighterSystem {
     pub fn new() -> Self {
         Self {
             nearby_entities: HashSet::new(),
+            targets: Vec::new(),
         }
     }
 
-    fn get_target(
-        &mut self,
-        entity: usize,
-        chunk: &mut Chunk,
-        input: &mut Input,
-        players: &Option<RefMut<ComponentStore<Player>>>,
-        actors: &mut RefMut<ComponentStore<Actor>>,
-        healths: &RefMut<ComponentStore<Health>>,
-    ) -> Option<usize> {
-        if let Some(ref players) = players {
-            if players.borrow().has(entity) {
-                self.get_target_raycast(entity, chunk, input, actors, healths)
-            } else {
-                self.get_target_proximity(entity, chunk, actors, healths)
-            }
-        } else {
-            self.get_target_proximity(entity, chunk, actors, healths)
+    // Aim above the target to make up for the distance the projectile will fall before reaching it.
+    fn get_aim_direction(
+        start: cgmath::Vector3<f32>,
+        target: cgmath::Vector3<f32>,
+        projectile: ProjectileKind,
+    ) -> cgmath::Vector3<f32> {
+        let mut offset = target - start;
+
+        if projectile.has_gravity() {
+            let time = offset.magnitude() / projectile.speed();
+            offset.y += 0.5 * GRAVITY * time * time;
         }
+
+        offset
     }
 
-    fn get_target_proximity(
+    // Find every actor with health that a melee swing hits. The closest actor along the
+    // attacker's view is always hit, and the swing arc cleaves anything else in front of them.
+    fn get_melee_targets(
         &mut self,
         entity: usize,
-        chunk: &mut Chunk,
-        actors: &mut RefMut<ComponentStore<Actor>>,
-        healths: &RefMut<ComponentStore<Health>>,
-    ) -> Option<usize> {
-        let actor = actors.borrow_mut().get_mut(entity).unwrap();
-
-        actor.get_nearby_entities(chunk, &mut self.nearby_entities);
-
-        let position = actor.position();
-        let size = actor.size();
+        weapon: &Weapon,
+        chunk: &Chunk,
+        actors: &ComponentStore<Actor>,
+        healths: &ComponentStore<Health>,
+    ) {
+        self.targets.clear();
+
+        let actor = actors.get(entity).unwrap();
+        let position = actor.head_position();
+        let dir = get_look_direction(actor.look_x(), actor.look_y());
+
+        let max_distance = chunk
+            .raycast(
+                position / BLOCK_SIZE_F,
+                dir,
+                weapon.reach() / BLOCK_SIZE_F,
+                None,
+            )
+            .map(|hit| hit.distance * BLOCK_SIZE_F)
+            .unwrap_or(f32::INFINITY)
+            .min(weapon.reach());
+
+        actor.get_entities_in_range(
+            chunk,
+            weapon.reach() + actor.size().x,
+            &mut self.nearby_entities,
+        );
+
+        let ray = Ray { position, dir };
+        let mut closest_hit = None;
 
         for nearby_entity in &self.nearby_entities {
-            if *nearby_entity == entity {
+            if entity == *nearby_entity {
                 continue;
             }
 
-            let nearby_actor = match actors.borrow_mut().get(*nearby_entity) {
+            if !healths.has(*nearby_entity) {
+                continue;
+            }
+
+            let nearby_actor = match actors.get(*nearby_entity) {
                 Some(a) => a,
                 None => continue,
             };
 
-            if !nearby_actor.intersects(position, size) {
+            if weapon.arc() > 0.0 && Self::is_in_swing_arc(actor, nearby_actor, weapon, chunk) {
+                self.targets.push(*nearby_entity);
+                continue;
+            }
+
+            let distance =
+                match ray.intersection_distance(nearby_actor.position(), nearby_actor.size()) {
+                    Some(d) => d.max(0.0),
+                    None => continue,
+                };
+
+            if distance > max_distance {
                 continue;
             }
 
-            if healths.has(*nearby_entity) {
-                return Some(*nearby_entity);
+            match closest_hit {
+                Some((_, closest_distance)) if closest_distance <= distance => {}
+                _ => closest_hit = Some((*nearby_entity, distance)),
             }
         }
 
-        None
+        if let Some((hit_entity, _)) = closest_hit {
+            self.targets.push(hit_entity);
+        }
     }
 
-    fn get_target_raycast(
-        &mut self,
-        entity: usize,
-        chunk: &mut Chunk,
-        input: &mut Input,
-        actors: &mut RefMut<ComponentStore<Actor>>,
-        healths: &RefMut<ComponentStore<Health>>,
-    ) -> Option<usize> {
-        let actor = actors.borrow_mut().get_mut(entity).unwrap();
-
-        let position = actor.position();
-        let look_y = actor.look_y();
+    fn is_in_swing_arc(actor: &Actor, target: &Actor, weapon: &Weapon, chunk: &Chunk) -> bool {
+        let offset = target.position() - actor.position();
 
-        if input.was_mouse_button_pressed(MouseButton::Left) {
-            let start = position / BLOCK_SIZE_F;
-            let dir = Camera::get_direction_vec(look_y);
+        if offset.y.abs() > (actor.size().y + target.size().y) * 0.5 {
+            return false;
+        }
 
-            chunk.raycast(start, dir, 10.0, Some(&mut self.nearby_entities));
+        let flat_offset = cgmath::vec3(offset.x, 0.0, offset.z);
+        let distance = flat_offset.magnitude();
 
-            for hit_entity in &self.nearby_entities {
-                if entity == *hit_entity {
-                    continue;
-                }
+        if distance - target.size().x * 0.5 > weapon.reach() {
+            return false;
+        }
 
-                if !healths.has(*hit_entity) {
-                    continue;
-                }
+        if distance <= f32::EPSILON {
+            return true;
+        }
 
-                if let Some(hit_actor) = actors.get(*hit_entity) {
-                    let ray = Ray { position, dir };
+        let dir = flat_offset / distance;
+        let forward = Camera::get_direction_vec(actor.look_y());
 
-                    if ray.intersects(hit_actor.position(), hit_actor.size()) {
-                        return Some(*hit_entity);
-                    }
-                }
-            }
+        if forward.angle(dir) > cgmath::Deg(weapon.arc() * 0.5).into() {
+            return false;
         }
 
-        None
+        // Swings can't pass through walls.
+        let block_distance = distance / BLOCK_SIZE_F;
+
+        match chunk.raycast(
+            actor.head_position() / BLOCK_SIZE_F,
+            dir,
+            block_distance,
+            None,
+        ) {
+            Some(hit) => hit.distance >= block_distance,
+            None => true,
+        }
     }
 }
 
@@ -170,17 +275,19 @@ impl System for FighterSystem {
         let Ecs {
             manager,
             entity_cache,
-            ..
+            queue,
         } = ecs;
 
         if !manager.get_entities_with_both::<Fighter, Actor>(entity_cache) {
             return;
         }
 
-        let mut actors = manager.borrow_components::<Actor>().unwrap();
+        let actors = manager.borrow_components::<Actor>().unwrap();
         let mut fighters = manager.borrow_components::<Fighter>().unwrap();
         let players = manager.borrow_components::<Player>();
-        let mut healths = match manager.borrow_components::<Health>() {
+        let ais = manager.borrow_components::<Ai>();
+        let mut inventories = manager.borrow_components::<Inventory>();
+        let healths = match manager.borrow_components::<Health>() {
             Some(h) => h,
             None => return,
         };
@@ -188,19 +295,124 @@ impl System for FighterSystem {
         for entity in entity_cache {
             let fighter = fighters.borrow_mut().get_mut(*entity).unwrap();
 
-            fighter.update(delta_time);
+            let is_player = players.as_ref().is_some_and(|p| p.has(*entity));
+            let mut inventory = inventories.as_mut().and_then(|i| i.get_mut(*entity));
+            let ai = ais.as_ref().and_then(|ais| ais.get(*entity));
 
-            // Find a target actor with health that this entity can hit, AI characters and players
-            // use different methods to find a target.
-            let target =
-                match self.get_target(*entity, chunk, input, &players, &mut actors, &healths) {
-                    Some(t) => t,
-                    None => continue,
+            // Ais attack when their behaviour decides to, players when they click.
+            let ai_target = ai.and_then(|ai| ai.attack_target());
+            let wants_to_attack = match ai {
+                Some(_) => ai_target.is_some(),
+                None => is_player && input.was_mouse_button_pressed(MouseButton::Left),
+            };
+
+            // Players fight with their equipped item if it is a weapon, otherwise with the weapon
+            // they were spawned with.
+            let weapon = inventory
+                .as_ref()
+                .and_then(|i| i.equipped())
+                .and_then(|i| i.weapon())
+                .unwrap_or(fighter.weapon());
+
+            if wants_to_attack {
+                fighter.start_attack(weapon);
+            }
+
+            let weapon = match fighter.update(delta_time) {
+                Some(w) => w,
+                None => continue,
+            };
+
+            let actor = actors.borrow().get(*entity).unwrap();
+            let position = actor.position();
+
+            if let Some(projectile) = weapon.projectile() {
+                let start = actor.head_position();
+
+                let dir = match ai_target {
+                    Some(target) => Self::get_aim_direction(start, target, projectile),
+                    None => get_look_direction(actor.look_x(), actor.look_y()),
                 };
 
-            if let Some(health) = healths.get_mut(target) {
-                health.take_damage(fighter.get_attack());
+                queue.spawn_projectile(Projectile::new(*entity, projectile, dir), start);
+                queue.make_noise(Noise {
+                    source: Some(*entity),
+                    position,
+                    loudness: ATTACK_NOISE,
+                });
+
+                if let Some(ref mut inventory) = inventory {
+                    if inventory.equipped().is_some_and(|i| i.is_consumed_on_use()) {
+                        inventory.remove_equipped();
+                    }
+                }
+
+                continue;
+            }
+
+            self.get_melee_targets(*entity, &weapon, chunk, &actors, &healths);
+
+            if self.targets.is_empty() {
+                continue;
+            }
+
+            let mut damage =
+                DamageInfo::new(Some(*entity), fighter.damage(&weapon), weapon.damage_type());
+            damage.crit_chance = weapon.crit_chance();
+            damage.effect = weapon.effect();
+
+            let forward = Camera::get_direction_vec(actor.look_y());
+
+            for target in &self.targets {
+                let offset = actors.get(*target).unwrap().position() - position;
+                let dir = cgmath::vec3(offset.x, 0.0, offset.z);
+                let dir = if dir.magnitude() > f32::EPSILON {
+                    dir.normalize()
+                } else {
+                    forward
+                };
+
+                damage.knockback = dir * weapon.knockback();
+                queue.deal_damage(*target, damage);
             }
+
+            queue.make_noise(Noise {
+                source: Some(*entity),
+                position,
+                loudness: ATTACK_NOISE,
+            });
+            queue.hit_stop(weapon.hit_stop());
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::{ecs::EntityManager, prefab::Prefab};
+
+    // Archers do all of their damage through arrows, so they have no attack damage of their own.
+    #[test]
+    fn archers_fire_without_attack_damage() {
+        let mut manager = EntityManager::new();
+        let archer = Prefab::Archer.spawn(&mut manager, cgmath::vec3(0.0, 0.0, 0.0));
+        let mut fighters = manager.borrow_components::<Fighter>().unwrap();
+        let fighter = fighters.get_mut(archer).unwrap();
+        let weapon = fighter.weapon();
+
+        assert!(weapon.projectile().is_some());
+        assert_eq!(fighter.damage(&weapon), 0);
+
+        fighter.start_attack(weapon);
+        let shot = fighter.update(weapon.wind_up() + 0.01);
+        assert!(shot.is_some_and(|w| w.projectile() == weapon.projectile()));
+
+        // It can't fire again until it has recovered.
+        fighter.start_attack(weapon);
+        assert!(fighter.update(0.01).is_none());
+        fighter.update(weapon.recovery());
+
+        fighter.start_attack(weapon);
+        assert!(fighter.update(weapon.wind_up() + 0.01).is_some());
+    }
+}
@@ -1,23 +1,112 @@
-use std::borrow::Borrow;
+use std::borrow::BorrowMut;
 
-use super::ecs::{Ecs, System};
+use super::{
+    damage::DamageInfo,
+    death::Death,
+    ecs::{Ecs, System},
+};
 
 pub struct Health {
     amount: i32,
+    max_amount: i32,
+    // Extra max health from attributes, on top of the max health the entity spawned with.
+    bonus_max_amount: i32,
+    // Health regained per second, fractions of a point are kept until they add up.
+    regeneration: f32,
+    regeneration_progress: f32,
+    // How long the entity can't be hit again after being hit.
+    invulnerability_time: f32,
+    invulnerability_timer: f32,
+    last_damage: Option<DamageInfo>,
 }
 
 impl Health {
-    pub fn new(amount: i32) -> Self {
-        Self { amount }
+    pub fn new(max_amount: i32) -> Self {
+        Self {
+            amount: max_amount,
+            max_amount,
+            bonus_max_amount: 0,
+            regeneration: 0.0,
+            regeneration_progress: 0.0,
+            invulnerability_time: 0.0,
+            invulnerability_timer: 0.0,
+            last_damage: None,
+        }
+    }
+
+    // Gaining max health also restores the health that was gained.
+    pub fn set_bonus_max_amount(&mut self, bonus_max_amount: i32) {
+        let gained = bonus_max_amount - self.bonus_max_amount;
+        self.bonus_max_amount = bonus_max_amount;
+
+        if gained > 0 {
+            self.amount += gained;
+        }
+
+        self.amount = self.amount.min(self.max_amount());
+    }
+
+    pub fn set_regeneration(&mut self, regeneration: f32) {
+        self.regeneration = regeneration;
+    }
+
+    pub fn set_invulnerability_time(&mut self, invulnerability_time: f32) {
+        self.invulnerability_time = invulnerability_time;
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.invulnerability_timer -= delta_time;
+
+        if self.amount <= 0 || self.amount >= self.max_amount() {
+            self.regeneration_progress = 0.0;
+            return;
+        }
+
+        self.regeneration_progress += self.regeneration * delta_time;
+
+        let regenerated = self.regeneration_progress.floor();
+        self.regeneration_progress -= regenerated;
+        self.heal(regenerated as i32);
+    }
+
+    // Lose health regardless of invulnerability, used for damage over time.
+    pub fn take_damage(&mut self, damage: &DamageInfo) {
+        self.amount -= damage.amount;
+        self.last_damage = Some(*damage);
     }
 
-    pub fn take_damage(&mut self, amount: i32) {
-        self.amount -= amount;
+    // Lose health from a direct hit, which makes the entity briefly invulnerable to other hits.
+    // Returns false if the entity was already invulnerable.
+    pub fn take_hit(&mut self, damage: &DamageInfo) -> bool {
+        if self.is_invulnerable() {
+            return false;
+        }
+
+        self.take_damage(damage);
+        self.invulnerability_timer = self.invulnerability_time;
+
+        true
+    }
+
+    // Returns the amount of health that was actually restored.
+    pub fn heal(&mut self, amount: i32) -> i32 {
+        let healed = amount.clamp(0, (self.max_amount() - self.amount).max(0));
+        self.amount += healed;
+
+        healed
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerability_timer > 0.0
     }
 
     pub fn amount(&self) -> i32 {
         self.amount
     }
+
+    pub fn max_amount(&self) -> i32 {
+        self.max_amount + self.bonus_max_amount
+    }
 }
 
 pub struct HealthSystem {}
@@ -29,7 +118,7 @@ impl System for HealthSystem {
         _chunk: &mut crate::chunk::Chunk,
         _input: &mut crate::input::Input,
         _gui: &mut crate::gfx::gui::Gui,
-        _delta_time: f32,
+        delta_time: f32,
     ) {
         let Ecs {
             manager,
@@ -41,13 +130,18 @@ impl System for HealthSystem {
             return;
         }
 
-        let healths = manager.borrow_components::<Health>().unwrap();
+        let mut healths = manager.borrow_components::<Health>().unwrap();
 
         for entity in entity_cache {
-            let health = healths.borrow().get(*entity).unwrap();
+            let health = healths.borrow_mut().get_mut(*entity).unwrap();
+
+            health.update(delta_time);
 
             if health.amount() <= 0 {
-                queue.remove_entity(*entity);
+                queue.kill(Death {
+                    entity: *entity,
+                    cause: health.last_damage,
+                });
             }
         }
     }
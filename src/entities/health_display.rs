@@ -34,7 +34,11 @@ impl System for HealthDisplaySystem {
 
         for entity in entity_cache {
             let health = healths.borrow().get(*entity).unwrap();
-            gui.write(&format!("Health: {}", health.amount()));
+            gui.write(&format!(
+                "Health: {}/{}",
+                health.amount(),
+                health.max_amount()
+            ));
         }
     }
 }
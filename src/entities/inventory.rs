@@ -3,34 +3,76 @@ use std::{
     collections::HashSet,
 };
 
+use winit::event::{MouseButton, VirtualKeyCode};
+
 use super::{
     actor::Actor,
     ecs::{Ecs, System},
-    item::Item,
+    item::{Item, ItemKind},
+    perception::{Noise, PICKUP_NOISE},
+    status_effect::StatusEffects,
 };
 
+const EQUIP_KEYS: [VirtualKeyCode; 9] = [
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+];
+
 pub struct Inventory {
-    items: Vec<char>,
+    items: Vec<ItemKind>,
+    equipped: Option<usize>,
 }
 
 impl Inventory {
     pub fn new() -> Self {
-        Self { items: Vec::new() }
+        Self {
+            items: Vec::new(),
+            equipped: None,
+        }
     }
 
-    // TODO: Make item components return the parameters for this function.
-    pub fn add_item(&mut self) {
-        let c = if self.items.len() % 2 == 0 { 'a' } else { 'b' };
-        self.items.push(c);
+    pub fn add_item(&mut self, kind: ItemKind) {
+        self.items.push(kind);
     }
 
     pub fn remove_item(&mut self, index: usize) {
-        if index < self.items().len() {
-            self.items.remove(index);
+        if index >= self.items().len() {
+            return;
         }
+
+        self.items.remove(index);
+
+        self.equipped = match self.equipped {
+            Some(equipped) if equipped == index => None,
+            Some(equipped) if equipped > index => Some(equipped - 1),
+            equipped => equipped,
+        };
     }
 
-    pub fn items(&self) -> &Vec<char> {
+    pub fn equip(&mut self, index: usize) {
+        if index < self.items.len() {
+            self.equipped = Some(index);
+        }
+    }
+
+    pub fn remove_equipped(&mut self) {
+        if let Some(equipped) = self.equipped {
+            self.remove_item(equipped);
+        }
+    }
+
+    pub fn equipped(&self) -> Option<ItemKind> {
+        self.equipped.map(|i| self.items[i])
+    }
+
+    pub fn items(&self) -> &Vec<ItemKind> {
         &self.items
     }
 }
@@ -52,7 +94,7 @@ impl System for InventorySystem {
         &mut self,
         ecs: &mut super::ecs::Ecs,
         chunk: &mut crate::chunk::Chunk,
-        _input: &mut crate::input::Input,
+        input: &mut crate::input::Input,
         _gui: &mut crate::gfx::gui::Gui,
         _delta_time: f32,
     ) {
@@ -68,15 +110,35 @@ impl System for InventorySystem {
 
         let actors = manager.borrow_components::<Actor>().unwrap();
         let mut inventories = manager.borrow_components::<Inventory>().unwrap();
-        let items = match manager.borrow_components::<Item>() {
-            Some(i) => i,
-            None => return,
-        };
+        let mut status_effects = manager.borrow_components::<StatusEffects>();
+        let items = manager.borrow_components::<Item>();
 
         for entity in entity_cache {
             let actor = actors.borrow().get(*entity).unwrap();
             let inventory = inventories.borrow_mut().get_mut(*entity).unwrap();
 
+            for (i, key) in EQUIP_KEYS.iter().enumerate() {
+                if input.was_key_pressed(*key) {
+                    inventory.equip(i);
+                }
+            }
+
+            // Right clicking uses the equipped item on yourself.
+            if input.was_mouse_button_pressed(MouseButton::Right) {
+                let effect = inventory.equipped().and_then(|i| i.effect());
+                let status_effects = status_effects.as_mut().and_then(|s| s.get_mut(*entity));
+
+                if let (Some(effect), Some(status_effects)) = (effect, status_effects) {
+                    status_effects.add(effect);
+                    inventory.remove_equipped();
+                }
+            }
+
+            let items = match items {
+                Some(ref i) => i,
+                None => continue,
+            };
+
             actor.get_nearby_entities(chunk, &mut self.nearby_entities);
 
             for nearby_entity in &self.nearby_entities {
@@ -84,9 +146,10 @@ impl System for InventorySystem {
                     continue;
                 }
 
-                if !items.has(*nearby_entity) {
-                    continue;
-                }
+                let item = match items.get(*nearby_entity) {
+                    Some(i) => i,
+                    None => continue,
+                };
 
                 let nearby_actor = match actors.borrow().get(*nearby_entity) {
                     Some(a) => a,
@@ -97,8 +160,13 @@ impl System for InventorySystem {
                     continue;
                 }
 
-                inventory.add_item();
+                inventory.add_item(item.kind());
                 queue.remove_entity(*nearby_entity);
+                queue.make_noise(Noise {
+                    source: Some(*entity),
+                    position: actor.position(),
+                    loudness: PICKUP_NOISE,
+                });
             }
         }
     }
@@ -47,7 +47,11 @@ impl System for InventoryDisplaySystem {
 
             self.string.clear();
             for item in inventory.items() {
-                self.string.push(*item);
+                self.string.push(item.symbol());
+            }
+
+            if let Some(equipped) = inventory.equipped() {
+                gui.write(&format!("Equipped: {}", equipped.name()));
             }
 
             let height = gui.write(&self.string);
@@ -1 +1,166 @@
-pub struct Item {}
+use super::{
+    status_effect::{StatusEffect, StatusEffectKind},
+    weapon::Weapon,
+};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum KeyKind {
+    Bronze,
+    Silver,
+    Gold,
+}
+
+impl KeyKind {
+    pub const ALL: [KeyKind; 3] = [KeyKind::Bronze, KeyKind::Silver, KeyKind::Gold];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyKind::Bronze => "Bronze Key",
+            KeyKind::Silver => "Silver Key",
+            KeyKind::Gold => "Gold Key",
+        }
+    }
+
+    // The tint of doors that are locked with this kind of key.
+    pub fn color(&self) -> [f32; 3] {
+        match self {
+            KeyKind::Bronze => [0.8, 0.5, 0.2],
+            KeyKind::Silver => [0.75, 0.75, 0.85],
+            KeyKind::Gold => [1.0, 0.85, 0.2],
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ItemKind {
+    Trinket,
+    Bow,
+    Wand,
+    ThrowingKnife,
+    Sword,
+    Spear,
+    Hammer,
+    Pickaxe,
+    RegenerationPotion,
+    HastePotion,
+    InvisibilityPotion,
+    Key(KeyKind),
+}
+
+impl ItemKind {
+    // Keys are left out, they are placed along with the doors that they unlock.
+    pub const ALL: [ItemKind; 11] = [
+        ItemKind::Trinket,
+        ItemKind::Bow,
+        ItemKind::Wand,
+        ItemKind::ThrowingKnife,
+        ItemKind::Sword,
+        ItemKind::Spear,
+        ItemKind::Hammer,
+        ItemKind::Pickaxe,
+        ItemKind::RegenerationPotion,
+        ItemKind::HastePotion,
+        ItemKind::InvisibilityPotion,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ItemKind::Trinket => "Trinket",
+            ItemKind::Bow => "Bow",
+            ItemKind::Wand => "Wand",
+            ItemKind::ThrowingKnife => "Throwing Knife",
+            ItemKind::Sword => "Sword",
+            ItemKind::Spear => "Spear",
+            ItemKind::Hammer => "Hammer",
+            ItemKind::Pickaxe => "Pickaxe",
+            ItemKind::RegenerationPotion => "Potion of Regeneration",
+            ItemKind::HastePotion => "Potion of Haste",
+            ItemKind::InvisibilityPotion => "Potion of Invisibility",
+            ItemKind::Key(kind) => kind.name(),
+        }
+    }
+
+    pub fn symbol(&self) -> char {
+        match self {
+            ItemKind::Trinket => '*',
+            ItemKind::Bow => ')',
+            ItemKind::Wand => '/',
+            ItemKind::ThrowingKnife => '|',
+            ItemKind::Sword => '\\',
+            ItemKind::Spear => '^',
+            ItemKind::Hammer => 'T',
+            ItemKind::Pickaxe => '(',
+            ItemKind::RegenerationPotion | ItemKind::HastePotion | ItemKind::InvisibilityPotion => {
+                '!'
+            }
+            ItemKind::Key(_) => '&',
+        }
+    }
+
+    pub fn weapon(&self) -> Option<Weapon> {
+        match self {
+            ItemKind::Bow => Some(Weapon::BOW),
+            ItemKind::Wand => Some(Weapon::WAND),
+            ItemKind::ThrowingKnife => Some(Weapon::THROWING_KNIFE),
+            ItemKind::Sword => Some(Weapon::SWORD),
+            ItemKind::Spear => Some(Weapon::SPEAR),
+            ItemKind::Hammer => Some(Weapon::HAMMER),
+            ItemKind::Pickaxe => Some(Weapon::PICKAXE),
+            _ => None,
+        }
+    }
+
+    // The effect applied to whoever uses the item.
+    pub fn effect(&self) -> Option<StatusEffect> {
+        match self {
+            ItemKind::RegenerationPotion => {
+                Some(StatusEffect::new(StatusEffectKind::Regeneration, 10.0, 3.0))
+            }
+            ItemKind::HastePotion => Some(StatusEffect::new(StatusEffectKind::Haste, 8.0, 0.5)),
+            ItemKind::InvisibilityPotion => {
+                Some(StatusEffect::new(StatusEffectKind::Invisibility, 10.0, 0.0))
+            }
+            _ => None,
+        }
+    }
+
+    // How quickly the item breaks blocks, items that can't dig at all have no mining power.
+    pub fn mining_power(&self) -> f32 {
+        match self {
+            ItemKind::Pickaxe => 1.0,
+            ItemKind::Hammer => 0.5,
+            _ => 0.0,
+        }
+    }
+
+    // How much light the item gives off while it is lying on the ground.
+    pub fn light_level(&self) -> u8 {
+        match self {
+            ItemKind::Wand => 6,
+            ItemKind::RegenerationPotion | ItemKind::HastePotion | ItemKind::InvisibilityPotion => {
+                4
+            }
+            ItemKind::Key(_) => 3,
+            _ => 0,
+        }
+    }
+
+    // Thrown items and potions leave the inventory when they are used.
+    pub fn is_consumed_on_use(&self) -> bool {
+        matches!(self, ItemKind::ThrowingKnife) || self.effect().is_some()
+    }
+}
+
+pub struct Item {
+    kind: ItemKind,
+}
+
+impl Item {
+    pub fn new(kind: ItemKind) -> Self {
+        Self { kind }
+    }
+
+    pub fn kind(&self) -> ItemKind {
+        self.kind
+    }
+}
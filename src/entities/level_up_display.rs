@@ -0,0 +1,79 @@
+use std::borrow::BorrowMut;
+
+use winit::event::VirtualKeyCode;
+
+use crate::{chunk::Chunk, gfx::gui::Gui, input::Input};
+
+use super::{
+    attributes::{Attribute, Attributes},
+    ecs::{Ecs, System},
+    experience::Experience,
+};
+
+// Keys for spending points, in the same order as Attribute::ALL.
+const SPEND_KEYS: [VirtualKeyCode; 3] = [VirtualKeyCode::J, VirtualKeyCode::K, VirtualKeyCode::L];
+const SPEND_KEY_NAMES: [char; 3] = ['J', 'K', 'L'];
+
+pub struct LevelUpDisplay {}
+
+pub struct LevelUpDisplaySystem {}
+
+impl System for LevelUpDisplaySystem {
+    fn update(
+        &mut self,
+        ecs: &mut Ecs,
+        _chunk: &mut Chunk,
+        input: &mut Input,
+        gui: &mut Gui,
+        _delta_time: f32,
+    ) {
+        let Ecs {
+            manager,
+            entity_cache,
+            ..
+        } = ecs;
+
+        if !manager.get_entities_with_both::<Experience, LevelUpDisplay>(entity_cache) {
+            return;
+        }
+
+        let mut experiences = manager.borrow_components::<Experience>().unwrap();
+        let mut attributes = manager.borrow_components::<Attributes>();
+
+        for entity in entity_cache {
+            let experience = experiences.borrow_mut().get_mut(*entity).unwrap();
+            let attributes = attributes.as_mut().and_then(|a| a.get_mut(*entity));
+
+            // The level up screen is shown while there are points to spend.
+            match attributes {
+                Some(attributes) if experience.unspent_points() > 0 => {
+                    for (i, attribute) in Attribute::ALL.iter().enumerate().rev() {
+                        if input.was_key_pressed(SPEND_KEYS[i]) && experience.spend_point() {
+                            attributes.increase(*attribute);
+                        }
+
+                        gui.write(&format!(
+                            "{}: {} {}",
+                            SPEND_KEY_NAMES[i],
+                            attribute.name(),
+                            attributes.get(*attribute)
+                        ));
+                    }
+
+                    gui.write(&format!(
+                        "Level up! Points to spend: {}",
+                        experience.unspent_points()
+                    ));
+                }
+                _ => {}
+            }
+
+            gui.write(&format!(
+                "Level {}: {}/{} XP",
+                experience.level(),
+                experience.experience(),
+                experience.experience_to_next_level()
+            ));
+        }
+    }
+}
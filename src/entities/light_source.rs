@@ -0,0 +1,82 @@
+use std::{borrow::Borrow, collections::HashSet};
+
+use crate::{
+    chunk::{Chunk, BLOCK_SIZE_F},
+    gfx::gui::Gui,
+    input::Input,
+    math::round_vec_to_i32,
+};
+
+use super::{
+    actor::Actor,
+    ecs::{Ecs, System},
+};
+
+pub struct LightSource {
+    level: u8,
+}
+
+impl LightSource {
+    pub fn new(level: u8) -> Self {
+        Self { level }
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+}
+
+// Keeps the chunk's light sources in sync with the entities that emit light.
+pub struct LightingSystem {
+    lit_entities: HashSet<usize>,
+    current_entities: HashSet<usize>,
+}
+
+impl LightingSystem {
+    pub fn new() -> Self {
+        Self {
+            lit_entities: HashSet::new(),
+            current_entities: HashSet::new(),
+        }
+    }
+}
+
+impl System for LightingSystem {
+    fn update(
+        &mut self,
+        ecs: &mut Ecs,
+        chunk: &mut Chunk,
+        _input: &mut Input,
+        _gui: &mut Gui,
+        _delta_time: f32,
+    ) {
+        let Ecs {
+            manager,
+            entity_cache,
+            ..
+        } = ecs;
+
+        self.current_entities.clear();
+
+        if manager.get_entities_with_both::<LightSource, Actor>(entity_cache) {
+            let light_sources = manager.borrow_components::<LightSource>().unwrap();
+            let actors = manager.borrow_components::<Actor>().unwrap();
+
+            for entity in entity_cache.iter() {
+                let light_source = light_sources.borrow().get(*entity).unwrap();
+                let actor = actors.borrow().get(*entity).unwrap();
+                let position = round_vec_to_i32(actor.position() / BLOCK_SIZE_F);
+
+                chunk.set_light_source(*entity, position, light_source.level());
+                self.current_entities.insert(*entity);
+            }
+        }
+
+        // Remove the light of anything that was removed or stopped emitting light.
+        for entity in self.lit_entities.difference(&self.current_entities) {
+            chunk.remove_light_source(*entity);
+        }
+
+        std::mem::swap(&mut self.lit_entities, &mut self.current_entities);
+    }
+}
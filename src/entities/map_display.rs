@@ -0,0 +1,146 @@
+use std::borrow::Borrow;
+
+use image::RgbaImage;
+use winit::event::VirtualKeyCode;
+
+use crate::{
+    chunk::{Chunk, BLOCK_SIZE_F},
+    gfx::{camera::Camera, gui::Gui},
+    input::Input,
+    map::{build_map_image, MapMarker, MapMarkerKind, MAP_PIXELS},
+    math::round_vec_to_i32,
+};
+
+use super::{
+    actor::Actor,
+    ai::Ai,
+    ecs::{Ecs, System},
+    item::Item,
+    player::Player,
+    trigger::Trigger,
+};
+
+const TOGGLE_KEY: VirtualKeyCode = VirtualKeyCode::M;
+
+// Builds the map of the player's floor, shown as a minimap or toggled to fill the screen.
+pub struct MapDisplaySystem {
+    image: RgbaImage,
+    markers: Vec<MapMarker>,
+    is_fullscreen: bool,
+}
+
+impl MapDisplaySystem {
+    pub fn new() -> Self {
+        Self {
+            image: RgbaImage::new(MAP_PIXELS, MAP_PIXELS),
+            markers: Vec::new(),
+            is_fullscreen: false,
+        }
+    }
+
+    pub fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.is_fullscreen
+    }
+}
+
+impl System for MapDisplaySystem {
+    fn update(
+        &mut self,
+        ecs: &mut Ecs,
+        chunk: &mut Chunk,
+        input: &mut Input,
+        _gui: &mut Gui,
+        _delta_time: f32,
+    ) {
+        if input.was_key_pressed(TOGGLE_KEY) {
+            self.is_fullscreen = !self.is_fullscreen;
+        }
+
+        let Ecs {
+            manager,
+            entity_cache,
+            ..
+        } = ecs;
+
+        if !manager.get_entities_with_both::<Player, Actor>(entity_cache) {
+            return;
+        }
+
+        let actors = manager.borrow_components::<Actor>().unwrap();
+
+        let player_actor = match entity_cache.first() {
+            Some(player) => actors.borrow().get(*player).unwrap(),
+            None => return,
+        };
+        let player_position = round_vec_to_i32(player_actor.head_position() / BLOCK_SIZE_F);
+        let heading = Camera::get_direction_vec(player_actor.look_y());
+
+        self.markers.clear();
+        self.markers.push(MapMarker {
+            x: player_position.x,
+            z: player_position.z,
+            kind: MapMarkerKind::Player {
+                heading: cgmath::vec2(heading.x, heading.z),
+            },
+        });
+
+        // Traps and items are remembered once their block has been explored, but enemies are only
+        // shown while they are in view.
+        if manager.get_entities_with_both::<Trigger, Actor>(entity_cache) {
+            let triggers = manager.borrow_components::<Trigger>().unwrap();
+
+            for entity in entity_cache.iter() {
+                if triggers.get(*entity).unwrap().is_hidden() {
+                    continue;
+                }
+
+                let position =
+                    round_vec_to_i32(actors.get(*entity).unwrap().position() / BLOCK_SIZE_F);
+
+                if chunk.is_explored(position.x, position.z) {
+                    self.markers.push(MapMarker {
+                        x: position.x,
+                        z: position.z,
+                        kind: MapMarkerKind::Trap,
+                    });
+                }
+            }
+        }
+
+        if manager.get_entities_with_both::<Item, Actor>(entity_cache) {
+            for entity in entity_cache.iter() {
+                let position =
+                    round_vec_to_i32(actors.get(*entity).unwrap().position() / BLOCK_SIZE_F);
+
+                if chunk.is_explored(position.x, position.z) {
+                    self.markers.push(MapMarker {
+                        x: position.x,
+                        z: position.z,
+                        kind: MapMarkerKind::Item,
+                    });
+                }
+            }
+        }
+
+        if manager.get_entities_with_both::<Ai, Actor>(entity_cache) {
+            for entity in entity_cache.iter() {
+                let position =
+                    round_vec_to_i32(actors.get(*entity).unwrap().position() / BLOCK_SIZE_F);
+
+                if chunk.is_visible(position.x, position.z) {
+                    self.markers.push(MapMarker {
+                        x: position.x,
+                        z: position.z,
+                        kind: MapMarkerKind::Enemy,
+                    });
+                }
+            }
+        }
+
+        self.image = build_map_image(chunk, player_position.y, &self.markers);
+    }
+}
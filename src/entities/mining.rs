@@ -0,0 +1,187 @@
+use std::borrow::{Borrow, BorrowMut};
+
+use winit::event::VirtualKeyCode;
+
+use crate::{
+    chunk::{Block, Chunk, BLOCK_SIZE_F},
+    gfx::{camera::get_look_direction, gui::Gui},
+    input::Input,
+};
+
+use super::{
+    actor::Actor,
+    ecs::{ComponentStore, Ecs, System},
+    inventory::Inventory,
+    perception::{Noise, DIG_NOISE},
+};
+
+const DIG_KEY: VirtualKeyCode = VirtualKeyCode::Z;
+const PLACE_KEY: VirtualKeyCode = VirtualKeyCode::C;
+// In blocks.
+const MINING_RANGE: f32 = 2.0;
+
+// Digging out blocks collects them so that they can be placed somewhere else.
+pub struct Miner {
+    blocks: u32,
+    dig_target: Option<cgmath::Vector3<i32>>,
+    dig_progress: f32,
+}
+
+impl Miner {
+    pub fn new() -> Self {
+        Self {
+            blocks: 0,
+            dig_target: None,
+            dig_progress: 0.0,
+        }
+    }
+
+    fn stop_digging(&mut self) {
+        self.dig_target = None;
+        self.dig_progress = 0.0;
+    }
+}
+
+pub struct MiningSystem {}
+
+impl MiningSystem {
+    // Blocks can't be placed where they would overlap anything.
+    fn is_block_clear(
+        chunk: &Chunk,
+        actors: &ComponentStore<Actor>,
+        position: cgmath::Vector3<i32>,
+    ) -> bool {
+        let block_center =
+            (position.cast::<f32>().unwrap() + cgmath::vec3(0.5, 0.5, 0.5)) * BLOCK_SIZE_F;
+        let block_size = cgmath::vec3(BLOCK_SIZE_F, BLOCK_SIZE_F, BLOCK_SIZE_F);
+
+        chunk
+            .entities_at_block(position.x, position.z)
+            .is_none_or(|mut entities| {
+                entities.all(|e| {
+                    actors
+                        .get(*e)
+                        .is_none_or(|a| !a.intersects(block_center, block_size))
+                })
+            })
+    }
+}
+
+impl System for MiningSystem {
+    fn update(
+        &mut self,
+        ecs: &mut Ecs,
+        chunk: &mut Chunk,
+        input: &mut Input,
+        gui: &mut Gui,
+        delta_time: f32,
+    ) {
+        let Ecs {
+            manager,
+            entity_cache,
+            queue,
+        } = ecs;
+
+        if !manager.get_entities_with_both::<Miner, Actor>(entity_cache) {
+            return;
+        }
+
+        let actors = manager.borrow_components::<Actor>().unwrap();
+        let mut miners = manager.borrow_components::<Miner>().unwrap();
+        let inventories = manager.borrow_components::<Inventory>();
+
+        for entity in entity_cache.iter() {
+            let miner = miners.borrow_mut().get_mut(*entity).unwrap();
+            let actor = actors.borrow().get(*entity).unwrap();
+            let look_direction = get_look_direction(actor.look_x(), actor.look_y());
+
+            let hit = match chunk.raycast(
+                actor.head_position() / BLOCK_SIZE_F,
+                look_direction,
+                MINING_RANGE,
+                None,
+            ) {
+                Some(h) => h,
+                None => {
+                    miner.stop_digging();
+                    continue;
+                }
+            };
+
+            let mining_power = inventories
+                .as_ref()
+                .and_then(|i| i.get(*entity))
+                .and_then(|i| i.equipped())
+                .map_or(0.0, |i| i.mining_power());
+
+            let position = hit.position;
+            let block = chunk.block_at(position.x, position.y, position.z);
+            let break_time = block
+                .break_time()
+                .filter(|_| chunk.is_editable(position.x, position.y, position.z));
+
+            if let Some(break_time) = break_time {
+                if mining_power <= 0.0 {
+                    gui.write("Needs a pickaxe or hammer to dig");
+                } else if miner.dig_target == Some(position) {
+                    gui.write(&format!(
+                        "Z: Dig ({:.0}%)",
+                        miner.dig_progress / break_time * 100.0
+                    ));
+                } else {
+                    gui.write("Z: Dig");
+                }
+            }
+
+            let place_position = hit.last_position;
+            let can_place = miner.blocks > 0
+                && chunk.is_editable(place_position.x, place_position.y, place_position.z)
+                && chunk.block_at(place_position.x, place_position.y, place_position.z)
+                    == Block::Air;
+
+            if can_place {
+                gui.write(&format!("C: Place block ({})", miner.blocks));
+            }
+
+            // Digging keeps going for as long as the key is held on the same block.
+            match break_time {
+                Some(break_time) if mining_power > 0.0 && input.is_key_held(DIG_KEY) => {
+                    if miner.dig_target != Some(position) {
+                        miner.dig_target = Some(position);
+                        miner.dig_progress = 0.0;
+                    }
+
+                    miner.dig_progress += mining_power * delta_time;
+
+                    if miner.dig_progress >= break_time {
+                        chunk.set_block_at(Block::Air, position.x, position.y, position.z);
+                        miner.blocks += 1;
+                        miner.stop_digging();
+
+                        queue.make_noise(Noise {
+                            source: Some(*entity),
+                            position: (position.cast::<f32>().unwrap()
+                                + cgmath::vec3(0.5, 0.5, 0.5))
+                                * BLOCK_SIZE_F,
+                            loudness: DIG_NOISE,
+                        });
+                    }
+                }
+                _ => miner.stop_digging(),
+            }
+
+            if can_place
+                && input.was_key_pressed(PLACE_KEY)
+                && Self::is_block_clear(chunk, &actors, place_position)
+            {
+                chunk.set_block_at(
+                    Block::Wall,
+                    place_position.x,
+                    place_position.y,
+                    place_position.z,
+                );
+                miner.blocks -= 1;
+            }
+        }
+    }
+}
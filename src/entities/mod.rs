@@ -1,12 +1,33 @@
+pub mod ability;
+pub mod ability_display;
 pub mod actor;
-pub mod chase_ai;
+pub mod ai;
+pub mod attributes;
+pub mod behaviour;
+pub mod damage;
+pub mod death;
 pub mod display;
+pub mod door;
 pub mod ecs;
+pub mod editor;
 pub mod entity_instances_system;
+pub mod experience;
+pub mod field_of_view;
 pub mod fighter;
 pub mod health;
 pub mod health_display;
 pub mod inventory;
 pub mod inventory_display;
 pub mod item;
+pub mod level_up_display;
+pub mod light_source;
+pub mod map_display;
+pub mod mining;
+pub mod perception;
 pub mod player;
+pub mod prefab;
+pub mod projectile;
+pub mod status_effect;
+pub mod status_effect_display;
+pub mod trigger;
+pub mod weapon;
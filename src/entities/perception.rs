@@ -0,0 +1,171 @@
+use cgmath::prelude::*;
+use std::borrow::{Borrow, BorrowMut};
+
+use crate::{
+    chunk::{Chunk, BLOCK_SIZE_F},
+    gfx::{camera::Camera, gui::Gui},
+    input::Input,
+};
+
+use super::{
+    actor::Actor,
+    ecs::{Ecs, System},
+    player::Player,
+    status_effect::{StatusEffectKind, StatusEffects},
+};
+
+pub const RUN_NOISE: f32 = 0.75;
+pub const ATTACK_NOISE: f32 = 1.0;
+pub const PICKUP_NOISE: f32 = 0.5;
+pub const DOOR_NOISE: f32 = 0.5;
+pub const DIG_NOISE: f32 = 1.5;
+
+// Something that made a sound this frame. Louder noises can be heard from further away,
+// a loudness of 1.0 can be heard from the edge of a listener's hearing radius.
+pub struct Noise {
+    pub source: Option<usize>,
+    pub position: cgmath::Vector3<f32>,
+    pub loudness: f32,
+}
+
+pub struct Perception {
+    sight_range: f32,
+    field_of_view: f32,
+    hearing_radius: f32,
+    can_see_target: bool,
+    last_known_position: Option<cgmath::Vector3<f32>>,
+}
+
+impl Perception {
+    pub fn new(sight_range: f32, field_of_view: f32, hearing_radius: f32) -> Self {
+        Self {
+            sight_range,
+            field_of_view,
+            hearing_radius,
+            can_see_target: false,
+            last_known_position: None,
+        }
+    }
+
+    pub fn can_see(
+        &self,
+        actor: &Actor,
+        target_position: cgmath::Vector3<f32>,
+        chunk: &Chunk,
+    ) -> bool {
+        let eye_position = actor.head_position();
+        let offset = target_position - eye_position;
+        let distance = offset.magnitude();
+
+        if distance > self.sight_range {
+            return false;
+        }
+
+        if distance <= f32::EPSILON {
+            return true;
+        }
+
+        let flat_offset = cgmath::vec3(offset.x, 0.0, offset.z);
+        if flat_offset.magnitude() > f32::EPSILON {
+            let forward = Camera::get_direction_vec(actor.look_y());
+            let angle = forward.angle(flat_offset.normalize());
+
+            if angle > cgmath::Deg(self.field_of_view * 0.5).into() {
+                return false;
+            }
+        }
+
+        // The target is hidden if there is a block between the eyes and the target.
+        let dir = offset / distance;
+        let block_distance = distance / BLOCK_SIZE_F;
+
+        match chunk.raycast(eye_position / BLOCK_SIZE_F, dir, block_distance, None) {
+            Some(hit) => hit.distance >= block_distance,
+            None => true,
+        }
+    }
+
+    pub fn can_hear(&self, position: cgmath::Vector3<f32>, noise: &Noise) -> bool {
+        (noise.position - position).magnitude() <= self.hearing_radius * noise.loudness
+    }
+
+    pub fn forget_target(&mut self) {
+        self.last_known_position = None;
+    }
+
+    pub fn can_see_target(&self) -> bool {
+        self.can_see_target
+    }
+
+    pub fn last_known_position(&self) -> Option<cgmath::Vector3<f32>> {
+        self.last_known_position
+    }
+}
+
+pub struct PerceptionSystem {}
+
+impl System for PerceptionSystem {
+    fn update(
+        &mut self,
+        ecs: &mut Ecs,
+        chunk: &mut Chunk,
+        _input: &mut Input,
+        _gui: &mut Gui,
+        _delta_time: f32,
+    ) {
+        let Ecs {
+            manager,
+            entity_cache,
+            queue,
+        } = ecs;
+
+        manager.get_entities_with_both::<Player, Actor>(entity_cache);
+
+        let player = match entity_cache.first() {
+            Some(p) => *p,
+            None => return,
+        };
+
+        if !manager.get_entities_with_both::<Perception, Actor>(entity_cache) {
+            return;
+        }
+
+        let actors = manager.borrow_components::<Actor>().unwrap();
+        let mut perceptions = manager.borrow_components::<Perception>().unwrap();
+
+        let player_position = actors.borrow().get(player).unwrap().position();
+        let is_player_invisible = manager
+            .borrow_components::<StatusEffects>()
+            .and_then(|s| s.get(player).map(|s| s.has(StatusEffectKind::Invisibility)))
+            .unwrap_or(false);
+
+        for entity in entity_cache {
+            let actor = actors.borrow().get(*entity).unwrap();
+            let perception = perceptions.borrow_mut().get_mut(*entity).unwrap();
+
+            perception.can_see_target =
+                !is_player_invisible && perception.can_see(actor, player_position, chunk);
+
+            // Getting hit by the target gives away where they are, even from behind.
+            let was_hit_by_target = queue
+                .damages()
+                .iter()
+                .any(|(target, damage)| *target == *entity && damage.source == Some(player));
+
+            if perception.can_see_target || was_hit_by_target {
+                perception.last_known_position = Some(player_position);
+                continue;
+            }
+
+            for noise in queue.noises() {
+                if noise.source == Some(*entity) {
+                    continue;
+                }
+
+                if perception.can_hear(actor.position(), noise) {
+                    perception.last_known_position = Some(noise.position);
+                }
+            }
+        }
+    }
+}
@@ -12,15 +12,28 @@ use crate::{
 use super::{
     actor::Actor,
     ecs::{Ecs, System},
+    perception::{Noise, RUN_NOISE},
 };
 
 const MOUSE_SENSITIVITY: f32 = 0.1;
+const SPRINT_MULTIPLIER: f32 = 1.5;
 
 pub struct Player {}
 
-pub struct PlayerMovementSystem {}
+pub struct PlayerMovementSystem {
+    // Flying players move freely through blocks and up and down, such as in the editor.
+    is_flying: bool,
+}
 
-impl PlayerMovementSystem {}
+impl PlayerMovementSystem {
+    pub fn new() -> Self {
+        Self { is_flying: false }
+    }
+
+    pub fn flying() -> Self {
+        Self { is_flying: true }
+    }
+}
 
 impl System for PlayerMovementSystem {
     fn update(
@@ -34,7 +47,7 @@ impl System for PlayerMovementSystem {
         let Ecs {
             manager,
             entity_cache,
-            ..
+            queue,
         } = ecs;
 
         if !manager.get_entities_with_both::<Player, Actor>(entity_cache) {
@@ -65,11 +78,22 @@ impl System for PlayerMovementSystem {
                 dir_x -= 1.0;
             }
 
-            if actor.grounded() && input.is_key_held(VirtualKeyCode::Space) {
+            let mut dir_y = 0.0;
+
+            if self.is_flying {
+                if input.is_key_held(VirtualKeyCode::Space) {
+                    dir_y += 1.0;
+                }
+
+                if input.is_key_held(VirtualKeyCode::LControl) {
+                    dir_y -= 1.0;
+                }
+            } else if actor.grounded() && input.is_key_held(VirtualKeyCode::Space) {
                 actor.jump();
             }
 
-            let no_clip = input.is_key_held(VirtualKeyCode::V);
+            let no_clip = self.is_flying || input.is_key_held(VirtualKeyCode::V);
+            let is_sprinting = input.is_key_held(VirtualKeyCode::LShift);
 
             let forward = Camera::get_direction_vec(actor.look_y());
             let right = Camera::get_direction_vec(actor.look_y() + 90.0);
@@ -79,21 +103,45 @@ impl System for PlayerMovementSystem {
                 dir = dir.normalize();
             }
 
+            let mut speed = actor.speed();
+
+            if is_sprinting {
+                speed *= SPRINT_MULTIPLIER;
+
+                if dir.magnitude() != 0.0 && actor.grounded() && !self.is_flying {
+                    queue.make_noise(Noise {
+                        source: Some(*entity),
+                        position: actor.position(),
+                        loudness: RUN_NOISE,
+                    });
+                }
+            }
+
             actor.step(
                 *entity,
                 cgmath::vec3(dir.x, 0.0, 0.0),
-                actor.speed() * delta_time,
+                speed * delta_time,
                 chunk,
                 no_clip,
             );
             actor.step(
                 *entity,
                 cgmath::vec3(0.0, 0.0, dir.z),
-                actor.speed() * delta_time,
+                speed * delta_time,
                 chunk,
                 no_clip,
             );
 
+            if dir_y != 0.0 {
+                actor.step(
+                    *entity,
+                    cgmath::vec3(0.0, dir_y, 0.0),
+                    speed * delta_time,
+                    chunk,
+                    no_clip,
+                );
+            }
+
             actor.rotate(
                 input.mouse_delta_y() * MOUSE_SENSITIVITY,
                 -input.mouse_delta_x() * MOUSE_SENSITIVITY,
@@ -0,0 +1,384 @@
+use crate::chunk::BLOCK_SIZE_F;
+
+use super::{
+    ability::{Abilities, AbilityKind, Mana},
+    ability_display::AbilityDisplay,
+    actor::Actor,
+    ai::Ai,
+    attributes::Attributes,
+    behaviour::Behaviour,
+    damage::{Armor, DamageType, Resistances},
+    display::Display,
+    ecs::EntityManager,
+    experience::Experience,
+    fighter::Fighter,
+    health::Health,
+    health_display::HealthDisplay,
+    inventory::Inventory,
+    inventory_display::InventoryDisplay,
+    item::{Item, ItemKind},
+    level_up_display::LevelUpDisplay,
+    light_source::LightSource,
+    mining::Miner,
+    perception::Perception,
+    player::Player,
+    status_effect::StatusEffects,
+    status_effect_display::StatusEffectDisplay,
+    weapon::Weapon,
+};
+
+const PLAYER_REGENERATION: f32 = 0.5;
+const PLAYER_INVULNERABILITY_TIME: f32 = 0.5;
+const ENEMY_INVULNERABILITY_TIME: f32 = 0.2;
+const PLAYER_MANA: f32 = 50.0;
+const PLAYER_MANA_REGENERATION: f32 = 2.0;
+// The fraction of an enemy's max mana that it regains per second.
+const ENEMY_MANA_REGENERATION: f32 = 0.05;
+const CORPSE_TEX_INDEX: u32 = 2;
+const TORCH_TEX_INDEX: u32 = 4;
+const PLAYER_LANTERN_LIGHT: u8 = 10;
+const TORCH_LIGHT: u8 = 14;
+const HUMANOID_SIZE: cgmath::Vector3<f32> = cgmath::vec3(1.0, 1.0, 1.0);
+const ITEM_SIZE: cgmath::Vector3<f32> = cgmath::vec3(1.0, 1.0, 1.0);
+const TORCH_SIZE: cgmath::Vector3<f32> = cgmath::vec3(0.5, 1.0, 0.5);
+const FLEE_DISTANCE: f32 = BLOCK_SIZE_F * 5.0;
+const RANGED_MIN_RANGE: f32 = BLOCK_SIZE_F * 2.0;
+const RANGED_MAX_RANGE: f32 = BLOCK_SIZE_F * 6.0;
+const BLAST_RANGE: f32 = BLOCK_SIZE_F * 1.5;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Prefab {
+    Player,
+    Chaser,
+    Guard,
+    Wanderer,
+    Archer,
+    Mage,
+    Item(ItemKind),
+    Corpse,
+    Torch,
+}
+
+// The stats that make up an enemy, everything else is shared between enemy prefabs.
+struct EnemyData {
+    tex_index: u32,
+    level: u32,
+    speed: f32,
+    health: i32,
+    attack_damage: i32,
+    weapon: Weapon,
+    sight_range: f32,
+    field_of_view: f32,
+    hearing_radius: f32,
+    flee_health: i32,
+    armor: i32,
+    dodge_chance: f32,
+    resistances: &'static [(DamageType, f32)],
+    // Weighted item drops, None entries drop nothing.
+    loot: &'static [(Option<ItemKind>, u32)],
+    abilities: &'static [AbilityKind],
+    mana: f32,
+}
+
+impl Prefab {
+    pub fn spawn(&self, manager: &mut EntityManager, position: cgmath::Vector3<f32>) -> usize {
+        let entity = manager.add_entity();
+
+        match self {
+            Prefab::Player => {
+                manager.add_component_to_entity(entity, Actor::new(position, HUMANOID_SIZE, 6.0));
+                manager.add_component_to_entity(entity, Player {});
+                manager.add_component_to_entity(entity, Fighter::new(25, Weapon::FISTS));
+
+                let mut health = Health::new(100);
+                health.set_regeneration(PLAYER_REGENERATION);
+                health.set_invulnerability_time(PLAYER_INVULNERABILITY_TIME);
+                manager.add_component_to_entity(entity, health);
+
+                manager.add_component_to_entity(entity, Armor::new(0, 0.1));
+                manager.add_component_to_entity(entity, HealthDisplay {});
+                manager.add_component_to_entity(entity, Inventory::new());
+                manager.add_component_to_entity(entity, InventoryDisplay {});
+                manager.add_component_to_entity(entity, StatusEffects::new());
+                manager.add_component_to_entity(entity, StatusEffectDisplay {});
+                manager.add_component_to_entity(entity, Experience::new(1));
+                manager.add_component_to_entity(entity, Attributes::new(0, 0, 0));
+                manager.add_component_to_entity(entity, LevelUpDisplay {});
+                manager.add_component_to_entity(
+                    entity,
+                    Abilities::new(vec![
+                        AbilityKind::Bolt,
+                        AbilityKind::Blast,
+                        AbilityKind::Blink,
+                        AbilityKind::Heal,
+                    ]),
+                );
+                manager.add_component_to_entity(
+                    entity,
+                    Mana::new(PLAYER_MANA, PLAYER_MANA_REGENERATION),
+                );
+                manager.add_component_to_entity(entity, AbilityDisplay {});
+                manager.add_component_to_entity(entity, LightSource::new(PLAYER_LANTERN_LIGHT));
+                manager.add_component_to_entity(entity, Miner::new());
+            }
+            Prefab::Item(kind) => {
+                manager.add_component_to_entity(entity, Actor::new(position, ITEM_SIZE, 0.0));
+                manager.add_component_to_entity(entity, Display::new(0));
+                manager.add_component_to_entity(entity, Item::new(*kind));
+
+                if kind.light_level() > 0 {
+                    manager.add_component_to_entity(entity, LightSource::new(kind.light_level()));
+                }
+            }
+            Prefab::Corpse => {
+                manager.add_component_to_entity(entity, Actor::new(position, HUMANOID_SIZE, 0.0));
+                manager.add_component_to_entity(entity, Display::new(CORPSE_TEX_INDEX));
+            }
+            Prefab::Torch => {
+                manager.add_component_to_entity(entity, Actor::new(position, TORCH_SIZE, 0.0));
+                manager.add_component_to_entity(entity, Display::new(TORCH_TEX_INDEX));
+                manager.add_component_to_entity(entity, LightSource::new(TORCH_LIGHT));
+            }
+            _ => {
+                let data = self.enemy_data().unwrap();
+
+                manager.add_component_to_entity(
+                    entity,
+                    Actor::new(position, HUMANOID_SIZE, data.speed),
+                );
+                manager.add_component_to_entity(entity, Display::new(data.tex_index));
+
+                let mut health = Health::new(data.health);
+                health.set_invulnerability_time(ENEMY_INVULNERABILITY_TIME);
+                manager.add_component_to_entity(entity, health);
+
+                manager.add_component_to_entity(entity, Armor::new(data.armor, data.dodge_chance));
+                manager.add_component_to_entity(entity, Resistances::new(data.resistances));
+                manager.add_component_to_entity(entity, StatusEffects::new());
+                manager.add_component_to_entity(entity, Experience::new(data.level));
+
+                if !data.abilities.is_empty() {
+                    manager
+                        .add_component_to_entity(entity, Abilities::new(data.abilities.to_vec()));
+                    manager.add_component_to_entity(
+                        entity,
+                        Mana::new(data.mana, data.mana * ENEMY_MANA_REGENERATION),
+                    );
+                }
+
+                manager
+                    .add_component_to_entity(entity, Fighter::new(data.attack_damage, data.weapon));
+                manager.add_component_to_entity(
+                    entity,
+                    Perception::new(data.sight_range, data.field_of_view, data.hearing_radius),
+                );
+                manager.add_component_to_entity(entity, Ai::new(self.behaviour(&data), position));
+            }
+        }
+
+        // Remember what the entity was spawned as, for loot, death messages and saving levels.
+        manager.add_component_to_entity(entity, *self);
+
+        entity
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Prefab::Player => "Player",
+            Prefab::Chaser => "Chaser",
+            Prefab::Guard => "Guard",
+            Prefab::Wanderer => "Wanderer",
+            Prefab::Archer => "Archer",
+            Prefab::Mage => "Mage",
+            Prefab::Item(kind) => kind.name(),
+            Prefab::Corpse => "Corpse",
+            Prefab::Torch => "Torch",
+        }
+    }
+
+    pub fn loot_table(&self) -> &'static [(Option<ItemKind>, u32)] {
+        match self.enemy_data() {
+            Some(data) => data.loot,
+            None => &[],
+        }
+    }
+
+    fn enemy_data(&self) -> Option<EnemyData> {
+        match self {
+            Prefab::Chaser => Some(EnemyData {
+                tex_index: 1,
+                level: 1,
+                speed: 4.0,
+                health: 50,
+                attack_damage: 10,
+                weapon: Weapon::CLAWS,
+                sight_range: 30.0,
+                field_of_view: 120.0,
+                hearing_radius: 15.0,
+                flee_health: 0,
+                armor: 0,
+                dodge_chance: 0.0,
+                resistances: &[(DamageType::Poison, 0.5)],
+                loot: &[
+                    (None, 2),
+                    (Some(ItemKind::RegenerationPotion), 1),
+                    (Some(ItemKind::ThrowingKnife), 1),
+                ],
+                abilities: &[],
+                mana: 0.0,
+            }),
+            Prefab::Guard => Some(EnemyData {
+                tex_index: 1,
+                level: 2,
+                speed: 4.0,
+                health: 75,
+                attack_damage: 10,
+                weapon: Weapon::SPEAR,
+                sight_range: 36.0,
+                field_of_view: 90.0,
+                hearing_radius: 20.0,
+                flee_health: 0,
+                armor: 5,
+                dodge_chance: 0.0,
+                resistances: &[(DamageType::Fire, -0.5)],
+                loot: &[
+                    (None, 1),
+                    (Some(ItemKind::Sword), 1),
+                    (Some(ItemKind::Spear), 1),
+                    (Some(ItemKind::Hammer), 1),
+                ],
+                abilities: &[],
+                mana: 0.0,
+            }),
+            Prefab::Wanderer => Some(EnemyData {
+                tex_index: 1,
+                level: 1,
+                speed: 5.0,
+                health: 40,
+                attack_damage: 5,
+                weapon: Weapon::FISTS,
+                sight_range: 24.0,
+                field_of_view: 150.0,
+                hearing_radius: 12.0,
+                flee_health: 20,
+                armor: 0,
+                dodge_chance: 0.25,
+                resistances: &[],
+                loot: &[
+                    (None, 2),
+                    (Some(ItemKind::HastePotion), 1),
+                    (Some(ItemKind::InvisibilityPotion), 1),
+                    (Some(ItemKind::Pickaxe), 1),
+                ],
+                abilities: &[],
+                mana: 0.0,
+            }),
+            Prefab::Archer => Some(EnemyData {
+                tex_index: 1,
+                level: 2,
+                speed: 4.0,
+                health: 35,
+                attack_damage: 0,
+                weapon: Weapon::BOW,
+                sight_range: 36.0,
+                field_of_view: 120.0,
+                hearing_radius: 15.0,
+                flee_health: 10,
+                armor: 2,
+                dodge_chance: 0.1,
+                resistances: &[(DamageType::Magic, -0.5)],
+                loot: &[
+                    (None, 1),
+                    (Some(ItemKind::Bow), 2),
+                    (Some(ItemKind::ThrowingKnife), 1),
+                ],
+                abilities: &[],
+                mana: 0.0,
+            }),
+            Prefab::Mage => Some(EnemyData {
+                tex_index: 1,
+                level: 3,
+                speed: 3.5,
+                health: 40,
+                attack_damage: 0,
+                weapon: Weapon::WAND,
+                sight_range: 36.0,
+                field_of_view: 120.0,
+                hearing_radius: 18.0,
+                flee_health: 0,
+                armor: 0,
+                dodge_chance: 0.05,
+                resistances: &[(DamageType::Magic, 0.5), (DamageType::Fire, 0.25)],
+                loot: &[
+                    (None, 1),
+                    (Some(ItemKind::Wand), 2),
+                    (Some(ItemKind::RegenerationPotion), 1),
+                ],
+                abilities: &[AbilityKind::Heal, AbilityKind::Blink, AbilityKind::Blast],
+                mana: 60.0,
+            }),
+            _ => None,
+        }
+    }
+
+    fn behaviour(&self, data: &EnemyData) -> Behaviour {
+        let idle = match self {
+            Prefab::Guard => Behaviour::Patrol(vec![
+                cgmath::vec3(BLOCK_SIZE_F * 2.0, 0.0, 0.0),
+                cgmath::vec3(BLOCK_SIZE_F * 2.0, 0.0, BLOCK_SIZE_F * 2.0),
+                cgmath::vec3(0.0, 0.0, BLOCK_SIZE_F * 2.0),
+                cgmath::vec3(0.0, 0.0, 0.0),
+            ]),
+            Prefab::Wanderer => Behaviour::Wander(BLOCK_SIZE_F * 4.0),
+            _ => Behaviour::Sequence(vec![
+                Behaviour::AwayFromHome(BLOCK_SIZE_F),
+                Behaviour::ReturnHome,
+            ]),
+        };
+
+        let mut children = vec![Behaviour::Sequence(vec![
+            Behaviour::HealthBelow(data.flee_health),
+            Behaviour::TargetWithin(FLEE_DISTANCE),
+            Behaviour::Flee,
+        ])];
+
+        // Abilities take priority over regular attacks whenever they can be used.
+        for ability in data.abilities {
+            children.push(match ability {
+                AbilityKind::Heal => Behaviour::Sequence(vec![
+                    Behaviour::HealthBelow(data.health / 2),
+                    Behaviour::UseAbility(*ability),
+                ]),
+                AbilityKind::Blink => Behaviour::Sequence(vec![
+                    Behaviour::CanSeeTarget,
+                    Behaviour::TargetWithin(RANGED_MIN_RANGE),
+                    Behaviour::UseAbility(*ability),
+                ]),
+                AbilityKind::Blast => Behaviour::Sequence(vec![
+                    Behaviour::CanSeeTarget,
+                    Behaviour::TargetWithin(BLAST_RANGE),
+                    Behaviour::UseAbility(*ability),
+                ]),
+                AbilityKind::Bolt => Behaviour::Sequence(vec![
+                    Behaviour::CanSeeTarget,
+                    Behaviour::TargetWithin(RANGED_MAX_RANGE),
+                    Behaviour::UseAbility(*ability),
+                ]),
+            });
+        }
+
+        children.extend([
+            if data.weapon.projectile().is_some() {
+                Behaviour::RangedAttack(RANGED_MIN_RANGE, RANGED_MAX_RANGE)
+            } else {
+                Behaviour::Attack(data.weapon.reach() + HUMANOID_SIZE.x * 0.5)
+            },
+            Behaviour::Sequence(vec![Behaviour::CanSeeTarget, Behaviour::Chase]),
+            Behaviour::Search,
+            idle,
+            Behaviour::Idle,
+        ]);
+
+        Behaviour::Selector(children)
+    }
+}
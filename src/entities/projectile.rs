@@ -0,0 +1,288 @@
+use cgmath::prelude::*;
+use std::{
+    borrow::{Borrow, BorrowMut},
+    collections::HashSet,
+};
+
+use crate::{
+    chunk::{Chunk, BLOCK_SIZE_F},
+    gfx::gui::Gui,
+    input::Input,
+    ray::Ray,
+};
+
+use super::{
+    actor::{Actor, GRAVITY},
+    damage::{DamageInfo, DamageType},
+    display::Display,
+    ecs::{ComponentStore, Ecs, EntityManager, System},
+    health::Health,
+    status_effect::{StatusEffect, StatusEffectKind},
+};
+
+const PROJECTILE_SIZE: cgmath::Vector3<f32> = cgmath::vec3(0.25, 0.25, 0.25);
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ProjectileKind {
+    Arrow,
+    Bolt,
+    Knife,
+}
+
+impl ProjectileKind {
+    pub fn speed(&self) -> f32 {
+        match self {
+            ProjectileKind::Arrow => 30.0,
+            ProjectileKind::Bolt => 20.0,
+            ProjectileKind::Knife => 20.0,
+        }
+    }
+
+    pub fn damage(&self) -> i32 {
+        match self {
+            ProjectileKind::Arrow => 20,
+            ProjectileKind::Bolt => 15,
+            ProjectileKind::Knife => 25,
+        }
+    }
+
+    pub fn damage_type(&self) -> DamageType {
+        match self {
+            ProjectileKind::Bolt => DamageType::Magic,
+            _ => DamageType::Physical,
+        }
+    }
+
+    pub fn crit_chance(&self) -> f32 {
+        match self {
+            ProjectileKind::Arrow => 0.1,
+            ProjectileKind::Bolt => 0.0,
+            ProjectileKind::Knife => 0.2,
+        }
+    }
+
+    pub fn knockback(&self) -> f32 {
+        match self {
+            ProjectileKind::Arrow => 3.0,
+            ProjectileKind::Bolt => 0.0,
+            ProjectileKind::Knife => 2.0,
+        }
+    }
+
+    pub fn effect(&self) -> Option<StatusEffect> {
+        match self {
+            ProjectileKind::Arrow => Some(StatusEffect::new(StatusEffectKind::Slow, 2.0, 0.3)),
+            ProjectileKind::Bolt => Some(StatusEffect::new(StatusEffectKind::Burning, 3.0, 3.0)),
+            ProjectileKind::Knife => None,
+        }
+    }
+
+    pub fn has_gravity(&self) -> bool {
+        !matches!(self, ProjectileKind::Bolt)
+    }
+
+    pub fn lifetime(&self) -> f32 {
+        match self {
+            ProjectileKind::Arrow => 3.0,
+            ProjectileKind::Bolt => 2.0,
+            ProjectileKind::Knife => 2.0,
+        }
+    }
+
+    pub fn tex_index(&self) -> u32 {
+        0
+    }
+}
+
+pub struct Projectile {
+    owner: usize,
+    kind: ProjectileKind,
+    velocity: cgmath::Vector3<f32>,
+    lifetime: f32,
+}
+
+impl Projectile {
+    pub fn new(owner: usize, kind: ProjectileKind, dir: cgmath::Vector3<f32>) -> Self {
+        Self {
+            owner,
+            kind,
+            velocity: dir.normalize_to(kind.speed()),
+            lifetime: kind.lifetime(),
+        }
+    }
+
+    pub fn spawn(self, manager: &mut EntityManager, position: cgmath::Vector3<f32>) -> usize {
+        let entity = manager.add_entity();
+
+        let mut actor = Actor::new(position, PROJECTILE_SIZE, 0.0);
+        actor.set_gravity(false);
+
+        manager.add_component_to_entity(entity, actor);
+        manager.add_component_to_entity(entity, Display::new(self.kind.tex_index()));
+        manager.add_component_to_entity(entity, self);
+
+        entity
+    }
+
+    pub fn owner(&self) -> usize {
+        self.owner
+    }
+
+    // The damage dealt when hitting something while travelling in a direction.
+    pub fn damage(&self, dir: cgmath::Vector3<f32>) -> DamageInfo {
+        let mut damage = DamageInfo::new(
+            Some(self.owner),
+            self.kind.damage(),
+            self.kind.damage_type(),
+        );
+        damage.crit_chance = self.kind.crit_chance();
+        damage.knockback = dir * self.kind.knockback();
+        damage.effect = self.kind.effect();
+
+        damage
+    }
+}
+
+pub struct ProjectileSystem {
+    nearby_entities: HashSet<usize>,
+}
+
+impl ProjectileSystem {
+    pub fn new() -> Self {
+        Self {
+            nearby_entities: HashSet::new(),
+        }
+    }
+
+    // Find the closest nearby actor with health that the projectile passes through this step.
+    fn get_hit_entity(
+        &self,
+        owner: usize,
+        ray: &Ray,
+        max_distance: f32,
+        actors: &ComponentStore<Actor>,
+        healths: &ComponentStore<Health>,
+    ) -> Option<(usize, f32)> {
+        let mut closest_hit = None;
+
+        for nearby_entity in &self.nearby_entities {
+            if *nearby_entity == owner {
+                continue;
+            }
+
+            if !healths.has(*nearby_entity) {
+                continue;
+            }
+
+            let nearby_actor = match actors.get(*nearby_entity) {
+                Some(a) => a,
+                None => continue,
+            };
+
+            let distance =
+                match ray.intersection_distance(nearby_actor.position(), nearby_actor.size()) {
+                    Some(d) => d.max(0.0),
+                    None => continue,
+                };
+
+            if distance > max_distance {
+                continue;
+            }
+
+            match closest_hit {
+                Some((_, closest_distance)) if closest_distance <= distance => {}
+                _ => closest_hit = Some((*nearby_entity, distance)),
+            }
+        }
+
+        closest_hit
+    }
+}
+
+impl System for ProjectileSystem {
+    fn update(
+        &mut self,
+        ecs: &mut Ecs,
+        chunk: &mut Chunk,
+        _input: &mut Input,
+        _gui: &mut Gui,
+        delta_time: f32,
+    ) {
+        let Ecs {
+            manager,
+            entity_cache,
+            queue,
+        } = ecs;
+
+        if !manager.get_entities_with_both::<Projectile, Actor>(entity_cache) {
+            return;
+        }
+
+        let mut projectiles = manager.borrow_components::<Projectile>().unwrap();
+        let mut actors = manager.borrow_components::<Actor>().unwrap();
+        let healths = manager.borrow_components::<Health>();
+
+        for entity in entity_cache {
+            let projectile = projectiles.borrow_mut().get_mut(*entity).unwrap();
+
+            projectile.lifetime -= delta_time;
+
+            if projectile.lifetime <= 0.0 {
+                queue.remove_entity(*entity);
+                continue;
+            }
+
+            if projectile.kind.has_gravity() {
+                projectile.velocity.y -= GRAVITY * delta_time;
+            }
+
+            let movement = projectile.velocity * delta_time;
+            let distance = movement.magnitude();
+
+            if distance <= f32::EPSILON {
+                continue;
+            }
+
+            let dir = movement / distance;
+            let position = actors.borrow().get(*entity).unwrap().position();
+
+            let wall_distance = chunk
+                .raycast(position / BLOCK_SIZE_F, dir, distance / BLOCK_SIZE_F, None)
+                .map(|hit| hit.distance * BLOCK_SIZE_F)
+                .filter(|wall_distance| *wall_distance <= distance);
+
+            let max_distance = wall_distance.unwrap_or(distance);
+            actors.borrow().get(*entity).unwrap().get_entities_in_range(
+                chunk,
+                max_distance + 1.0,
+                &mut self.nearby_entities,
+            );
+
+            let ray = Ray { position, dir };
+            let hit = match healths {
+                Some(ref healths) => {
+                    self.get_hit_entity(projectile.owner(), &ray, max_distance, &actors, healths)
+                }
+                None => None,
+            };
+
+            if let Some((hit_entity, _)) = hit {
+                queue.deal_damage(hit_entity, projectile.damage(dir));
+
+                queue.remove_entity(*entity);
+                continue;
+            }
+
+            if wall_distance.is_some() {
+                queue.remove_entity(*entity);
+                continue;
+            }
+
+            actors
+                .borrow_mut()
+                .get_mut(*entity)
+                .unwrap()
+                .step(*entity, dir, distance, chunk, true);
+        }
+    }
+}
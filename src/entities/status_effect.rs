@@ -0,0 +1,251 @@
+use std::borrow::BorrowMut;
+
+use crate::{chunk::Chunk, gfx::gui::Gui, input::Input};
+
+use super::{
+    actor::Actor,
+    damage::{DamageInfo, DamageType},
+    ecs::{Ecs, System},
+    fighter::Fighter,
+    health::Health,
+};
+
+const MAX_POISON_STACKS: u32 = 5;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum StatusEffectKind {
+    Poison,
+    Regeneration,
+    Burning,
+    Slow,
+    Haste,
+    Stun,
+    Invisibility,
+}
+
+// What happens when an effect is applied to an entity that already has it.
+pub enum Stacking {
+    // Keep the longest duration and the strongest strength.
+    Refresh,
+    // Add a stack up to a maximum, each stack multiplies the effect's strength.
+    Stack(u32),
+    // Add the new duration to the remaining duration.
+    Extend,
+}
+
+impl StatusEffectKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatusEffectKind::Poison => "Poison",
+            StatusEffectKind::Regeneration => "Regeneration",
+            StatusEffectKind::Burning => "Burning",
+            StatusEffectKind::Slow => "Slow",
+            StatusEffectKind::Haste => "Haste",
+            StatusEffectKind::Stun => "Stun",
+            StatusEffectKind::Invisibility => "Invisibility",
+        }
+    }
+
+    // How often effects that act over time apply themselves, in seconds.
+    pub fn tick_rate(&self) -> Option<f32> {
+        match self {
+            StatusEffectKind::Poison => Some(1.0),
+            StatusEffectKind::Regeneration => Some(1.0),
+            StatusEffectKind::Burning => Some(0.5),
+            _ => None,
+        }
+    }
+
+    pub fn stacking(&self) -> Stacking {
+        match self {
+            StatusEffectKind::Poison => Stacking::Stack(MAX_POISON_STACKS),
+            StatusEffectKind::Invisibility => Stacking::Extend,
+            _ => Stacking::Refresh,
+        }
+    }
+}
+
+// Strength is the amount of damage or healing per tick for effects that tick, and the fraction
+// of speed lost or gained for effects that change speed.
+#[derive(Copy, Clone, Debug)]
+pub struct StatusEffect {
+    kind: StatusEffectKind,
+    duration: f32,
+    strength: f32,
+    stacks: u32,
+    tick_timer: f32,
+}
+
+impl StatusEffect {
+    pub const fn new(kind: StatusEffectKind, duration: f32, strength: f32) -> Self {
+        Self {
+            kind,
+            duration,
+            strength,
+            stacks: 1,
+            tick_timer: 0.0,
+        }
+    }
+
+    pub fn kind(&self) -> StatusEffectKind {
+        self.kind
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    pub fn stacks(&self) -> u32 {
+        self.stacks
+    }
+
+    fn amount(&self) -> i32 {
+        (self.strength * self.stacks as f32).round() as i32
+    }
+
+    // Advance the effect, returning how many times it ticked.
+    fn update(&mut self, delta_time: f32) -> u32 {
+        self.duration -= delta_time;
+
+        let tick_rate = match self.kind.tick_rate() {
+            Some(t) => t,
+            None => return 0,
+        };
+
+        self.tick_timer += delta_time;
+
+        let mut ticks = 0;
+        while self.tick_timer >= tick_rate {
+            self.tick_timer -= tick_rate;
+            ticks += 1;
+        }
+
+        ticks
+    }
+}
+
+pub struct StatusEffects {
+    effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    pub fn new() -> Self {
+        Self {
+            effects: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, effect: StatusEffect) {
+        let existing = match self.effects.iter_mut().find(|e| e.kind == effect.kind) {
+            Some(e) => e,
+            None => {
+                self.effects.push(effect);
+                return;
+            }
+        };
+
+        match effect.kind.stacking() {
+            Stacking::Refresh => {
+                existing.duration = existing.duration.max(effect.duration);
+                existing.strength = existing.strength.max(effect.strength);
+            }
+            Stacking::Stack(max_stacks) => {
+                existing.duration = existing.duration.max(effect.duration);
+                existing.stacks = (existing.stacks + 1).min(max_stacks);
+            }
+            Stacking::Extend => existing.duration += effect.duration,
+        }
+    }
+
+    pub fn has(&self, kind: StatusEffectKind) -> bool {
+        self.effects.iter().any(|e| e.kind == kind)
+    }
+
+    // Scales both movement and attack speed.
+    pub fn speed_multiplier(&self) -> f32 {
+        let mut multiplier = 1.0;
+
+        for effect in &self.effects {
+            match effect.kind {
+                StatusEffectKind::Slow => multiplier *= 1.0 - effect.strength,
+                StatusEffectKind::Haste => multiplier *= 1.0 + effect.strength,
+                StatusEffectKind::Stun => multiplier = 0.0,
+                _ => {}
+            }
+        }
+
+        multiplier.max(0.0)
+    }
+
+    pub fn effects(&self) -> &Vec<StatusEffect> {
+        &self.effects
+    }
+}
+
+pub struct StatusEffectSystem {}
+
+impl System for StatusEffectSystem {
+    fn update(
+        &mut self,
+        ecs: &mut Ecs,
+        _chunk: &mut Chunk,
+        _input: &mut Input,
+        _gui: &mut Gui,
+        delta_time: f32,
+    ) {
+        let Ecs {
+            manager,
+            entity_cache,
+            queue,
+        } = ecs;
+
+        if !manager.get_entities_with::<StatusEffects>(entity_cache) {
+            return;
+        }
+
+        let mut status_effects = manager.borrow_components::<StatusEffects>().unwrap();
+        let mut healths = manager.borrow_components::<Health>();
+        let mut actors = manager.borrow_components::<Actor>();
+        let mut fighters = manager.borrow_components::<Fighter>();
+
+        for entity in entity_cache {
+            let status_effects = status_effects.borrow_mut().get_mut(*entity).unwrap();
+
+            for effect in status_effects.effects.iter_mut() {
+                let ticks = effect.update(delta_time);
+
+                for _ in 0..ticks {
+                    match effect.kind {
+                        StatusEffectKind::Poison => queue.deal_damage(
+                            *entity,
+                            DamageInfo::new(None, effect.amount(), DamageType::Poison),
+                        ),
+                        StatusEffectKind::Burning => queue.deal_damage(
+                            *entity,
+                            DamageInfo::new(None, effect.amount(), DamageType::Fire),
+                        ),
+                        StatusEffectKind::Regeneration => {
+                            if let Some(health) = healths.as_mut().and_then(|h| h.get_mut(*entity))
+                            {
+                                health.heal(effect.amount());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            status_effects.effects.retain(|e| e.duration > 0.0);
+
+            let speed_multiplier = status_effects.speed_multiplier();
+
+            if let Some(actor) = actors.as_mut().and_then(|a| a.get_mut(*entity)) {
+                actor.set_speed_multiplier(speed_multiplier);
+            }
+
+            if let Some(fighter) = fighters.as_mut().and_then(|f| f.get_mut(*entity)) {
+                fighter.set_attack_speed(speed_multiplier);
+            }
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use std::borrow::Borrow;
+
+use crate::{chunk::Chunk, gfx::gui::Gui, input::Input};
+
+use super::{
+    ecs::{Ecs, System},
+    status_effect::StatusEffects,
+};
+
+pub struct StatusEffectDisplay {}
+
+pub struct StatusEffectDisplaySystem {}
+
+impl System for StatusEffectDisplaySystem {
+    fn update(
+        &mut self,
+        ecs: &mut Ecs,
+        _chunk: &mut Chunk,
+        _input: &mut Input,
+        gui: &mut Gui,
+        _delta_time: f32,
+    ) {
+        let Ecs {
+            manager,
+            entity_cache,
+            ..
+        } = ecs;
+
+        if !manager.get_entities_with_both::<StatusEffects, StatusEffectDisplay>(entity_cache) {
+            return;
+        }
+
+        let status_effects = manager.borrow_components::<StatusEffects>().unwrap();
+
+        for entity in entity_cache {
+            let status_effects = status_effects.borrow().get(*entity).unwrap();
+
+            for effect in status_effects.effects() {
+                if effect.stacks() > 1 {
+                    gui.write(&format!(
+                        "{} x{}: {:.1}s",
+                        effect.kind().name(),
+                        effect.stacks(),
+                        effect.duration()
+                    ));
+                } else {
+                    gui.write(&format!(
+                        "{}: {:.1}s",
+                        effect.kind().name(),
+                        effect.duration()
+                    ));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,413 @@
+use std::{
+    borrow::{Borrow, BorrowMut},
+    collections::HashSet,
+};
+
+use cgmath::prelude::*;
+
+use crate::{
+    chunk::{Block, Chunk, BLOCK_SIZE_F},
+    gfx::gui::Gui,
+    input::Input,
+    math::round_vec_to_i32,
+    rng::Rng,
+};
+
+use super::{
+    actor::Actor,
+    damage::{DamageInfo, DamageType},
+    display::Display,
+    ecs::{CommandQueue, ComponentStore, Ecs, EntityManager, System},
+    health::Health,
+    perception::Noise,
+    player::Player,
+    prefab::Prefab,
+    projectile::{Projectile, ProjectileKind},
+};
+
+pub const TRAP_TEX_INDEX: u32 = 3;
+const TRIGGER_SIZE: cgmath::Vector3<f32> = cgmath::vec3(2.0, 1.0, 2.0);
+const DETECTION_RANGE: f32 = BLOCK_SIZE_F * 2.0;
+// The chance per second of noticing a hidden trap that is in range.
+const DETECTION_CHANCE: f32 = 0.5;
+const SPIKE_DAMAGE: i32 = 15;
+const ALARM_NOISE: f32 = 3.0;
+const TRAP_REARM_TIME: f32 = 2.0;
+// How far away from an arrow trap the wall that it shoots from can be, in blocks.
+const ARROW_TRAP_RANGE: i32 = 5;
+// How far away from an ambush trap the enemy that it spawns can appear, in blocks.
+const AMBUSH_RANGE: i32 = 3;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TriggerAction {
+    Spikes {
+        damage: i32,
+    },
+    // Shoots an arrow from the origin at whoever set off the trap.
+    ArrowTrap {
+        origin: cgmath::Vector3<f32>,
+    },
+    Teleporter {
+        destination: cgmath::Vector3<f32>,
+    },
+    Alarm {
+        loudness: f32,
+    },
+    OpenDoor {
+        block: cgmath::Vector3<i32>,
+    },
+    SpawnEnemy {
+        prefab: Prefab,
+        position: cgmath::Vector3<f32>,
+    },
+}
+
+impl TriggerAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TriggerAction::Spikes { .. } => "Spike Trap",
+            TriggerAction::ArrowTrap { .. } => "Arrow Trap",
+            TriggerAction::Teleporter { .. } => "Teleporter",
+            TriggerAction::Alarm { .. } => "Alarm",
+            TriggerAction::OpenDoor { .. } => "Pressure Plate",
+            TriggerAction::SpawnEnemy { .. } => "Ambush",
+        }
+    }
+}
+
+pub struct Trigger {
+    action: TriggerAction,
+    is_hidden: bool,
+    // Triggers without a rearm time only fire once.
+    rearm_time: Option<f32>,
+    rearm_timer: f32,
+    is_spent: bool,
+    // Entities that were inside last frame, triggers only fire when something new enters.
+    occupants: HashSet<usize>,
+}
+
+impl Trigger {
+    pub fn new(action: TriggerAction, rearm_time: Option<f32>) -> Self {
+        Self {
+            action,
+            is_hidden: false,
+            rearm_time,
+            rearm_timer: 0.0,
+            is_spent: false,
+            occupants: HashSet::new(),
+        }
+    }
+
+    pub fn set_hidden(&mut self, is_hidden: bool) {
+        self.is_hidden = is_hidden;
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.is_hidden
+    }
+
+    pub fn action(&self) -> TriggerAction {
+        self.action
+    }
+
+    pub fn rearm_time(&self) -> Option<f32> {
+        self.rearm_time
+    }
+
+    pub fn spawn(self, manager: &mut EntityManager, position: cgmath::Vector3<f32>) -> usize {
+        let entity = manager.add_entity();
+
+        manager.add_component_to_entity(entity, Actor::new(position, TRIGGER_SIZE, 0.0));
+        manager.add_component_to_entity(entity, Display::new(TRAP_TEX_INDEX));
+        manager.add_component_to_entity(entity, self);
+
+        entity
+    }
+
+    // Hidden spikes that hurt whatever steps on them.
+    pub fn spike_trap() -> Self {
+        let mut trigger = Self::new(
+            TriggerAction::Spikes {
+                damage: SPIKE_DAMAGE,
+            },
+            Some(TRAP_REARM_TIME),
+        );
+        trigger.set_hidden(true);
+
+        trigger
+    }
+
+    // Opens a door the first time that something steps on it.
+    pub fn pressure_plate(door: cgmath::Vector3<i32>) -> Self {
+        Self::new(TriggerAction::OpenDoor { block: door }, None)
+    }
+
+    // Make one of the traps that are scattered around the level. Returns None if the chosen trap
+    // couldn't be placed at the position.
+    pub fn random_trap(
+        chunk: &Chunk,
+        rng: &mut Rng,
+        position: cgmath::Vector3<f32>,
+    ) -> Option<Self> {
+        let (action, rearm_time, is_hidden) = match rng.range(5) {
+            0 => return Some(Self::spike_trap()),
+            1 => (
+                TriggerAction::ArrowTrap {
+                    origin: Self::find_arrow_origin(chunk, position)?,
+                },
+                Some(TRAP_REARM_TIME),
+                true,
+            ),
+            2 => (
+                TriggerAction::Teleporter {
+                    destination: chunk.get_spawn_position(rng)?,
+                },
+                Some(TRAP_REARM_TIME),
+                false,
+            ),
+            3 => (
+                TriggerAction::Alarm {
+                    loudness: ALARM_NOISE,
+                },
+                Some(TRAP_REARM_TIME),
+                true,
+            ),
+            _ => (
+                TriggerAction::SpawnEnemy {
+                    prefab: Prefab::Chaser,
+                    position: chunk.get_spawn_position_near(rng, position, AMBUSH_RANGE)?,
+                },
+                None,
+                true,
+            ),
+        };
+
+        let mut trigger = Self::new(action, rearm_time);
+        trigger.set_hidden(is_hidden);
+
+        Some(trigger)
+    }
+
+    // Arrow traps shoot from the closest wall that is in a straight line from the trap.
+    fn find_arrow_origin(
+        chunk: &Chunk,
+        position: cgmath::Vector3<f32>,
+    ) -> Option<cgmath::Vector3<f32>> {
+        let block_position = round_vec_to_i32(position / BLOCK_SIZE_F);
+
+        (1..=ARROW_TRAP_RANGE)
+            .flat_map(|distance| {
+                [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                    .into_iter()
+                    .map(move |(x, z)| (distance, cgmath::vec3(x, 0, z)))
+            })
+            .find(|(distance, dir)| {
+                let wall = block_position + dir * *distance;
+                chunk.get_block(wall.x, wall.y, wall.z)
+            })
+            .map(|(distance, dir)| {
+                let origin = block_position + dir * (distance - 1);
+                (origin.cast::<f32>().unwrap() + cgmath::vec3(0.5, 0.5, 0.5)) * BLOCK_SIZE_F
+            })
+    }
+
+    // Returns false if the trigger can't fire right now.
+    fn try_fire(&mut self) -> bool {
+        if self.is_spent || self.rearm_timer > 0.0 {
+            return false;
+        }
+
+        match self.rearm_time {
+            Some(rearm_time) => self.rearm_timer = rearm_time,
+            None => self.is_spent = true,
+        }
+
+        // Anything that sets off a trap knows where it is afterwards.
+        self.is_hidden = false;
+
+        true
+    }
+}
+
+// Triggers are only set off by living things entering them. Firing them from scripts is left until
+// there is a scripting system, and would go through the command queue like other requests.
+pub struct TriggerSystem {
+    rng: Rng,
+    nearby_entities: HashSet<usize>,
+    entered: Vec<usize>,
+}
+
+impl TriggerSystem {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            nearby_entities: HashSet::new(),
+            entered: Vec::new(),
+        }
+    }
+
+    fn fire(
+        trigger_entity: usize,
+        action: TriggerAction,
+        activator: Option<usize>,
+        chunk: &mut Chunk,
+        queue: &mut CommandQueue,
+        actors: &mut ComponentStore<Actor>,
+    ) {
+        let position = match actors.get(trigger_entity) {
+            Some(a) => a.position(),
+            None => return,
+        };
+
+        match action {
+            TriggerAction::Spikes { damage } => {
+                if let Some(activator) = activator {
+                    queue.deal_damage(
+                        activator,
+                        DamageInfo::new(Some(trigger_entity), damage, DamageType::Physical),
+                    );
+                }
+            }
+            TriggerAction::ArrowTrap { origin } => {
+                let target = match activator.and_then(|a| actors.get(a)) {
+                    Some(a) => a.position(),
+                    None => return,
+                };
+
+                queue.spawn_projectile(
+                    Projectile::new(trigger_entity, ProjectileKind::Arrow, target - origin),
+                    origin,
+                );
+            }
+            TriggerAction::Teleporter { destination } => {
+                if let Some(activator) = activator {
+                    if let Some(actor) = actors.get_mut(activator) {
+                        actor.teleport(activator, destination, chunk);
+                    }
+                }
+            }
+            TriggerAction::Alarm { loudness } => {
+                queue.make_noise(Noise {
+                    source: activator,
+                    position,
+                    loudness,
+                });
+            }
+            TriggerAction::OpenDoor { block } => {
+                if let Block::Door(mut door) = chunk.block_at(block.x, block.y, block.z) {
+                    door.is_open = true;
+                    door.lock = None;
+                    chunk.set_block_at(Block::Door(door), block.x, block.y, block.z);
+                }
+            }
+            TriggerAction::SpawnEnemy { prefab, position } => {
+                queue.spawn_prefab(prefab, position);
+            }
+        }
+    }
+}
+
+impl System for TriggerSystem {
+    fn update(
+        &mut self,
+        ecs: &mut Ecs,
+        chunk: &mut Chunk,
+        _input: &mut Input,
+        _gui: &mut Gui,
+        delta_time: f32,
+    ) {
+        let Ecs {
+            manager,
+            entity_cache,
+            queue,
+        } = ecs;
+
+        manager.get_entities_with_both::<Player, Actor>(entity_cache);
+        let player = entity_cache.first().copied();
+
+        if !manager.get_entities_with_both::<Trigger, Actor>(entity_cache) {
+            return;
+        }
+
+        let mut triggers = manager.borrow_components::<Trigger>().unwrap();
+        let mut actors = manager.borrow_components::<Actor>().unwrap();
+        let mut displays = manager.borrow_components::<Display>();
+        let healths = manager.borrow_components::<Health>();
+
+        let player_position = player
+            .and_then(|p| actors.borrow().get(p))
+            .map(|a| a.position());
+
+        for entity in entity_cache.iter() {
+            let trigger = triggers.borrow_mut().get_mut(*entity).unwrap();
+            let position = actors.borrow().get(*entity).unwrap().position();
+
+            trigger.rearm_timer = (trigger.rearm_timer - delta_time).max(0.0);
+
+            // The player might notice hidden traps that are nearby and in view.
+            if trigger.is_hidden {
+                let block_position = round_vec_to_i32(position / BLOCK_SIZE_F);
+
+                if player_position.is_some_and(|p| (p - position).magnitude() < DETECTION_RANGE)
+                    && chunk.is_visible(block_position.x, block_position.z)
+                    && self.rng.chance(DETECTION_CHANCE * delta_time)
+                {
+                    trigger.is_hidden = false;
+                }
+            }
+
+            // Only living things can set off triggers.
+            actors
+                .borrow()
+                .get(*entity)
+                .unwrap()
+                .get_nearby_entities(chunk, &mut self.nearby_entities);
+            self.entered.clear();
+
+            let mut occupants = HashSet::new();
+            for nearby_entity in &self.nearby_entities {
+                if *nearby_entity == *entity
+                    || !healths.as_ref().is_some_and(|h| h.has(*nearby_entity))
+                {
+                    continue;
+                }
+
+                let is_inside = actors
+                    .borrow()
+                    .get(*nearby_entity)
+                    .is_some_and(|a| a.intersects(position, TRIGGER_SIZE));
+
+                if !is_inside {
+                    continue;
+                }
+
+                occupants.insert(*nearby_entity);
+
+                if !trigger.occupants.contains(nearby_entity) {
+                    self.entered.push(*nearby_entity);
+                }
+            }
+
+            trigger.occupants = occupants;
+
+            for activator in &self.entered {
+                if !trigger.try_fire() {
+                    break;
+                }
+
+                Self::fire(
+                    *entity,
+                    trigger.action,
+                    Some(*activator),
+                    chunk,
+                    queue,
+                    &mut actors,
+                );
+            }
+
+            if let Some(display) = displays.as_mut().and_then(|d| d.get_mut(*entity)) {
+                display.set_visible(!trigger.is_hidden);
+            }
+        }
+    }
+}
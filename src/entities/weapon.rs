@@ -0,0 +1,172 @@
+use super::{
+    damage::DamageType,
+    projectile::ProjectileKind,
+    status_effect::{StatusEffect, StatusEffectKind},
+};
+
+// How a fighter attacks. Melee weapons hit every target within their reach and swing arc,
+// ranged weapons fire their projectile instead. Distances are in world units, angles in degrees.
+#[derive(Copy, Clone, Debug)]
+pub struct Weapon {
+    damage: i32,
+    damage_type: DamageType,
+    crit_chance: f32,
+    reach: f32,
+    arc: f32,
+    knockback: f32,
+    wind_up: f32,
+    recovery: f32,
+    hit_stop: f32,
+    projectile: Option<ProjectileKind>,
+    effect: Option<StatusEffect>,
+}
+
+impl Weapon {
+    pub const FISTS: Weapon = Weapon {
+        damage: 0,
+        damage_type: DamageType::Physical,
+        crit_chance: 0.05,
+        reach: 1.0,
+        arc: 0.0,
+        knockback: 2.0,
+        wind_up: 0.0,
+        recovery: 0.25,
+        hit_stop: 0.0,
+        projectile: None,
+        effect: None,
+    };
+
+    pub const CLAWS: Weapon = Weapon {
+        damage: 0,
+        damage_type: DamageType::Physical,
+        crit_chance: 0.05,
+        reach: 1.0,
+        arc: 60.0,
+        knockback: 4.0,
+        wind_up: 0.2,
+        recovery: 0.3,
+        hit_stop: 0.05,
+        projectile: None,
+        effect: Some(StatusEffect::new(StatusEffectKind::Poison, 5.0, 2.0)),
+    };
+
+    pub const SWORD: Weapon = Weapon {
+        damage: 10,
+        damage_type: DamageType::Physical,
+        crit_chance: 0.1,
+        reach: 1.75,
+        arc: 90.0,
+        knockback: 6.0,
+        wind_up: 0.1,
+        recovery: 0.3,
+        hit_stop: 0.05,
+        projectile: None,
+        effect: None,
+    };
+
+    pub const PICKAXE: Weapon = Weapon {
+        damage: 6,
+        damage_type: DamageType::Physical,
+        crit_chance: 0.1,
+        reach: 1.5,
+        arc: 60.0,
+        knockback: 5.0,
+        wind_up: 0.2,
+        recovery: 0.4,
+        hit_stop: 0.05,
+        projectile: None,
+        effect: None,
+    };
+
+    pub const SPEAR: Weapon = Weapon {
+        damage: 5,
+        damage_type: DamageType::Physical,
+        crit_chance: 0.15,
+        reach: 2.5,
+        arc: 0.0,
+        knockback: 8.0,
+        wind_up: 0.2,
+        recovery: 0.4,
+        hit_stop: 0.05,
+        projectile: None,
+        effect: None,
+    };
+
+    pub const HAMMER: Weapon = Weapon {
+        damage: 25,
+        damage_type: DamageType::Physical,
+        crit_chance: 0.05,
+        reach: 1.5,
+        arc: 120.0,
+        knockback: 15.0,
+        wind_up: 0.5,
+        recovery: 0.6,
+        hit_stop: 0.15,
+        projectile: None,
+        effect: Some(StatusEffect::new(StatusEffectKind::Stun, 1.0, 0.0)),
+    };
+
+    pub const BOW: Weapon = Weapon::ranged(ProjectileKind::Arrow, 0.3, 1.2);
+    pub const WAND: Weapon = Weapon::ranged(ProjectileKind::Bolt, 0.0, 0.5);
+    pub const THROWING_KNIFE: Weapon = Weapon::ranged(ProjectileKind::Knife, 0.1, 0.25);
+
+    const fn ranged(projectile: ProjectileKind, wind_up: f32, recovery: f32) -> Self {
+        Self {
+            damage: 0,
+            damage_type: DamageType::Physical,
+            crit_chance: 0.0,
+            reach: 0.0,
+            arc: 0.0,
+            knockback: 0.0,
+            wind_up,
+            recovery,
+            hit_stop: 0.0,
+            projectile: Some(projectile),
+            effect: None,
+        }
+    }
+
+    pub fn damage(&self) -> i32 {
+        self.damage
+    }
+
+    pub fn damage_type(&self) -> DamageType {
+        self.damage_type
+    }
+
+    pub fn crit_chance(&self) -> f32 {
+        self.crit_chance
+    }
+
+    pub fn reach(&self) -> f32 {
+        self.reach
+    }
+
+    pub fn arc(&self) -> f32 {
+        self.arc
+    }
+
+    pub fn knockback(&self) -> f32 {
+        self.knockback
+    }
+
+    pub fn wind_up(&self) -> f32 {
+        self.wind_up
+    }
+
+    pub fn recovery(&self) -> f32 {
+        self.recovery
+    }
+
+    pub fn hit_stop(&self) -> f32 {
+        self.hit_stop
+    }
+
+    pub fn projectile(&self) -> Option<ProjectileKind> {
+        self.projectile
+    }
+
+    pub fn effect(&self) -> Option<StatusEffect> {
+        self.effect
+    }
+}
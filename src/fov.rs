@@ -0,0 +1,102 @@
+// Recursive shadowcasting over a 2d grid. Each octant is scanned row by row moving away from the
+// origin, and slopes that are blocked by opaque cells are skipped in later rows.
+
+// Multipliers for transforming coordinates in the first octant into each of the other octants.
+const OCTANTS: [[i32; 4]; 8] = [
+    [1, 0, 0, 1],
+    [0, 1, 1, 0],
+    [0, -1, 1, 0],
+    [-1, 0, 0, 1],
+    [-1, 0, 0, -1],
+    [0, -1, -1, 0],
+    [0, 1, -1, 0],
+    [1, 0, 0, -1],
+];
+
+struct Shadowcaster<'a, O: Fn(i32, i32) -> bool, V: FnMut(i32, i32)> {
+    origin_x: i32,
+    origin_z: i32,
+    radius: i32,
+    is_opaque: &'a O,
+    mark_visible: &'a mut V,
+}
+
+// Calls mark_visible for every cell within the radius that can be seen from the origin.
+// Opaque cells that are seen are also marked, so walls around the visible area are shown.
+pub fn shadowcast(
+    origin_x: i32,
+    origin_z: i32,
+    radius: i32,
+    is_opaque: &impl Fn(i32, i32) -> bool,
+    mark_visible: &mut impl FnMut(i32, i32),
+) {
+    mark_visible(origin_x, origin_z);
+
+    let mut shadowcaster = Shadowcaster {
+        origin_x,
+        origin_z,
+        radius,
+        is_opaque,
+        mark_visible,
+    };
+
+    for octant in OCTANTS {
+        shadowcaster.cast_light(1, 1.0, 0.0, octant);
+    }
+}
+
+impl<'a, O: Fn(i32, i32) -> bool, V: FnMut(i32, i32)> Shadowcaster<'a, O, V> {
+    fn cast_light(&mut self, row: i32, mut start_slope: f32, end_slope: f32, octant: [i32; 4]) {
+        if start_slope < end_slope {
+            return;
+        }
+
+        let [xx, xz, zx, zz] = octant;
+        let radius_squared = self.radius * self.radius;
+        let mut next_start_slope = start_slope;
+
+        for distance in row..=self.radius {
+            let mut is_blocked = false;
+            let dz = -distance;
+
+            for dx in -distance..=0 {
+                let left_slope = (dx as f32 - 0.5) / (dz as f32 + 0.5);
+                let right_slope = (dx as f32 + 0.5) / (dz as f32 - 0.5);
+
+                if start_slope < right_slope {
+                    continue;
+                }
+
+                if end_slope > left_slope {
+                    break;
+                }
+
+                let x = self.origin_x + dx * xx + dz * xz;
+                let z = self.origin_z + dx * zx + dz * zz;
+
+                if dx * dx + dz * dz <= radius_squared {
+                    (self.mark_visible)(x, z);
+                }
+
+                let is_opaque = (self.is_opaque)(x, z);
+
+                if is_blocked {
+                    if is_opaque {
+                        next_start_slope = right_slope;
+                    } else {
+                        is_blocked = false;
+                        start_slope = next_start_slope;
+                    }
+                } else if is_opaque && distance < self.radius {
+                    is_blocked = true;
+                    self.cast_light(distance + 1, start_slope, left_slope, octant);
+                    next_start_slope = right_slope;
+                }
+            }
+
+            if is_blocked {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,612 @@
+use crate::chunk::{Block, Chunk, ChunkSnapshot, BLOCK_SIZE_F, CHUNK_HEIGHT, SECTION_SIZE};
+use crate::direction::{dir_outward_component, dir_to_offset, index_to_dir, Direction};
+
+use super::cube_mesh::{CUBE_INDICES, CUBE_VERTICES};
+use super::vertex::Vertex;
+
+// How bright explored blocks that aren't currently visible are.
+const EXPLORED_LIGHT: f32 = 0.35;
+const DOOR_TEX_INDEX: u32 = 1;
+
+// The geometry of a section of a chunk, ready to be uploaded.
+pub struct MeshData {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+struct VertexNeighbors {
+    side1: bool,
+    side2: bool,
+    corner: bool,
+}
+
+// How a block face looks, faces that look the same can be merged together.
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct Face {
+    tex_index: u32,
+    tint: [f32; 3],
+    light: f32,
+    // For each vertex of the face.
+    ao: [i32; 4],
+}
+
+impl Face {
+    // A face can only be stretched along an axis if its ambient occlusion doesn't change along
+    // it, otherwise the shading would be stretched too. other_axis gives which side of the face a
+    // corner is on across the axis being stretched along.
+    fn can_stretch(
+        &self,
+        corners: &[(usize, usize); 4],
+        other_axis: fn(&(usize, usize)) -> usize,
+    ) -> bool {
+        (0..4).all(|i| {
+            (0..4).all(|j| {
+                other_axis(&corners[i]) != other_axis(&corners[j]) || self.ao[i] == self.ao[j]
+            })
+        })
+    }
+}
+
+pub fn build_section_mesh(chunk: &ChunkSnapshot, section: usize) -> MeshData {
+    let mut mesh = MeshData {
+        vertices: Vec::new(),
+        indices: Vec::new(),
+    };
+    let origin = Chunk::section_origin(section).into();
+
+    for dir_i in 0..6 {
+        mesh_faces(chunk, &mut mesh, origin, dir_i);
+    }
+
+    mesh
+}
+
+// Mesh the faces that point in one direction a slice at a time, merging neighbouring faces that
+// look the same into larger quads.
+fn mesh_faces(chunk: &ChunkSnapshot, mesh: &mut MeshData, section_origin: [i32; 3], dir_i: usize) {
+    let normal_axis = dir_outward_component(index_to_dir(dir_i)) as usize;
+    let u_axis = (normal_axis + 1) % 3;
+    let v_axis = (normal_axis + 2) % 3;
+    let dimensions = [SECTION_SIZE, CHUNK_HEIGHT, SECTION_SIZE];
+    let width = dimensions[u_axis];
+    let height = dimensions[v_axis];
+
+    // Which corner of the face each vertex is on.
+    let corners =
+        CUBE_VERTICES[dir_i].map(|v| (v.position[u_axis] as usize, v.position[v_axis] as usize));
+    let mut faces = vec![None; width * height];
+
+    for slice in 0..dimensions[normal_axis] {
+        let mut position = section_origin;
+        position[normal_axis] += slice as i32;
+
+        for v in 0..height {
+            for u in 0..width {
+                position[u_axis] = section_origin[u_axis] + u as i32;
+                position[v_axis] = section_origin[v_axis] + v as i32;
+                faces[u + v * width] = face_at(chunk, position, dir_i);
+            }
+        }
+
+        for v in 0..height {
+            for u in 0..width {
+                let face = match faces[u + v * width] {
+                    Some(f) => f,
+                    None => continue,
+                };
+
+                let mut quad_width = 1;
+                if face.can_stretch(&corners, |c| c.1) {
+                    while u + quad_width < width && faces[u + quad_width + v * width] == Some(face)
+                    {
+                        quad_width += 1;
+                    }
+                }
+
+                let mut quad_height = 1;
+                if face.can_stretch(&corners, |c| c.0) {
+                    while v + quad_height < height
+                        && (u..u + quad_width)
+                            .all(|u| faces[u + (v + quad_height) * width] == Some(face))
+                    {
+                        quad_height += 1;
+                    }
+                }
+
+                for quad_v in v..v + quad_height {
+                    for quad_u in u..u + quad_width {
+                        faces[quad_u + quad_v * width] = None;
+                    }
+                }
+
+                let mut origin = section_origin.map(|n| n as f32);
+                origin[normal_axis] += slice as f32;
+                origin[u_axis] += u as f32;
+                origin[v_axis] += v as f32;
+
+                let mut size = [1.0; 3];
+                size[u_axis] = quad_width as f32;
+                size[v_axis] = quad_height as f32;
+
+                push_quad(mesh, dir_i, face, origin, size);
+            }
+        }
+    }
+}
+
+fn face_at(chunk: &ChunkSnapshot, position: [i32; 3], dir_i: usize) -> Option<Face> {
+    let [x, y, z] = position;
+    let block = chunk.block_at(x, y, z);
+
+    if !block.is_solid() {
+        return None;
+    }
+
+    let dir = index_to_dir(dir_i);
+    let dir_offset = dir_to_offset(dir);
+    if chunk.get_block(x + dir_offset[0], y + dir_offset[1], z + dir_offset[2]) {
+        return None;
+    }
+
+    // Doors use their own texture, and locked doors are tinted by their key.
+    let (tex_index, tint) = match block {
+        Block::Door(door) => (
+            DOOR_TEX_INDEX,
+            door.lock.map(|k| k.color()).unwrap_or([1.0; 3]),
+        ),
+        _ => (0, [1.0; 3]),
+    };
+
+    // Unexplored blocks are hidden, and remembered ones are dimmed.
+    let visibility_light = if chunk.is_visible(x, z) {
+        1.0
+    } else if chunk.is_explored(x, z) {
+        EXPLORED_LIGHT
+    } else {
+        return None;
+    };
+
+    // Faces are lit by the light in the empty block that they face.
+    let light = chunk.light_at(x + dir_offset[0], y + dir_offset[1], z + dir_offset[2]);
+
+    let ao = CUBE_VERTICES[dir_i].map(|vert| {
+        let neighbors =
+            check_vertex_neighbors(chunk, position, vert.position.map(|n| n as i32), dir);
+        calculate_ao_level(neighbors)
+    });
+
+    Some(Face {
+        tex_index,
+        tint,
+        light: visibility_light * light,
+        ao,
+    })
+}
+
+// Add a face that covers size blocks from the origin, in blocks.
+fn push_quad(mesh: &mut MeshData, dir_i: usize, face: Face, origin: [f32; 3], size: [f32; 3]) {
+    let vert_count = mesh.vertices.len() as u32;
+
+    for vert_i in 0..4 {
+        let mut vert = CUBE_VERTICES[dir_i][vert_i];
+
+        for axis in 0..3 {
+            vert.position[axis] = (vert.position[axis] * size[axis] + origin[axis]) * BLOCK_SIZE_F;
+        }
+
+        // The texture repeats once per block.
+        for (k, tex_coord) in vert.tex_coords.iter_mut().enumerate() {
+            *tex_coord *= size[tex_axis(dir_i, k)];
+        }
+
+        let ao_light_value = face.ao[vert_i] as f32 * 0.33 * face.light;
+        vert.color = face.tint.map(|n| n * ao_light_value);
+        vert.tex_index = face.tex_index;
+
+        mesh.vertices.push(vert);
+    }
+
+    for ind_i in 0..6 {
+        mesh.indices.push(CUBE_INDICES[dir_i][ind_i] + vert_count);
+    }
+
+    orient_last_face(&mut mesh.vertices, face.ao);
+}
+
+// The axis that a texture coordinate runs along on the faces in a direction.
+fn tex_axis(dir_i: usize, k: usize) -> usize {
+    let verts = &CUBE_VERTICES[dir_i];
+
+    (0..3)
+        .find(|axis| {
+            verts.iter().all(|v| v.tex_coords[k] == v.position[*axis])
+                || verts
+                    .iter()
+                    .all(|v| v.tex_coords[k] == 1.0 - v.position[*axis])
+        })
+        .unwrap()
+}
+
+fn calculate_ao_level(neighbors: VertexNeighbors) -> i32 {
+    if neighbors.side1 && neighbors.side2 {
+        return 0;
+    }
+
+    let mut occupied = 0;
+
+    if neighbors.side1 {
+        occupied += 1;
+    }
+
+    if neighbors.side2 {
+        occupied += 1;
+    }
+
+    if neighbors.corner {
+        occupied += 1;
+    }
+
+    3 - occupied
+}
+
+fn check_vertex_neighbors(
+    chunk: &ChunkSnapshot,
+    world_position: [i32; 3],
+    vertex_position: [i32; 3],
+    direction: Direction,
+) -> VertexNeighbors {
+    let direction_corner = [
+        vertex_position[0] * 2 - 1,
+        vertex_position[1] * 2 - 1,
+        vertex_position[2] * 2 - 1,
+    ];
+
+    let outward_component = dir_outward_component(direction);
+    let mut direction_side1 = direction_corner;
+    direction_side1[((outward_component + 2) % 3) as usize] = 0;
+    let mut direction_side2 = direction_corner;
+    direction_side2[((outward_component + 1) % 3) as usize] = 0;
+
+    let side1_position = [
+        world_position[0] + direction_side1[0],
+        world_position[1] + direction_side1[1],
+        world_position[2] + direction_side1[2],
+    ];
+    let side2_position = [
+        world_position[0] + direction_side2[0],
+        world_position[1] + direction_side2[1],
+        world_position[2] + direction_side2[2],
+    ];
+    let corner_position = [
+        world_position[0] + direction_corner[0],
+        world_position[1] + direction_corner[1],
+        world_position[2] + direction_corner[2],
+    ];
+
+    VertexNeighbors {
+        side1: chunk.get_block(side1_position[0], side1_position[1], side1_position[2]),
+        side2: chunk.get_block(side2_position[0], side2_position[1], side2_position[2]),
+        corner: chunk.get_block(corner_position[0], corner_position[1], corner_position[2]),
+    }
+}
+
+// Flip the diagonal of the last face so that its ambient occlusion is interpolated evenly.
+fn orient_last_face(vertices: &mut [Vertex], ao: [i32; 4]) {
+    let face_start = vertices.len() - 4;
+    let v0 = vertices[face_start];
+    let v1 = vertices[face_start + 1];
+    let v2 = vertices[face_start + 2];
+    let v3 = vertices[face_start + 3];
+
+    if ao[0] + ao[2] > ao[1] + ao[3] {
+        return;
+    }
+
+    vertices[face_start] = v3;
+    vertices[face_start + 1] = v0;
+    vertices[face_start + 2] = v1;
+    vertices[face_start + 3] = v2;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::{Door, CHUNK_SIZE, SECTION_COUNT};
+    use crate::entities::item::KeyKind;
+
+    fn empty_mesh() -> MeshData {
+        MeshData {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    fn dir_index(offset: [i32; 3]) -> usize {
+        (0..6)
+            .find(|i| dir_to_offset(index_to_dir(*i)) == offset)
+            .unwrap()
+    }
+
+    // The block and direction of every face that would be drawn without any merging.
+    fn naive_faces(chunk: &ChunkSnapshot, section: usize) -> Vec<([i32; 3], usize)> {
+        let origin = Chunk::section_origin(section);
+        let mut faces = Vec::new();
+
+        for dir_i in 0..6 {
+            for z in 0..SECTION_SIZE as i32 {
+                for y in 0..CHUNK_HEIGHT as i32 {
+                    for x in 0..SECTION_SIZE as i32 {
+                        let position = [origin.x + x, origin.y + y, origin.z + z];
+
+                        if face_at(chunk, position, dir_i).is_some() {
+                            faces.push((position, dir_i));
+                        }
+                    }
+                }
+            }
+        }
+
+        faces.sort();
+        faces
+    }
+
+    // The block and direction of every face covered by the merged quads, once for each quad that
+    // covers it. Also checks that every face a quad covers looks the same, and that the corners
+    // of each quad are shaded like the faces they are on.
+    fn merged_faces(chunk: &ChunkSnapshot, section: usize) -> Vec<([i32; 3], usize)> {
+        let origin = Chunk::section_origin(section).into();
+        let mut faces = Vec::new();
+
+        for dir_i in 0..6 {
+            let mut mesh = empty_mesh();
+            mesh_faces(chunk, &mut mesh, origin, dir_i);
+
+            let offset = dir_to_offset(index_to_dir(dir_i));
+
+            for quad in mesh.vertices.chunks(4) {
+                let corner = |pick: fn(f32, f32) -> f32| {
+                    let mut corner = [0; 3];
+                    for axis in 0..3 {
+                        let n = quad.iter().map(|v| v.position[axis]).reduce(pick).unwrap();
+                        corner[axis] = (n / BLOCK_SIZE_F) as i32;
+                    }
+                    corner
+                };
+                let min = corner(f32::min);
+                let mut max = corner(f32::max);
+
+                // The quad is flat along its normal, and on the far side of the block if it
+                // points the positive way.
+                let mut cell_min = min;
+                for axis in 0..3 {
+                    if min[axis] == max[axis] {
+                        cell_min[axis] -= (offset[axis] > 0) as i32;
+                        max[axis] = cell_min[axis] + 1;
+                    }
+                }
+
+                let quad_face = face_at(chunk, cell_min, dir_i).unwrap();
+
+                for z in cell_min[2]..max[2] {
+                    for y in cell_min[1]..max[1] {
+                        for x in cell_min[0]..max[0] {
+                            assert_eq!(face_at(chunk, [x, y, z], dir_i), Some(quad_face));
+                            faces.push(([x, y, z], dir_i));
+                        }
+                    }
+                }
+
+                for vert in quad {
+                    let block_position = vert.position.map(|n| (n / BLOCK_SIZE_F) as i32);
+                    let mut cell = [0; 3];
+                    for axis in 0..3 {
+                        cell[axis] = block_position[axis].clamp(cell_min[axis], max[axis] - 1);
+                    }
+
+                    let mut unit = empty_mesh();
+                    let cell_origin = cell.map(|n| n as f32);
+                    push_quad(&mut unit, dir_i, quad_face, cell_origin, [1.0; 3]);
+
+                    let unit_vert = unit
+                        .vertices
+                        .iter()
+                        .find(|v| v.position == vert.position)
+                        .unwrap();
+                    assert_eq!(unit_vert.color, vert.color);
+                }
+            }
+        }
+
+        faces.sort();
+        faces
+    }
+
+    // Duplicates in the merged faces would be overlapping quads, and they won't match the naive
+    // faces either.
+    fn assert_merged_faces_match(chunk: Chunk) {
+        let snapshot = chunk.snapshot();
+
+        for section in 0..SECTION_COUNT {
+            assert_eq!(
+                merged_faces(&snapshot, section),
+                naive_faces(&snapshot, section)
+            );
+        }
+    }
+
+    fn floor_chunk() -> Chunk {
+        let mut chunk = Chunk::new();
+        for z in 0..CHUNK_SIZE as i32 {
+            for x in 0..CHUNK_SIZE as i32 {
+                chunk.set_block(true, x, 0, z);
+            }
+        }
+        chunk.reveal_all();
+
+        chunk
+    }
+
+    #[test]
+    fn flat_floor() {
+        let chunk = floor_chunk();
+        let snapshot = chunk.snapshot();
+
+        // The top of the floor is the same all over, except where it is shaded by the walls
+        // outside of the chunk, so the sections away from the edges are one quad.
+        let up = dir_index([0, 1, 0]);
+        let is_inside = |n: i32| n > 0 && n + (SECTION_SIZE as i32) < CHUNK_SIZE as i32;
+
+        for section in 0..SECTION_COUNT {
+            let origin = Chunk::section_origin(section);
+            if !is_inside(origin.x) || !is_inside(origin.z) {
+                continue;
+            }
+
+            let mut mesh = empty_mesh();
+            mesh_faces(&snapshot, &mut mesh, origin.into(), up);
+
+            assert_eq!(mesh.vertices.len(), 4);
+        }
+
+        assert_merged_faces_match(chunk);
+    }
+
+    #[test]
+    fn checkerboard() {
+        let mut chunk = floor_chunk();
+        for z in 0..CHUNK_SIZE as i32 {
+            for x in 0..CHUNK_SIZE as i32 {
+                chunk.set_block((x + z) % 2 == 0, x, 1, z);
+            }
+        }
+
+        assert_merged_faces_match(chunk);
+    }
+
+    #[test]
+    fn doors_with_different_locks() {
+        let mut chunk = floor_chunk();
+        let locks = [None].into_iter().chain(KeyKind::ALL.map(Some));
+
+        for (i, lock) in locks.enumerate() {
+            for x in 0..CHUNK_SIZE as i32 {
+                let door = Door {
+                    is_open: false,
+                    lock,
+                };
+                chunk.set_block_at(Block::Door(door), x, 1, 4 + i as i32);
+            }
+        }
+
+        // Locks change along x too, across the middle of a section.
+        let door = Door {
+            is_open: false,
+            lock: Some(KeyKind::Gold),
+        };
+        for x in 4..12 {
+            chunk.set_block_at(Block::Door(door), x, 2, 4);
+        }
+
+        assert_merged_faces_match(chunk);
+    }
+
+    #[test]
+    fn ambient_occlusion_corners() {
+        let mut chunk = floor_chunk();
+
+        // A lone block, a pillar in a corner, and an L shaped wall all shade the floor around them.
+        chunk.set_block(true, 3, 1, 3);
+        for y in 1..4 {
+            chunk.set_block(true, 8, y, 8);
+        }
+        for i in 0..5 {
+            chunk.set_block(true, 18 + i, 1, 18);
+            chunk.set_block(true, 18, 1, 18 + i);
+        }
+        chunk.set_block(true, 20, 2, 20);
+
+        assert_merged_faces_match(chunk);
+    }
+
+    #[test]
+    fn faces_between_solid_blocks_are_culled() {
+        let mut chunk = Chunk::new();
+        chunk.set_block(true, 5, 1, 5);
+        chunk.set_block(true, 6, 1, 5);
+        chunk.reveal_all();
+        let snapshot = chunk.snapshot();
+
+        let right = dir_index([1, 0, 0]);
+        let left = dir_index([-1, 0, 0]);
+
+        assert!(face_at(&snapshot, [5, 1, 5], right).is_none());
+        assert!(face_at(&snapshot, [6, 1, 5], left).is_none());
+        assert!(face_at(&snapshot, [5, 1, 5], left).is_some());
+        assert!(face_at(&snapshot, [6, 1, 5], right).is_some());
+
+        for dir_i in 0..6 {
+            if dir_i != left && dir_i != right {
+                assert!(face_at(&snapshot, [5, 1, 5], dir_i).is_some());
+                assert!(face_at(&snapshot, [6, 1, 5], dir_i).is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn ao_levels() {
+        let cases = [
+            (false, false, false, 3),
+            (false, false, true, 2),
+            (true, false, false, 2),
+            (false, true, false, 2),
+            (true, false, true, 1),
+            (false, true, true, 1),
+            // Both sides block the corner, whether or not it is there.
+            (true, true, false, 0),
+            (true, true, true, 0),
+        ];
+
+        for (side1, side2, corner, level) in cases {
+            let neighbors = VertexNeighbors {
+                side1,
+                side2,
+                corner,
+            };
+            assert_eq!(calculate_ao_level(neighbors), level);
+        }
+    }
+
+    #[test]
+    fn last_face_diagonal_flip() {
+        let face = || {
+            (0..4)
+                .map(|i| Vertex {
+                    position: [i as f32, 0.0, 0.0],
+                    tex_coords: [0.0; 2],
+                    color: [1.0; 3],
+                    tex_index: 0,
+                })
+                .collect::<Vec<_>>()
+        };
+        let positions =
+            |vertices: &[Vertex]| vertices.iter().map(|v| v.position[0]).collect::<Vec<_>>();
+
+        // Only the last four vertices are touched.
+        let mut vertices = face();
+        vertices.extend(face());
+        orient_last_face(&mut vertices, [3, 0, 3, 0]);
+        assert_eq!(
+            positions(&vertices),
+            [0.0, 1.0, 2.0, 3.0, 0.0, 1.0, 2.0, 3.0]
+        );
+
+        let mut vertices = face();
+        orient_last_face(&mut vertices, [0, 3, 0, 3]);
+        assert_eq!(positions(&vertices), [3.0, 0.0, 1.0, 2.0]);
+
+        // Faces with evenly lit diagonals are flipped too.
+        let mut vertices = face();
+        orient_last_face(&mut vertices, [3, 3, 3, 3]);
+        assert_eq!(positions(&vertices), [3.0, 0.0, 1.0, 2.0]);
+    }
+}
@@ -0,0 +1,126 @@
+use std::sync::{
+    mpsc::{self, Receiver, Sender},
+    Arc, Mutex,
+};
+use std::thread;
+
+use crate::chunk::{Chunk, ChunkSnapshot, SECTION_COUNT};
+
+use super::chunk_mesh::{build_section_mesh, MeshData};
+
+const WORKER_COUNT: usize = 2;
+
+struct MeshJob {
+    section: usize,
+    generation: u32,
+    chunk: Arc<ChunkSnapshot>,
+}
+
+struct MeshResult {
+    section: usize,
+    generation: u32,
+    mesh: MeshData,
+}
+
+// Builds the meshes of chunk sections on worker threads. Each section only has one job at a time,
+// so a section that keeps changing still gets a mesh every time one finishes, and it is meshed
+// again afterwards to catch up. Every job gets a new generation, and a mesh is only kept if it is
+// newer than the one that was last uploaded.
+pub struct ChunkMesher {
+    jobs: Sender<MeshJob>,
+    results: Receiver<MeshResult>,
+    requested_generations: [u32; SECTION_COUNT],
+    uploaded_generations: [u32; SECTION_COUNT],
+    is_pending: [bool; SECTION_COUNT],
+}
+
+impl ChunkMesher {
+    pub fn new() -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<MeshJob>();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        // The workers stop once the mesher is dropped and the job channel closes.
+        for _ in 0..WORKER_COUNT {
+            let job_receiver = Arc::clone(&job_receiver);
+            let result_sender = result_sender.clone();
+
+            thread::spawn(move || loop {
+                let job = match job_receiver.lock().unwrap().recv() {
+                    Ok(j) => j,
+                    Err(_) => return,
+                };
+
+                let result = MeshResult {
+                    section: job.section,
+                    generation: job.generation,
+                    mesh: build_section_mesh(&job.chunk, job.section),
+                };
+
+                if result_sender.send(result).is_err() {
+                    return;
+                }
+            });
+        }
+
+        Self {
+            jobs,
+            results,
+            requested_generations: [0; SECTION_COUNT],
+            uploaded_generations: [0; SECTION_COUNT],
+            is_pending: [false; SECTION_COUNT],
+        }
+    }
+
+    // Mesh the sections from a snapshot of the chunk, returning the ones that were requested.
+    // Sections that are still being meshed are left out, and should be asked for again. There is
+    // only the one chunk, so there are no neighbours to take the blocks along its borders from.
+    pub fn request(&mut self, chunk: &Chunk, sections: &[usize]) -> Vec<usize> {
+        let sections = sections
+            .iter()
+            .copied()
+            .filter(|s| !self.is_pending[*s])
+            .collect::<Vec<_>>();
+
+        if sections.is_empty() {
+            return sections;
+        }
+
+        let chunk = Arc::new(chunk.snapshot());
+
+        for section in &sections {
+            self.requested_generations[*section] += 1;
+            self.is_pending[*section] = true;
+
+            // The workers only stop when the mesher is dropped, so this can't fail.
+            self.jobs
+                .send(MeshJob {
+                    section: *section,
+                    generation: self.requested_generations[*section],
+                    chunk: Arc::clone(&chunk),
+                })
+                .unwrap();
+        }
+
+        sections
+    }
+
+    // The meshes that are ready, along with their sections.
+    pub fn poll(&mut self) -> Vec<(usize, MeshData)> {
+        let mut meshes = Vec::new();
+
+        for result in self.results.try_iter() {
+            self.is_pending[result.section] = false;
+
+            if result.generation <= self.uploaded_generations[result.section] {
+                continue;
+            }
+
+            self.uploaded_generations[result.section] = result.generation;
+            meshes.retain(|(section, _)| *section != result.section);
+            meshes.push((result.section, result.mesh));
+        }
+
+        meshes
+    }
+}
@@ -41,6 +41,7 @@ impl Gui {
                     position: cgmath::vec3(i as f32 * UI_SPRITE_WIDTH, write_line, 0.0),
                     rotation: cgmath::Quaternion::zero(),
                     tex_index: *char_index,
+                    light: 1.0,
                 })
             }
         }
@@ -4,6 +4,8 @@ pub struct Instance {
     pub position: cgmath::Vector3<f32>,
     pub rotation: cgmath::Quaternion<f32>,
     pub tex_index: u32,
+    // Multiplies the colour of the instance, for lighting.
+    pub light: f32,
 }
 
 impl Instance {
@@ -13,6 +15,7 @@ impl Instance {
                 * cgmath::Matrix4::from(self.rotation))
             .into(),
             tex_index: self.tex_index,
+            light: self.light,
         }
     }
 
@@ -34,6 +37,7 @@ impl Instance {
 pub struct InstanceRaw {
     pub model: [[f32; 4]; 4],
     pub tex_index: u32,
+    pub light: f32,
 }
 
 impl InstanceRaw {
@@ -70,6 +74,13 @@ impl InstanceRaw {
                     shader_location: 8,
                     format: wgpu::VertexFormat::Uint32,
                 },
+                // Light:
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 16]>() + mem::size_of::<u32>())
+                        as wgpu::BufferAddress,
+                    shader_location: 9,
+                    format: wgpu::VertexFormat::Float32,
+                },
             ],
         }
     }
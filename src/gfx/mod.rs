@@ -1,4 +1,6 @@
 pub mod camera;
+pub mod chunk_mesh;
+pub mod chunk_mesher;
 pub mod cube_mesh;
 pub mod gui;
 pub mod instance;
@@ -1,12 +1,18 @@
+use crate::chunk::SECTION_COUNT;
 use crate::entities::actor::Actor;
 use crate::gfx::camera::{Camera, CameraOrthographicProjection, CameraPerspectiveProjection};
-use crate::gfx::instance::InstanceRaw;
+use crate::gfx::chunk_mesh::MeshData;
+use crate::gfx::chunk_mesher::ChunkMesher;
+use crate::gfx::instance::{Instance, InstanceRaw};
 use crate::gfx::model::Model;
-use crate::gfx::sprite_mesh::{SPRITE_INDICES, SPRITE_VERTICES, UI_SPRITE_VERTICES};
+use crate::gfx::sprite_mesh::{
+    ui_image_vertices, SPRITE_INDICES, SPRITE_VERTICES, UI_SPRITE_VERTICES,
+};
 use crate::gfx::texture::{self, Texture};
 use crate::gfx::texture_array::TextureArray;
 use crate::gfx::vertex::Vertex;
 use crate::input::Input;
+use crate::map::MAP_PIXELS;
 use crate::simulation::Simulation;
 use cgmath::prelude::*;
 use std::borrow::Borrow;
@@ -19,6 +25,12 @@ use winit::window::{Fullscreen, Window};
 const Z_NEAR: f32 = 0.1;
 const Z_FAR: f32 = 100.0;
 const UI_SCALE: f32 = 28.0;
+const MAP_TEX_INDEX: u32 = 1;
+// Sizes and margins of the map are in ui units, where one unit is the height of a line of text.
+const MINIMAP_SIZE: f32 = 8.0;
+const MINIMAP_MARGIN: f32 = 0.5;
+// The fraction of the screen's height that the full screen map takes up.
+const FULLSCREEN_MAP_SCALE: f32 = 0.9;
 
 pub struct Renderer {
     window: Window,
@@ -35,7 +47,12 @@ pub struct Renderer {
     camera: Camera,
     ui_camera: Camera,
     model: Model,
+    // One for each section of the chunk.
+    chunk_models: Vec<Option<Model>>,
+    chunk_mesher: ChunkMesher,
     ui_model: Model,
+    minimap_model: Model,
+    fullscreen_map_model: Model,
 }
 
 impl Renderer {
@@ -89,9 +106,22 @@ impl Renderer {
 
         let happy_tree = Texture::from_path(&device, &queue, "happy-tree.png").unwrap();
         let sad_tree = Texture::from_path(&device, &queue, "sad-tree.png").unwrap();
-        let texture_array = TextureArray::new(&device, vec![happy_tree, sad_tree]).unwrap();
+        let corpse = Texture::from_path(&device, &queue, "corpse.png").unwrap();
+        let trap = Texture::from_path(&device, &queue, "trap.png").unwrap();
+        let torch = Texture::from_path(&device, &queue, "torch.png").unwrap();
+        let texture_array =
+            TextureArray::new(&device, vec![happy_tree, sad_tree, corpse, trap, torch]).unwrap();
         let glyphs = Texture::from_path(&device, &queue, "bitka.png").unwrap();
-        let ui_texture_array = TextureArray::new(&device, vec![glyphs]).unwrap();
+        let map = Texture::from_dimensions(
+            &device,
+            MAP_PIXELS,
+            MAP_PIXELS,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            wgpu::TextureUsages::empty(),
+            Some("map"),
+        )
+        .unwrap();
+        let ui_texture_array = TextureArray::new(&device, vec![glyphs, map]).unwrap();
 
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader"),
@@ -233,6 +263,12 @@ impl Renderer {
 
         let model = Model::new(&device, SPRITE_VERTICES, SPRITE_INDICES);
         let ui_model = Model::new(&device, UI_SPRITE_VERTICES, SPRITE_INDICES);
+        let minimap_model = Model::new(
+            &device,
+            &ui_image_vertices(MINIMAP_SIZE, MINIMAP_SIZE, MAP_TEX_INDEX),
+            SPRITE_INDICES,
+        );
+        let fullscreen_map_model = Self::create_fullscreen_map_model(&device, config.height);
 
         Self {
             window,
@@ -249,7 +285,11 @@ impl Renderer {
             camera,
             ui_camera,
             model,
+            chunk_models: (0..SECTION_COUNT).map(|_| None).collect(),
+            chunk_mesher: ChunkMesher::new(),
             ui_model,
+            minimap_model,
+            fullscreen_map_model,
         }
     }
 
@@ -282,6 +322,32 @@ impl Renderer {
             self.config.height,
             "depth_texture",
         );
+        self.fullscreen_map_model =
+            Self::create_fullscreen_map_model(&self.device, self.config.height);
+    }
+
+    fn create_fullscreen_map_model(device: &wgpu::Device, height: u32) -> Model {
+        let size = height as f32 / UI_SCALE * FULLSCREEN_MAP_SCALE;
+        Model::new(
+            device,
+            &ui_image_vertices(size, size, MAP_TEX_INDEX),
+            SPRITE_INDICES,
+        )
+    }
+
+    fn upload_chunk_mesh(&mut self, section: usize, mesh: &MeshData) {
+        let mut model = Model::new(&self.device, &mesh.vertices, &mesh.indices);
+        model.update_instances(
+            &self.device,
+            &vec![Instance {
+                position: cgmath::Vector3::zero(),
+                rotation: cgmath::Quaternion::zero(),
+                tex_index: 0,
+                light: 1.0,
+            }],
+        );
+
+        self.chunk_models[section] = Some(model);
     }
 
     pub fn update(&mut self, input: &mut Input, simulation: &mut Simulation) {
@@ -319,7 +385,23 @@ impl Renderer {
         self.camera.update(&self.queue);
         self.ui_camera.update(&self.queue);
 
-        simulation.chunk.update_mesh(&self.device);
+        // Old meshes are drawn until the new ones have been built.
+        for (section, mesh) in self.chunk_mesher.poll() {
+            self.upload_chunk_mesh(section, &mesh);
+        }
+
+        let dirty_sections = simulation.chunk.dirty_sections();
+        let requested_sections = self
+            .chunk_mesher
+            .request(&simulation.chunk, &dirty_sections);
+
+        for section in requested_sections {
+            simulation.chunk.mark_meshed(section);
+        }
+
+        self.ui_texture_array
+            .texture(MAP_TEX_INDEX as usize)
+            .write_rgba(&self.queue, simulation.map_display().image());
     }
 
     pub fn render(&mut self, simulation: &Simulation) -> Result<(), wgpu::SurfaceError> {
@@ -364,7 +446,7 @@ impl Renderer {
             render_pass.set_bind_group(0, self.texture_array.bind_group(), &[]);
             render_pass.set_bind_group(1, self.camera.bind_group(), &[]);
 
-            if let Some(model) = simulation.chunk.model() {
+            for model in self.chunk_models.iter().flatten() {
                 render_pass.set_vertex_buffer(0, model.vertices().slice(..));
                 render_pass.set_index_buffer(model.indices().slice(..), wgpu::IndexFormat::Uint32);
                 render_pass.set_vertex_buffer(1, model.instances().slice(..));
@@ -416,6 +498,46 @@ impl Renderer {
             render_pass.set_bind_group(0, self.ui_texture_array.bind_group(), &[]);
             render_pass.set_bind_group(1, self.ui_camera.bind_group(), &[]);
 
+            // The map is drawn behind the text, either in the top right corner or in the center
+            // of the screen.
+            let ui_width = self.config.width as f32 / UI_SCALE;
+            let ui_height = self.config.height as f32 / UI_SCALE;
+            let (map_model, map_size) = if simulation.map_display().is_fullscreen() {
+                (
+                    &mut self.fullscreen_map_model,
+                    ui_height * FULLSCREEN_MAP_SCALE,
+                )
+            } else {
+                (&mut self.minimap_model, MINIMAP_SIZE)
+            };
+            let map_position = if simulation.map_display().is_fullscreen() {
+                cgmath::vec3(
+                    (ui_width - map_size) * 0.5,
+                    (ui_height - map_size) * 0.5,
+                    -1.0,
+                )
+            } else {
+                cgmath::vec3(
+                    ui_width - map_size - MINIMAP_MARGIN,
+                    ui_height - map_size - MINIMAP_MARGIN,
+                    -1.0,
+                )
+            };
+
+            map_model.update_instances(
+                &self.device,
+                &vec![Instance {
+                    position: map_position,
+                    rotation: cgmath::Quaternion::zero(),
+                    tex_index: 0,
+                    light: 1.0,
+                }],
+            );
+            render_pass.set_vertex_buffer(0, map_model.vertices().slice(..));
+            render_pass.set_index_buffer(map_model.indices().slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.set_vertex_buffer(1, map_model.instances().slice(..));
+            render_pass.draw_indexed(0..map_model.num_indices(), 0, 0..map_model.num_instances());
+
             self.ui_model
                 .update_instances(&self.device, simulation.gui_instances());
             render_pass.set_vertex_buffer(0, self.ui_model.vertices().slice(..));
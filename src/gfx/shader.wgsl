@@ -4,6 +4,7 @@ struct InstanceInput {
     @location(6) model_matrix_2: vec4<f32>,
     @location(7) model_matrix_3: vec4<f32>,
     @location(8) tex_index: u32,
+    @location(9) light: f32,
 };
 
 struct CameraUniform {
@@ -24,7 +25,6 @@ struct VertexOutput {
     @location(0) tex_coords: vec2<f32>,
     @location(1) tex_index: u32,
     @location(2) vertex_color: vec3<f32>,
-    @location(3) light_level: f32,
 }
 
 @vertex
@@ -39,14 +39,11 @@ fn vs_main(
         instance.model_matrix_3,
     );
 
-    let light_pos = vec3(0.0, 0.0, 0.0);
-
     var out: VertexOutput;
     out.tex_coords = model.tex_coords;
     out.tex_index = instance.tex_index + model.tex_index;
-    out.vertex_color = model.color;
+    out.vertex_color = model.color * instance.light;
     out.clip_position = camera.view_proj * model_matrix * vec4<f32>(model.position, 1.0);
-    out.light_level = distance(out.clip_position.xyz, light_pos);
     return out;
 }
 
@@ -63,7 +60,7 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         discard;
     }
 
-    let lit_color = vec4(in.vertex_color * color.rgb / (abs(in.light_level) + 1.0), color.a);
+    let lit_color = vec4(in.vertex_color * color.rgb, color.a);
 
     return lit_color;
 }
\ No newline at end of file
@@ -56,4 +56,34 @@ pub const UI_SPRITE_VERTICES: &[Vertex] = &[
     },
 ];
 
+// A quad for drawing a whole texture on the ui, rather than a single glyph.
+pub fn ui_image_vertices(width: f32, height: f32, tex_index: u32) -> [Vertex; 4] {
+    [
+        Vertex {
+            position: [0.0, 0.0, 0.0],
+            tex_coords: [0.0, 1.0],
+            color: [1.0, 1.0, 1.0],
+            tex_index,
+        },
+        Vertex {
+            position: [width, height, 0.0],
+            tex_coords: [1.0, 0.0],
+            color: [1.0, 1.0, 1.0],
+            tex_index,
+        },
+        Vertex {
+            position: [width, 0.0, 0.0],
+            tex_coords: [1.0, 1.0],
+            color: [1.0, 1.0, 1.0],
+            tex_index,
+        },
+        Vertex {
+            position: [0.0, height, 0.0],
+            tex_coords: [0.0, 0.0],
+            color: [1.0, 1.0, 1.0],
+            tex_index,
+        },
+    ]
+}
+
 pub const SPRITE_INDICES: &[u32] = &[0, 2, 1, 0, 1, 3];
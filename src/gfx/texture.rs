@@ -102,14 +102,29 @@ impl Texture {
             label,
         )?;
 
+        new_texture.write_rgba(queue, &rgba);
+
+        Ok(new_texture)
+    }
+
+    // Replace the contents of the texture, the image must be the same size as the texture.
+    pub fn write_rgba(&self, queue: &wgpu::Queue, rgba: &image::RgbaImage) {
+        let dimensions = rgba.dimensions();
+
+        let size = wgpu::Extent3d {
+            width: dimensions.0,
+            height: dimensions.1,
+            depth_or_array_layers: 1,
+        };
+
         queue.write_texture(
             wgpu::ImageCopyTexture {
                 aspect: wgpu::TextureAspect::All,
-                texture: &new_texture.texture,
+                texture: &self.texture,
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
             },
-            &rgba,
+            rgba,
             wgpu::ImageDataLayout {
                 offset: 0,
                 bytes_per_row: std::num::NonZeroU32::new(4 * dimensions.0),
@@ -117,8 +132,6 @@ impl Texture {
             },
             size,
         );
-
-        Ok(new_texture)
     }
 
     pub fn from_dimensions(
@@ -148,10 +161,12 @@ impl Texture {
         });
 
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // Merged block faces have texture coordinates past 1, so that the texture repeats across
+        // them.
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
             mag_filter: wgpu::FilterMode::Nearest,
             min_filter: wgpu::FilterMode::Nearest,
             mipmap_filter: wgpu::FilterMode::Nearest,
@@ -2,6 +2,7 @@ use super::texture;
 use std::num::NonZeroU32;
 
 pub struct TextureArray {
+    textures: Vec<texture::Texture>,
     bind_group_layout: wgpu::BindGroupLayout,
     bind_group: wgpu::BindGroup,
 }
@@ -59,11 +60,16 @@ impl TextureArray {
         });
 
         Ok(Self {
+            textures,
             bind_group_layout,
             bind_group,
         })
     }
 
+    pub fn texture(&self, index: usize) -> &texture::Texture {
+        &self.textures[index]
+    }
+
     pub fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
         &self.bind_group_layout
     }
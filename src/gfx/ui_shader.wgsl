@@ -40,7 +40,12 @@ fn vs_main(
     );
 
     var out: VertexOutput;
-    out.tex_coords = vec2((f32(instance.tex_index) + model.tex_coords.x) * sprite_width, model.tex_coords.y);
+    // Texture 0 holds the glyphs, other textures are drawn whole.
+    if model.tex_index == 0u {
+        out.tex_coords = vec2((f32(instance.tex_index) + model.tex_coords.x) * sprite_width, model.tex_coords.y);
+    } else {
+        out.tex_coords = model.tex_coords;
+    }
     out.tex_index = model.tex_index;
     out.vertex_color = model.color;
     out.clip_position = camera.view_proj * model_matrix * vec4<f32>(model.position, 1.0);
@@ -0,0 +1,398 @@
+mod text;
+pub mod vox;
+
+use std::{
+    borrow::BorrowMut,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use crate::{
+    chunk::{Block, Chunk, Door, CHUNK_LEN},
+    entities::{
+        actor::Actor,
+        ecs::Ecs,
+        item::{ItemKind, KeyKind},
+        prefab::Prefab,
+        trigger::{Trigger, TriggerAction},
+    },
+};
+
+const MAGIC: &[u8; 4] = b"STLV";
+const VERSION: u8 = 2;
+
+#[derive(Copy, Clone, Debug)]
+struct LevelTrigger {
+    action: TriggerAction,
+    rearm_time: Option<f32>,
+    is_hidden: bool,
+    position: cgmath::Vector3<f32>,
+}
+
+impl LevelTrigger {
+    fn new(trigger: &Trigger, position: cgmath::Vector3<f32>) -> Self {
+        Self {
+            action: trigger.action(),
+            rearm_time: trigger.rearm_time(),
+            is_hidden: trigger.is_hidden(),
+            position,
+        }
+    }
+}
+
+// Everything that makes up an authored level, the blocks of the chunk and what is spawned in it.
+pub struct Level {
+    blocks: Vec<Block>,
+    spawns: Vec<(Prefab, cgmath::Vector3<f32>)>,
+    triggers: Vec<LevelTrigger>,
+}
+
+impl Level {
+    // Take a snapshot of the current level. Only entities that were spawned from prefabs and
+    // triggers are included.
+    pub fn capture(chunk: &Chunk, ecs: &mut Ecs) -> Self {
+        let Ecs {
+            manager,
+            entity_cache,
+            ..
+        } = ecs;
+
+        let mut spawns = Vec::new();
+        let mut triggers = Vec::new();
+
+        if manager.get_entities_with_both::<Prefab, Actor>(entity_cache) {
+            let prefabs = manager.borrow_components::<Prefab>().unwrap();
+            let actors = manager.borrow_components::<Actor>().unwrap();
+
+            for entity in entity_cache.iter() {
+                let prefab = prefabs.get(*entity).unwrap();
+                let actor = actors.get(*entity).unwrap();
+                spawns.push((*prefab, actor.position()));
+            }
+        }
+
+        if manager.get_entities_with_both::<Trigger, Actor>(entity_cache) {
+            let trigger_components = manager.borrow_components::<Trigger>().unwrap();
+            let actors = manager.borrow_components::<Actor>().unwrap();
+
+            for entity in entity_cache.iter() {
+                let trigger = trigger_components.get(*entity).unwrap();
+                let actor = actors.get(*entity).unwrap();
+                triggers.push(LevelTrigger::new(trigger, actor.position()));
+            }
+        }
+
+        Self {
+            blocks: chunk.blocks().to_vec(),
+            spawns,
+            triggers,
+        }
+    }
+
+    // Replace the current level with this one. The player is kept and moved to the level's player
+    // spawn, if it has one.
+    pub fn apply(&self, chunk: &mut Chunk, ecs: &mut Ecs, player: usize) {
+        chunk.load_blocks(&self.blocks);
+
+        let Ecs {
+            manager,
+            entity_cache,
+            queue,
+        } = ecs;
+
+        if manager.get_entities_with::<Actor>(entity_cache) {
+            for entity in entity_cache.iter().filter(|e| **e != player) {
+                queue.remove_entity(*entity);
+            }
+        }
+
+        for (prefab, position) in &self.spawns {
+            if *prefab != Prefab::Player {
+                prefab.spawn(manager, *position);
+                continue;
+            }
+
+            if let Some(mut actors) = manager.borrow_components::<Actor>() {
+                if let Some(actor) = actors.borrow_mut().get_mut(player) {
+                    actor.teleport(player, *position, chunk);
+                }
+            }
+        }
+
+        for level_trigger in &self.triggers {
+            let mut trigger = Trigger::new(level_trigger.action, level_trigger.rearm_time);
+            trigger.set_hidden(level_trigger.is_hidden);
+            trigger.spawn(manager, level_trigger.position);
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), io::Error> {
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 1 + self.blocks.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend(self.blocks.iter().map(|b| encode_block(*b)));
+        bytes.extend_from_slice(&(self.spawns.len() as u32).to_le_bytes());
+
+        for (prefab, position) in &self.spawns {
+            bytes.extend_from_slice(&encode_prefab(*prefab));
+            write_vec3(&mut bytes, *position);
+        }
+
+        bytes.extend_from_slice(&(self.triggers.len() as u32).to_le_bytes());
+
+        for trigger in &self.triggers {
+            encode_trigger_action(&mut bytes, trigger.action);
+            bytes.push(trigger.is_hidden as u8);
+            // Triggers that only fire once are stored with a negative rearm time.
+            bytes.extend_from_slice(&trigger.rearm_time.unwrap_or(-1.0).to_le_bytes());
+            write_vec3(&mut bytes, trigger.position);
+        }
+
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::write(path, bytes)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+        let bytes = std::fs::read(path)?;
+        let mut reader = Reader {
+            bytes: &bytes,
+            i: 0,
+        };
+
+        if reader.read(MAGIC.len())? != MAGIC || reader.read(1)?[0] != VERSION {
+            return Err(invalid_data(
+                "Not a level file, or from a different version",
+            ));
+        }
+
+        let blocks = reader
+            .read(CHUNK_LEN)?
+            .iter()
+            .map(|b| decode_block(*b).ok_or_else(|| invalid_data("Invalid block")))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let spawn_count = reader.read_u32()?;
+        let mut spawns = Vec::new();
+
+        for _ in 0..spawn_count {
+            let prefab = decode_prefab(reader.read_array()?)
+                .ok_or_else(|| invalid_data("Invalid prefab"))?;
+            spawns.push((prefab, reader.read_vec3()?));
+        }
+
+        let trigger_count = reader.read_u32()?;
+        let mut triggers = Vec::new();
+
+        for _ in 0..trigger_count {
+            let action = decode_trigger_action(&mut reader)?;
+            let is_hidden = reader.read(1)?[0] != 0;
+            let rearm_time = Some(reader.read_f32()?).filter(|t| *t >= 0.0);
+
+            triggers.push(LevelTrigger {
+                action,
+                rearm_time,
+                is_hidden,
+                position: reader.read_vec3()?,
+            });
+        }
+
+        Ok(Self {
+            blocks,
+            spawns,
+            triggers,
+        })
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    i: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn read(&mut self, len: usize) -> Result<&'a [u8], io::Error> {
+        let bytes = self
+            .bytes
+            .get(self.i..self.i + len)
+            .ok_or_else(|| invalid_data("File ended early"))?;
+        self.i += len;
+
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], io::Error> {
+        Ok(self.read(N)?.try_into().unwrap())
+    }
+
+    fn read_u32(&mut self) -> Result<u32, io::Error> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_f32(&mut self) -> Result<f32, io::Error> {
+        Ok(f32::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_vec3(&mut self) -> Result<cgmath::Vector3<f32>, io::Error> {
+        Ok(cgmath::vec3(
+            self.read_f32()?,
+            self.read_f32()?,
+            self.read_f32()?,
+        ))
+    }
+
+    fn read_block_position(&mut self) -> Result<cgmath::Vector3<i32>, io::Error> {
+        Ok(cgmath::vec3(
+            i32::from_le_bytes(self.read_array()?),
+            i32::from_le_bytes(self.read_array()?),
+            i32::from_le_bytes(self.read_array()?),
+        ))
+    }
+}
+
+fn write_vec3(bytes: &mut Vec<u8>, vec: cgmath::Vector3<f32>) {
+    for n in [vec.x, vec.y, vec.z] {
+        bytes.extend_from_slice(&n.to_le_bytes());
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+// The low bits are the kind of block, doors keep their lock in the high bits.
+fn encode_block(block: Block) -> u8 {
+    match block {
+        Block::Air => 0,
+        Block::Wall => 1,
+        Block::Door(door) => {
+            let lock = door.lock.map_or(0, |key| key as u8 + 1);
+            (2 + door.is_open as u8) | lock << 4
+        }
+    }
+}
+
+fn decode_block(byte: u8) -> Option<Block> {
+    let lock = match byte >> 4 {
+        0 => None,
+        key => Some(*KeyKind::ALL.get(key as usize - 1)?),
+    };
+
+    match byte & 0xf {
+        0 if lock.is_none() => Some(Block::Air),
+        1 if lock.is_none() => Some(Block::Wall),
+        kind @ (2 | 3) => Some(Block::Door(Door {
+            is_open: kind == 3,
+            lock,
+        })),
+        _ => None,
+    }
+}
+
+// Items are stored by their position in ItemKind::ALL, with keys after them.
+fn encode_prefab(prefab: Prefab) -> [u8; 2] {
+    match prefab {
+        Prefab::Player => [0, 0],
+        Prefab::Chaser => [1, 0],
+        Prefab::Guard => [2, 0],
+        Prefab::Wanderer => [3, 0],
+        Prefab::Archer => [4, 0],
+        Prefab::Mage => [5, 0],
+        Prefab::Item(ItemKind::Key(key)) => [6, (ItemKind::ALL.len() + key as usize) as u8],
+        Prefab::Item(kind) => [
+            6,
+            ItemKind::ALL.iter().position(|k| *k == kind).unwrap_or(0) as u8,
+        ],
+        Prefab::Corpse => [7, 0],
+        Prefab::Torch => [8, 0],
+    }
+}
+
+fn decode_prefab(bytes: [u8; 2]) -> Option<Prefab> {
+    let [prefab, item] = bytes;
+
+    match prefab {
+        0 => Some(Prefab::Player),
+        1 => Some(Prefab::Chaser),
+        2 => Some(Prefab::Guard),
+        3 => Some(Prefab::Wanderer),
+        4 => Some(Prefab::Archer),
+        5 => Some(Prefab::Mage),
+        6 => {
+            let item = item as usize;
+            let kind = match item.checked_sub(ItemKind::ALL.len()) {
+                Some(key) => ItemKind::Key(*KeyKind::ALL.get(key)?),
+                None => *ItemKind::ALL.get(item)?,
+            };
+
+            Some(Prefab::Item(kind))
+        }
+        7 => Some(Prefab::Corpse),
+        8 => Some(Prefab::Torch),
+        _ => None,
+    }
+}
+
+// Each action starts with a byte for its kind, followed by its own values.
+fn encode_trigger_action(bytes: &mut Vec<u8>, action: TriggerAction) {
+    match action {
+        TriggerAction::Spikes { damage } => {
+            bytes.push(0);
+            bytes.extend_from_slice(&damage.to_le_bytes());
+        }
+        TriggerAction::ArrowTrap { origin } => {
+            bytes.push(1);
+            write_vec3(bytes, origin);
+        }
+        TriggerAction::Teleporter { destination } => {
+            bytes.push(2);
+            write_vec3(bytes, destination);
+        }
+        TriggerAction::Alarm { loudness } => {
+            bytes.push(3);
+            bytes.extend_from_slice(&loudness.to_le_bytes());
+        }
+        TriggerAction::OpenDoor { block } => {
+            bytes.push(4);
+
+            for n in [block.x, block.y, block.z] {
+                bytes.extend_from_slice(&n.to_le_bytes());
+            }
+        }
+        TriggerAction::SpawnEnemy { prefab, position } => {
+            bytes.push(5);
+            bytes.extend_from_slice(&encode_prefab(prefab));
+            write_vec3(bytes, position);
+        }
+    }
+}
+
+fn decode_trigger_action(reader: &mut Reader) -> Result<TriggerAction, io::Error> {
+    let action = match reader.read(1)?[0] {
+        0 => TriggerAction::Spikes {
+            damage: i32::from_le_bytes(reader.read_array()?),
+        },
+        1 => TriggerAction::ArrowTrap {
+            origin: reader.read_vec3()?,
+        },
+        2 => TriggerAction::Teleporter {
+            destination: reader.read_vec3()?,
+        },
+        3 => TriggerAction::Alarm {
+            loudness: reader.read_f32()?,
+        },
+        4 => TriggerAction::OpenDoor {
+            block: reader.read_block_position()?,
+        },
+        5 => TriggerAction::SpawnEnemy {
+            prefab: decode_prefab(reader.read_array()?)
+                .ok_or_else(|| invalid_data("Invalid prefab"))?,
+            position: reader.read_vec3()?,
+        },
+        _ => return Err(invalid_data("Invalid trigger")),
+    };
+
+    Ok(action)
+}
@@ -0,0 +1,453 @@
+// Levels written as text, one character per block. Each layer of blocks is a grid of rows along
+// the z axis, starting at the ground layer and going up, with a blank line between layers. Every
+// cell on the ground layer other than a space has a floor block put underneath it. Lines starting
+// with a semicolon are comments.
+//
+// Only what has a character in the tables below is kept when a level is written as text.
+
+use std::{collections::HashMap, io, path::Path};
+
+use cgmath::prelude::*;
+
+use crate::{
+    chunk::{Block, Chunk, Door, BLOCK_SIZE_F, CHUNK_HEIGHT, CHUNK_LEN, CHUNK_SIZE, GROUND_LAYER},
+    entities::{
+        item::{ItemKind, KeyKind},
+        prefab::Prefab,
+        trigger::{Trigger, TriggerAction},
+    },
+};
+
+use super::{invalid_data, Level, LevelTrigger};
+
+const COMMENT: char = ';';
+const EMPTY: char = '.';
+// Nothing at all, not even a floor.
+const PIT: char = ' ';
+const SPIKE_TRAP: char = 'x';
+// Opens the closest door when stepped on.
+const PRESSURE_PLATE: char = '_';
+
+const BLOCK_GLYPHS: [(char, Block); 6] = [
+    ('#', Block::Wall),
+    (
+        '+',
+        Block::Door(Door {
+            is_open: false,
+            lock: None,
+        }),
+    ),
+    (
+        '\'',
+        Block::Door(Door {
+            is_open: true,
+            lock: None,
+        }),
+    ),
+    (
+        'B',
+        Block::Door(Door {
+            is_open: false,
+            lock: Some(KeyKind::Bronze),
+        }),
+    ),
+    (
+        'S',
+        Block::Door(Door {
+            is_open: false,
+            lock: Some(KeyKind::Silver),
+        }),
+    ),
+    (
+        'G',
+        Block::Door(Door {
+            is_open: false,
+            lock: Some(KeyKind::Gold),
+        }),
+    ),
+];
+
+// Items use their inventory symbols where they aren't shared with another item.
+const PREFAB_GLYPHS: [(char, Prefab); 22] = [
+    ('@', Prefab::Player),
+    ('c', Prefab::Chaser),
+    ('g', Prefab::Guard),
+    ('w', Prefab::Wanderer),
+    ('a', Prefab::Archer),
+    ('m', Prefab::Mage),
+    ('t', Prefab::Torch),
+    ('%', Prefab::Corpse),
+    ('*', Prefab::Item(ItemKind::Trinket)),
+    (')', Prefab::Item(ItemKind::Bow)),
+    ('/', Prefab::Item(ItemKind::Wand)),
+    ('|', Prefab::Item(ItemKind::ThrowingKnife)),
+    ('\\', Prefab::Item(ItemKind::Sword)),
+    ('^', Prefab::Item(ItemKind::Spear)),
+    ('T', Prefab::Item(ItemKind::Hammer)),
+    ('(', Prefab::Item(ItemKind::Pickaxe)),
+    ('!', Prefab::Item(ItemKind::RegenerationPotion)),
+    ('h', Prefab::Item(ItemKind::HastePotion)),
+    ('i', Prefab::Item(ItemKind::InvisibilityPotion)),
+    ('1', Prefab::Item(ItemKind::Key(KeyKind::Bronze))),
+    ('2', Prefab::Item(ItemKind::Key(KeyKind::Silver))),
+    ('3', Prefab::Item(ItemKind::Key(KeyKind::Gold))),
+];
+
+impl Level {
+    pub fn from_text(text: &str) -> Result<Self, io::Error> {
+        let mut layers = Vec::<Vec<(usize, &str)>>::new();
+        let mut layer = Vec::new();
+
+        for (line_i, line) in text.lines().enumerate() {
+            if line.starts_with(COMMENT) {
+                continue;
+            }
+
+            if line.is_empty() {
+                if !layer.is_empty() {
+                    layers.push(std::mem::take(&mut layer));
+                }
+
+                continue;
+            }
+
+            layer.push((line_i + 1, line));
+        }
+
+        if !layer.is_empty() {
+            layers.push(layer);
+        }
+
+        if GROUND_LAYER as usize + layers.len() > CHUNK_HEIGHT {
+            return Err(invalid_data("Too many layers"));
+        }
+
+        let mut blocks = vec![Block::Air; CHUNK_LEN];
+        let mut spawns = Vec::new();
+        let mut triggers = Vec::new();
+        let mut pressure_plates = Vec::new();
+
+        for (layer_i, rows) in layers.iter().enumerate() {
+            let y = GROUND_LAYER + layer_i as i32;
+
+            if rows.len() > CHUNK_SIZE {
+                return Err(invalid_data(&format!(
+                    "Too many rows in the layer starting on line {}",
+                    rows[0].0
+                )));
+            }
+
+            for (z, (line_number, row)) in rows.iter().enumerate() {
+                for (x, glyph) in row.chars().enumerate() {
+                    let position = cgmath::vec3(x as i32, y, z as i32);
+                    let i = match Chunk::block_index(position.x, position.y, position.z) {
+                        Some(i) => i,
+                        None => {
+                            return Err(invalid_data(&format!("Line {} is too long", line_number)))
+                        }
+                    };
+
+                    if y == GROUND_LAYER && glyph != PIT {
+                        blocks[Chunk::block_index(position.x, 0, position.z).unwrap()] =
+                            Block::Wall;
+                    }
+
+                    if let Some((_, block)) = BLOCK_GLYPHS.iter().find(|(g, _)| *g == glyph) {
+                        blocks[i] = *block;
+                    } else if let Some((_, prefab)) =
+                        PREFAB_GLYPHS.iter().find(|(g, _)| *g == glyph)
+                    {
+                        spawns.push((*prefab, cell_center(position)));
+                    } else if glyph == SPIKE_TRAP {
+                        triggers.push(LevelTrigger::new(
+                            &Trigger::spike_trap(),
+                            cell_center(position),
+                        ));
+                    } else if glyph == PRESSURE_PLATE {
+                        pressure_plates.push(position);
+                    } else if glyph != EMPTY && glyph != PIT {
+                        return Err(invalid_data(&format!(
+                            "Unknown character '{}' on line {}",
+                            glyph, line_number
+                        )));
+                    }
+                }
+            }
+        }
+
+        for position in pressure_plates {
+            let door = closest_door(&blocks, position)
+                .ok_or_else(|| invalid_data("Pressure plate without a door to open"))?;
+
+            triggers.push(LevelTrigger::new(
+                &Trigger::pressure_plate(door),
+                cell_center(position),
+            ));
+        }
+
+        Ok(Self {
+            blocks,
+            spawns,
+            triggers,
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut glyphs = HashMap::new();
+
+        for trigger in &self.triggers {
+            let glyph = match trigger.action {
+                TriggerAction::Spikes { .. } => SPIKE_TRAP,
+                TriggerAction::OpenDoor { .. } => PRESSURE_PLATE,
+                _ => continue,
+            };
+
+            glyphs.insert(cell(trigger.position), glyph);
+        }
+
+        // Entities are drawn over triggers that they are standing on.
+        for (prefab, position) in &self.spawns {
+            if let Some((glyph, _)) = PREFAB_GLYPHS.iter().find(|(_, p)| p == prefab) {
+                glyphs.insert(cell(*position), *glyph);
+            }
+        }
+
+        let block_at = |x: i32, y: i32, z: i32| {
+            Chunk::block_index(x, y, z).map_or(Block::Air, |i| self.blocks[i])
+        };
+
+        // Leave out empty layers at the top.
+        let top_layer = (GROUND_LAYER..CHUNK_HEIGHT as i32)
+            .rev()
+            .find(|y| {
+                glyphs.keys().any(|p| p.y == *y)
+                    || (0..CHUNK_SIZE as i32)
+                        .any(|z| (0..CHUNK_SIZE as i32).any(|x| block_at(x, *y, z) != Block::Air))
+            })
+            .unwrap_or(GROUND_LAYER);
+
+        let mut text = String::new();
+
+        for y in GROUND_LAYER..=top_layer {
+            if y > GROUND_LAYER {
+                text.push('\n');
+            }
+
+            for z in 0..CHUNK_SIZE as i32 {
+                for x in 0..CHUNK_SIZE as i32 {
+                    let block = block_at(x, y, z);
+                    let glyph = match glyphs.get(&cgmath::vec3(x, y, z)) {
+                        Some(glyph) => *glyph,
+                        None if block == Block::Air => {
+                            if y == GROUND_LAYER && !block_at(x, 0, z).is_solid() {
+                                PIT
+                            } else {
+                                EMPTY
+                            }
+                        }
+                        None => BLOCK_GLYPHS
+                            .iter()
+                            .find(|(_, b)| *b == block)
+                            .map_or(EMPTY, |(g, _)| *g),
+                    };
+
+                    text.push(glyph);
+                }
+
+                text.push('\n');
+            }
+        }
+
+        text
+    }
+
+    pub fn save_text(&self, path: impl AsRef<Path>) -> Result<(), io::Error> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::write(path, self.to_text())
+    }
+
+    pub fn load_text(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+        Self::from_text(&std::fs::read_to_string(path)?)
+    }
+}
+
+fn cell(position: cgmath::Vector3<f32>) -> cgmath::Vector3<i32> {
+    (position / BLOCK_SIZE_F)
+        .map(|n| n.floor())
+        .cast::<i32>()
+        .unwrap()
+}
+
+fn cell_center(position: cgmath::Vector3<i32>) -> cgmath::Vector3<f32> {
+    (position.cast::<f32>().unwrap() + cgmath::vec3(0.5, 0.5, 0.5)) * BLOCK_SIZE_F
+}
+
+fn closest_door(blocks: &[Block], position: cgmath::Vector3<i32>) -> Option<cgmath::Vector3<i32>> {
+    let mut closest = None;
+    let mut closest_distance = i32::MAX;
+
+    for z in 0..CHUNK_SIZE as i32 {
+        for y in 0..CHUNK_HEIGHT as i32 {
+            for x in 0..CHUNK_SIZE as i32 {
+                let door = cgmath::vec3(x, y, z);
+                let distance = (door - position).magnitude2();
+
+                if matches!(blocks[Chunk::block_index(x, y, z).unwrap()], Block::Door(_))
+                    && distance < closest_distance
+                {
+                    closest = Some(door);
+                    closest_distance = distance;
+                }
+            }
+        }
+    }
+
+    closest
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        a_star::{a_star_search, reconstruct_path},
+        chunk::BLOCK_SIZE,
+    };
+
+    use super::*;
+
+    // A corridor that winds through an unlocked door to a chaser, with a spike trap, a key and a
+    // pressure plate on the way.
+    const ROOM: &str = "\
+#########
+#@..#...#
+###.#x#.#
+#...#.#.#
+#.###.#.#
+#1..+_#c#
+#########
+
+#########
+#.......#
+#########
+";
+
+    // Pad every layer out to the size of the chunk, which is how levels are written as text.
+    fn pad(text: &str) -> String {
+        let mut padded = String::new();
+
+        for (layer_i, layer) in text.split("\n\n").enumerate() {
+            if layer_i > 0 {
+                padded.push('\n');
+            }
+
+            let fill = if layer_i == 0 { PIT } else { EMPTY };
+            let mut rows = layer.lines().map(str::to_string).collect::<Vec<_>>();
+            rows.resize(CHUNK_SIZE, String::new());
+
+            for row in rows {
+                padded.push_str(&row);
+                padded.extend(std::iter::repeat(fill).take(CHUNK_SIZE - row.len()));
+                padded.push('\n');
+            }
+        }
+
+        padded
+    }
+
+    fn load_chunk(level: &Level) -> Chunk {
+        let mut chunk = Chunk::new();
+        chunk.load_blocks(&level.blocks);
+
+        chunk
+    }
+
+    fn spawn_cell(level: &Level, prefab: Prefab) -> cgmath::Vector3<i32> {
+        level
+            .spawns
+            .iter()
+            .find(|(p, _)| *p == prefab)
+            .map(|(_, position)| cell(*position))
+            .unwrap()
+    }
+
+    #[test]
+    fn round_trip_through_a_chunk() {
+        let text = pad(ROOM);
+        let level = Level::from_text(&text).unwrap();
+        let chunk = load_chunk(&level);
+
+        let captured = Level {
+            blocks: chunk.blocks().to_vec(),
+            spawns: level.spawns.clone(),
+            triggers: level.triggers.clone(),
+        };
+
+        assert_eq!(captured.to_text(), text);
+    }
+
+    #[test]
+    fn fixture_layout() {
+        let level = Level::from_text(ROOM).unwrap();
+        let chunk = load_chunk(&level);
+
+        // Ground layer cells get a floor, but pits around the room don't.
+        assert!(chunk.get_block(2, 0, 1));
+        assert!(!chunk.get_block(20, 0, 20));
+        assert_eq!(chunk.block_at(0, GROUND_LAYER + 1, 0), Block::Wall);
+        assert!(matches!(
+            chunk.block_at(4, GROUND_LAYER, 5),
+            Block::Door(Door { lock: None, .. })
+        ));
+
+        assert_eq!(spawn_cell(&level, Prefab::Player), cgmath::vec3(1, 1, 1));
+        assert_eq!(
+            spawn_cell(&level, Prefab::Item(ItemKind::Key(KeyKind::Bronze))),
+            cgmath::vec3(1, 1, 5)
+        );
+
+        let plate = level
+            .triggers
+            .iter()
+            .find(|t| matches!(t.action, TriggerAction::OpenDoor { .. }))
+            .unwrap();
+        assert_eq!(cell(plate.position), cgmath::vec3(5, 1, 5));
+        assert!(matches!(
+            plate.action,
+            TriggerAction::OpenDoor { block } if block == cgmath::vec3(4, 1, 5)
+        ));
+    }
+
+    #[test]
+    fn a_star_through_the_fixture() {
+        let path_in = |text: &str| {
+            let level = Level::from_text(text).unwrap();
+            let chunk = load_chunk(&level);
+            let start = spawn_cell(&level, Prefab::Player) * BLOCK_SIZE;
+            let goal = spawn_cell(&level, Prefab::Chaser) * BLOCK_SIZE;
+
+            let mut came_from = HashMap::new();
+            let mut path = Vec::new();
+            a_star_search(&chunk, start, goal, &mut came_from);
+            reconstruct_path(start, goal, &mut came_from, &mut path);
+
+            path.iter().map(|p| cell(*p)).collect::<Vec<_>>()
+        };
+
+        // There is only one way through, and it goes through the door.
+        let path = path_in(ROOM);
+        assert_eq!(path.len(), 22);
+        assert_eq!(path[0], cgmath::vec3(7, 1, 5));
+        assert!(path.contains(&cgmath::vec3(4, 1, 5)));
+
+        for step in path.windows(2) {
+            assert_eq!((step[0] - step[1]).map(i32::abs).sum(), 1);
+        }
+
+        // Ais can't go through locked doors.
+        assert!(path_in(&ROOM.replace('+', "B")).is_empty());
+    }
+}
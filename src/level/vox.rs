@@ -0,0 +1,433 @@
+// MagicaVoxel models. Blocks are matched to palette colours, so a model can be drawn with the
+// colours from an exported chunk to get doors and locks. MagicaVoxel has z pointing up, which is
+// y in the chunk. Both are right handed, so MagicaVoxel's y runs the opposite way to the chunk's
+// z, otherwise models would be mirrored.
+//
+// Only the first model in a file is used, and files without their own palette import as walls.
+
+use std::{io, path::Path};
+
+use crate::{
+    chunk::{Block, Chunk, Door, CHUNK_HEIGHT, CHUNK_SIZE},
+    entities::item::KeyKind,
+};
+
+use super::{invalid_data, Reader};
+
+const MAGIC: &[u8; 4] = b"VOX ";
+const VERSION: u32 = 150;
+const PALETTE_LEN: usize = 256;
+const WALL_COLOR: [f32; 3] = [0.5, 0.5, 0.5];
+const DOOR_COLOR: [f32; 3] = [0.55, 0.35, 0.2];
+// Open doors are a darker shade of the closed door.
+const OPEN_DOOR_SHADE: f32 = 0.5;
+
+pub struct VoxModel {
+    // In chunk axes.
+    size: cgmath::Vector3<i32>,
+    blocks: Vec<(cgmath::Vector3<i32>, Block)>,
+}
+
+impl VoxModel {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, io::Error> {
+        let mut reader = Reader { bytes, i: 0 };
+
+        if reader.read(MAGIC.len())? != MAGIC {
+            return Err(invalid_data("Not a .vox file"));
+        }
+
+        reader.read_u32()?;
+
+        let (id, content_len, _) = read_chunk_header(&mut reader)?;
+
+        if id != *b"MAIN" {
+            return Err(invalid_data("Missing MAIN chunk"));
+        }
+
+        reader.read(content_len)?;
+
+        let mut size = None;
+        let mut voxels = None;
+        let mut palette = None;
+
+        // Every other chunk is a child of MAIN.
+        while reader.i < bytes.len() {
+            let (id, content_len, children_len) = read_chunk_header(&mut reader)?;
+            let mut content = Reader {
+                bytes: reader.read(content_len)?,
+                i: 0,
+            };
+            reader.read(children_len)?;
+
+            match &id {
+                b"SIZE" if size.is_none() => {
+                    let x = content.read_u32()? as i32;
+                    let y = content.read_u32()? as i32;
+                    let z = content.read_u32()? as i32;
+                    size = Some(cgmath::vec3(x, z, y));
+                }
+                b"XYZI" if voxels.is_none() => {
+                    let count = content.read_u32()?;
+                    let mut model_voxels = Vec::with_capacity(count as usize);
+
+                    for _ in 0..count {
+                        model_voxels.push(content.read_array::<4>()?);
+                    }
+
+                    voxels = Some(model_voxels);
+                }
+                b"RGBA" => {
+                    let mut colors = [[0.0; 3]; PALETTE_LEN];
+
+                    for color in &mut colors {
+                        let [r, g, b, _] = content.read_array()?;
+                        *color = [r, g, b].map(|n| n as f32 / 255.0);
+                    }
+
+                    palette = Some(colors);
+                }
+                _ => (),
+            }
+        }
+
+        let size = size.ok_or_else(|| invalid_data("Missing SIZE chunk"))?;
+        let voxels = voxels.ok_or_else(|| invalid_data("Missing XYZI chunk"))?;
+
+        let blocks = voxels
+            .into_iter()
+            .map(|[x, y, z, color]| {
+                let position = cgmath::vec3(x as i32, z as i32, size.z - 1 - y as i32);
+
+                // Colour indices start at 1, and are stored from the start of the palette.
+                let block = palette
+                    .and_then(|p| p.get((color as usize).checked_sub(1)?).copied())
+                    .map_or(Block::Wall, closest_block);
+
+                (position, block)
+            })
+            .collect();
+
+        Ok(Self { size, blocks })
+    }
+
+    // The blocks of the model turned around the y axis a number of quarter turns, with its lowest
+    // corner at the offset. Some of them may be outside of the chunk.
+    pub fn blocks(
+        &self,
+        offset: cgmath::Vector3<i32>,
+        quarter_turns: u32,
+    ) -> impl Iterator<Item = (cgmath::Vector3<i32>, Block)> + '_ {
+        self.blocks.iter().map(move |(position, block)| {
+            let mut position = *position;
+            let mut size = self.size;
+
+            for _ in 0..quarter_turns % 4 {
+                position = cgmath::vec3(size.z - 1 - position.z, position.y, position.x);
+                size = cgmath::vec3(size.z, size.y, size.x);
+            }
+
+            (position + offset, *block)
+        })
+    }
+}
+
+// Write every block of the chunk to a .vox file.
+pub fn save_vox(chunk: &Chunk, path: impl AsRef<Path>) -> Result<(), io::Error> {
+    if let Some(parent) = path.as_ref().parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    std::fs::write(path, vox_bytes(chunk))
+}
+
+fn vox_bytes(chunk: &Chunk) -> Vec<u8> {
+    let palette = block_palette();
+
+    let mut size = Vec::new();
+    for n in [CHUNK_SIZE, CHUNK_SIZE, CHUNK_HEIGHT] {
+        size.extend_from_slice(&(n as u32).to_le_bytes());
+    }
+
+    let mut voxels = Vec::new();
+    let mut count = 0u32;
+
+    for z in 0..CHUNK_SIZE as i32 {
+        for y in 0..CHUNK_HEIGHT as i32 {
+            for x in 0..CHUNK_SIZE as i32 {
+                let block = chunk.block_at(x, y, z);
+
+                if let Some(i) = palette.iter().position(|(b, _)| *b == block) {
+                    let vox_y = CHUNK_SIZE as i32 - 1 - z;
+                    voxels.extend_from_slice(&[x as u8, vox_y as u8, y as u8, i as u8 + 1]);
+                    count += 1;
+                }
+            }
+        }
+    }
+
+    let mut xyzi = count.to_le_bytes().to_vec();
+    xyzi.extend(voxels);
+
+    let mut rgba = Vec::with_capacity(PALETTE_LEN * 4);
+    for i in 0..PALETTE_LEN {
+        let color = palette.get(i).map_or([0.0; 3], |(_, c)| *c);
+        rgba.extend(color.map(|n| (n * 255.0).round() as u8));
+        rgba.push(255);
+    }
+
+    let mut children = Vec::new();
+    write_chunk(&mut children, b"SIZE", &size, &[]);
+    write_chunk(&mut children, b"XYZI", &xyzi, &[]);
+    write_chunk(&mut children, b"RGBA", &rgba, &[]);
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    write_chunk(&mut bytes, b"MAIN", &[], &children);
+
+    bytes
+}
+
+fn read_chunk_header(reader: &mut Reader) -> Result<([u8; 4], usize, usize), io::Error> {
+    Ok((
+        reader.read_array()?,
+        reader.read_u32()? as usize,
+        reader.read_u32()? as usize,
+    ))
+}
+
+fn write_chunk(bytes: &mut Vec<u8>, id: &[u8; 4], content: &[u8], children: &[u8]) {
+    bytes.extend_from_slice(id);
+    bytes.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&(children.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(content);
+    bytes.extend_from_slice(children);
+}
+
+// Every solid or door block with the colour it has in a model.
+fn block_palette() -> Vec<(Block, [f32; 3])> {
+    let mut palette = vec![(Block::Wall, WALL_COLOR)];
+
+    for lock in [None].into_iter().chain(KeyKind::ALL.map(Some)) {
+        let color = lock.map_or(DOOR_COLOR, |k| k.color());
+
+        for is_open in [false, true] {
+            let shade = if is_open { OPEN_DOOR_SHADE } else { 1.0 };
+            palette.push((
+                Block::Door(Door { is_open, lock }),
+                color.map(|n| n * shade),
+            ));
+        }
+    }
+
+    palette
+}
+
+fn closest_block(color: [f32; 3]) -> Block {
+    let distance = |other: &[f32; 3]| -> f32 {
+        color
+            .iter()
+            .zip(other)
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum()
+    };
+
+    block_palette()
+        .into_iter()
+        .min_by(|a, b| distance(&a.1).total_cmp(&distance(&b.1)))
+        .map_or(Block::Wall, |(block, _)| block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::CHUNK_LEN;
+
+    // A model with the given size and voxels, as they are stored in the file.
+    fn model_bytes(size: [u32; 3], voxels: &[[u8; 4]], palette: Option<&[[u8; 4]]>) -> Vec<u8> {
+        let size = size
+            .iter()
+            .flat_map(|n| n.to_le_bytes())
+            .collect::<Vec<_>>();
+
+        let mut xyzi = (voxels.len() as u32).to_le_bytes().to_vec();
+        xyzi.extend(voxels.iter().flatten());
+
+        let mut children = Vec::new();
+        write_chunk(&mut children, b"SIZE", &size, &[]);
+        write_chunk(&mut children, b"XYZI", &xyzi, &[]);
+
+        if let Some(palette) = palette {
+            let mut rgba = palette.iter().flatten().copied().collect::<Vec<_>>();
+            rgba.resize(PALETTE_LEN * 4, 0);
+            write_chunk(&mut children, b"RGBA", &rgba, &[]);
+        }
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        write_chunk(&mut bytes, b"MAIN", &[], &children);
+
+        bytes
+    }
+
+    fn rgba(color: [f32; 3]) -> [u8; 4] {
+        let [r, g, b] = color.map(|n| (n * 255.0).round() as u8);
+        [r, g, b, 255]
+    }
+
+    fn sorted_blocks(
+        blocks: impl Iterator<Item = (cgmath::Vector3<i32>, Block)>,
+    ) -> Vec<([i32; 3], Block)> {
+        let mut blocks = blocks
+            .map(|(p, block)| (p.into(), block))
+            .collect::<Vec<_>>();
+        blocks.sort_by_key(|(p, _)| *p);
+
+        blocks
+    }
+
+    #[test]
+    fn chunk_round_trip() {
+        let mut chunk = Chunk::new();
+        for x in 0..CHUNK_SIZE as i32 {
+            chunk.set_block(true, x, 0, 3);
+        }
+        chunk.set_block(true, 0, CHUNK_HEIGHT as i32 - 1, CHUNK_SIZE as i32 - 1);
+
+        let mut x = 2;
+        for lock in [None].into_iter().chain(KeyKind::ALL.map(Some)) {
+            for is_open in [false, true] {
+                chunk.set_block_at(Block::Door(Door { is_open, lock }), x, 1, 5);
+                x += 1;
+            }
+        }
+
+        let model = VoxModel::from_bytes(&vox_bytes(&chunk)).unwrap();
+        let blocks = (0..CHUNK_LEN as i32).filter_map(|i| {
+            let position = cgmath::vec3(
+                i % CHUNK_SIZE as i32,
+                i / CHUNK_SIZE as i32 % CHUNK_HEIGHT as i32,
+                i / (CHUNK_SIZE * CHUNK_HEIGHT) as i32,
+            );
+            let block = chunk.block_at(position.x, position.y, position.z);
+
+            (block != Block::Air).then_some((position, block))
+        });
+
+        assert_eq!(model.size, cgmath::vec3(32, 8, 32));
+        assert_eq!(
+            sorted_blocks(model.blocks(cgmath::vec3(0, 0, 0), 0)),
+            sorted_blocks(blocks)
+        );
+    }
+
+    #[test]
+    fn models_are_not_mirrored() {
+        // MagicaVoxel's x, y and z are the chunk's x, -z and y.
+        let bytes = model_bytes([2, 3, 4], &[[1, 0, 3, 1]], None);
+        let model = VoxModel::from_bytes(&bytes).unwrap();
+
+        assert_eq!(model.size, cgmath::vec3(2, 4, 3));
+        assert_eq!(
+            sorted_blocks(model.blocks(cgmath::vec3(0, 0, 0), 0)),
+            [([1, 3, 2], Block::Wall)]
+        );
+    }
+
+    #[test]
+    fn palette_colors() {
+        let door = Block::Door(Door {
+            is_open: false,
+            lock: None,
+        });
+        let gold_door = Block::Door(Door {
+            is_open: false,
+            lock: Some(KeyKind::Gold),
+        });
+        let palette = [rgba(DOOR_COLOR), rgba(KeyKind::Gold.color())];
+
+        // Colour index 0 isn't in the palette, so it is a wall.
+        let voxels = [[0, 0, 0, 1], [1, 0, 0, 2], [2, 0, 0, 0]];
+        let model = VoxModel::from_bytes(&model_bytes([3, 1, 1], &voxels, Some(&palette))).unwrap();
+        assert_eq!(
+            sorted_blocks(model.blocks(cgmath::vec3(0, 0, 0), 0)),
+            [
+                ([0, 0, 0], door),
+                ([1, 0, 0], gold_door),
+                ([2, 0, 0], Block::Wall)
+            ]
+        );
+
+        // Without a palette, everything imports as walls.
+        let model = VoxModel::from_bytes(&model_bytes([3, 1, 1], &voxels, None)).unwrap();
+        assert!(model.blocks.iter().all(|(_, b)| *b == Block::Wall));
+    }
+
+    #[test]
+    fn quarter_turns() {
+        // An L shape, with a door on the short side so that every turn looks different.
+        let model = VoxModel {
+            size: cgmath::vec3(3, 1, 2),
+            blocks: vec![
+                (cgmath::vec3(0, 0, 0), Block::Wall),
+                (cgmath::vec3(1, 0, 0), Block::Wall),
+                (cgmath::vec3(2, 0, 0), Block::Wall),
+                (
+                    cgmath::vec3(0, 0, 1),
+                    Block::Door(Door {
+                        is_open: false,
+                        lock: None,
+                    }),
+                ),
+            ],
+        };
+        let turned = |turns| sorted_blocks(model.blocks(cgmath::vec3(0, 0, 0), turns));
+
+        assert_eq!(turned(4), turned(0));
+
+        let size = model.size;
+        let half_turn = sorted_blocks(model.blocks.iter().map(|(p, block)| {
+            let position = cgmath::vec3(size.x - 1 - p.x, p.y, size.z - 1 - p.z);
+            (position, *block)
+        }));
+        assert_eq!(turned(2), half_turn);
+
+        // Turning swaps the sides of the model, and keeps it within its new bounds.
+        let quarter_turn = turned(1);
+        assert!(quarter_turn
+            .iter()
+            .all(|(p, _)| (0..2).contains(&p[0]) && p[1] == 0 && (0..3).contains(&p[2])));
+        assert!(quarter_turn.contains(&([1, 0, 2], Block::Wall)));
+
+        // A turn keeps the handedness of the model, so it isn't mirrored.
+        let position = |turns, block: cgmath::Vector3<i32>| {
+            let i = model.blocks.iter().position(|(p, _)| *p == block).unwrap();
+            model.blocks(cgmath::vec3(0, 0, 0), turns).nth(i).unwrap().0
+        };
+        for turns in 0..4 {
+            let origin = position(turns, cgmath::vec3(0, 0, 0));
+            let x = position(turns, cgmath::vec3(1, 0, 0)) - origin;
+            let z = position(turns, cgmath::vec3(0, 0, 1)) - origin;
+
+            assert_eq!(x.cross(z), cgmath::vec3(0, -1, 0));
+        }
+    }
+
+    #[test]
+    fn truncated_files() {
+        let voxels = [[0, 0, 0, 1], [1, 0, 0, 1]];
+        let bytes = model_bytes([2, 1, 1], &voxels, Some(&[rgba(WALL_COLOR)]));
+
+        // Files can end after the voxels, since the palette is optional.
+        let voxels_end = 20 + 24 + 16 + voxels.len() * 4;
+        assert!(VoxModel::from_bytes(&bytes[..voxels_end]).is_ok());
+        assert!(VoxModel::from_bytes(&bytes).is_ok());
+
+        for len in (0..bytes.len()).filter(|len| *len != voxels_end) {
+            assert!(VoxModel::from_bytes(&bytes[..len]).is_err());
+        }
+    }
+}
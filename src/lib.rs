@@ -3,12 +3,17 @@ mod bytes;
 mod chunk;
 mod direction;
 mod entities;
+mod fov;
 mod gfx;
 mod input;
+mod level;
+mod light;
+mod map;
 mod math;
 mod ray;
 mod rng;
 mod simulation;
+mod steering;
 
 use std::time::Instant;
 
@@ -0,0 +1,315 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::chunk::{CHUNK_HEIGHT, CHUNK_SIZE};
+
+pub const MAX_LIGHT: u8 = 15;
+const LIGHT_LEN: usize = CHUNK_SIZE * CHUNK_HEIGHT * CHUNK_SIZE;
+const NEIGHBOR_OFFSETS: [[i32; 3]; 6] = [
+    [1, 0, 0],
+    [-1, 0, 0],
+    [0, 1, 0],
+    [0, -1, 0],
+    [0, 0, 1],
+    [0, 0, -1],
+];
+
+// Light levels for every block, spread flood fill style from light sources. Each step away from
+// a source loses one level of light, and solid blocks stop light completely.
+pub struct LightMap {
+    levels: Vec<u8>,
+    // Sources are identified by whatever owns them, usually an entity.
+    sources: HashMap<usize, (cgmath::Vector3<i32>, u8)>,
+    add_queue: VecDeque<cgmath::Vector3<i32>>,
+    remove_queue: VecDeque<(cgmath::Vector3<i32>, u8)>,
+    // Blocks whose light level was set since the changes were last taken. Recalculating
+    // everything clears it, since any block might have changed.
+    changes: Vec<cgmath::Vector3<i32>>,
+}
+
+impl LightMap {
+    pub fn new() -> Self {
+        Self {
+            levels: vec![0; LIGHT_LEN],
+            sources: HashMap::new(),
+            add_queue: VecDeque::new(),
+            remove_queue: VecDeque::new(),
+            changes: Vec::new(),
+        }
+    }
+
+    pub fn get(&self, x: i32, y: i32, z: i32) -> u8 {
+        Self::index(x, y, z).map_or(0, |i| self.levels[i])
+    }
+
+    // Adds or moves a light source. Returns true if any light levels changed.
+    pub fn set_source(
+        &mut self,
+        id: usize,
+        position: cgmath::Vector3<i32>,
+        level: u8,
+        is_solid: &impl Fn(i32, i32, i32) -> bool,
+    ) -> bool {
+        if self.sources.get(&id) == Some(&(position, level)) {
+            return false;
+        }
+
+        let mut has_changed = self.remove_source(id, is_solid);
+        self.sources.insert(id, (position, level));
+
+        if let Some(i) = Self::index(position.x, position.y, position.z) {
+            if self.levels[i] < level && !is_solid(position.x, position.y, position.z) {
+                self.levels[i] = level;
+                self.changes.push(position);
+                self.add_queue.push_back(position);
+                has_changed = true;
+            }
+        }
+
+        self.propagate(is_solid) || has_changed
+    }
+
+    // Returns true if any light levels changed.
+    pub fn remove_source(&mut self, id: usize, is_solid: &impl Fn(i32, i32, i32) -> bool) -> bool {
+        match self.sources.remove(&id) {
+            Some((position, _)) => self.darken(position, is_solid),
+            None => false,
+        }
+    }
+
+    // Every light level, in the same order as the chunk's blocks.
+    pub fn levels(&self) -> &[u8] {
+        &self.levels
+    }
+
+    pub fn take_changes(&mut self) -> Vec<cgmath::Vector3<i32>> {
+        std::mem::take(&mut self.changes)
+    }
+
+    // Recalculate all of the light from the sources, used when many blocks change at once.
+    pub fn recalculate(&mut self, is_solid: &impl Fn(i32, i32, i32) -> bool) {
+        self.levels.fill(0);
+        self.add_queue.clear();
+        self.remove_queue.clear();
+
+        self.relight_sources(is_solid);
+        self.propagate(is_solid);
+        self.changes.clear();
+    }
+
+    // Update the light around a block that has changed between solid and empty.
+    pub fn update_block(
+        &mut self,
+        position: cgmath::Vector3<i32>,
+        is_solid: &impl Fn(i32, i32, i32) -> bool,
+    ) -> bool {
+        if is_solid(position.x, position.y, position.z) {
+            return self.darken(position, is_solid);
+        }
+
+        // Let the neighbors' light spread into the newly opened block.
+        for offset in NEIGHBOR_OFFSETS {
+            let neighbor = position + cgmath::Vector3::from(offset);
+
+            if self.get(neighbor.x, neighbor.y, neighbor.z) > 1 {
+                self.add_queue.push_back(neighbor);
+            }
+        }
+
+        self.relight_sources(is_solid);
+        self.propagate(is_solid)
+    }
+
+    // Remove all of the light that could have come through a block, then fill the area back in
+    // from the edges of the darkened area and from any sources inside of it.
+    fn darken(
+        &mut self,
+        position: cgmath::Vector3<i32>,
+        is_solid: &impl Fn(i32, i32, i32) -> bool,
+    ) -> bool {
+        let i = match Self::index(position.x, position.y, position.z) {
+            Some(i) => i,
+            None => return false,
+        };
+
+        let level = self.levels[i];
+
+        if level == 0 {
+            return false;
+        }
+
+        self.levels[i] = 0;
+        self.changes.push(position);
+        self.remove_queue.push_back((position, level));
+
+        while let Some((position, level)) = self.remove_queue.pop_front() {
+            for offset in NEIGHBOR_OFFSETS {
+                let neighbor = position + cgmath::Vector3::from(offset);
+                let neighbor_i = match Self::index(neighbor.x, neighbor.y, neighbor.z) {
+                    Some(i) => i,
+                    None => continue,
+                };
+                let neighbor_level = self.levels[neighbor_i];
+
+                if neighbor_level != 0 && neighbor_level < level {
+                    self.levels[neighbor_i] = 0;
+                    self.changes.push(neighbor);
+                    self.remove_queue.push_back((neighbor, neighbor_level));
+                } else if neighbor_level >= level {
+                    self.add_queue.push_back(neighbor);
+                }
+            }
+        }
+
+        self.relight_sources(is_solid);
+        self.propagate(is_solid);
+
+        true
+    }
+
+    fn relight_sources(&mut self, is_solid: &impl Fn(i32, i32, i32) -> bool) {
+        for (position, level) in self.sources.values() {
+            let i = match Self::index(position.x, position.y, position.z) {
+                Some(i) => i,
+                None => continue,
+            };
+
+            if self.levels[i] < *level && !is_solid(position.x, position.y, position.z) {
+                self.levels[i] = *level;
+                self.changes.push(*position);
+                self.add_queue.push_back(*position);
+            }
+        }
+    }
+
+    fn propagate(&mut self, is_solid: &impl Fn(i32, i32, i32) -> bool) -> bool {
+        let mut has_changed = false;
+
+        while let Some(position) = self.add_queue.pop_front() {
+            let level = self.get(position.x, position.y, position.z);
+
+            if level <= 1 {
+                continue;
+            }
+
+            for offset in NEIGHBOR_OFFSETS {
+                let neighbor = position + cgmath::Vector3::from(offset);
+                let neighbor_i = match Self::index(neighbor.x, neighbor.y, neighbor.z) {
+                    Some(i) => i,
+                    None => continue,
+                };
+
+                if self.levels[neighbor_i] + 1 >= level
+                    || is_solid(neighbor.x, neighbor.y, neighbor.z)
+                {
+                    continue;
+                }
+
+                self.levels[neighbor_i] = level - 1;
+                self.changes.push(neighbor);
+                self.add_queue.push_back(neighbor);
+                has_changed = true;
+            }
+        }
+
+        has_changed
+    }
+
+    fn index(x: i32, y: i32, z: i32) -> Option<usize> {
+        let i_chunk_size = CHUNK_SIZE as i32;
+        let i_chunk_height = CHUNK_HEIGHT as i32;
+        if x < 0 || x >= i_chunk_size || y < 0 || y >= i_chunk_height || z < 0 || z >= i_chunk_size
+        {
+            return None;
+        }
+
+        Some(x as usize + y as usize * CHUNK_SIZE + z as usize * CHUNK_SIZE * CHUNK_HEIGHT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    // The light that the sources should give with the blocks as they are now.
+    fn recalculated(
+        sources: &[(usize, cgmath::Vector3<i32>, u8)],
+        is_solid: &impl Fn(i32, i32, i32) -> bool,
+    ) -> Vec<u8> {
+        let mut light = LightMap::new();
+        for (id, position, level) in sources {
+            light.sources.insert(*id, (*position, *level));
+        }
+        light.recalculate(is_solid);
+
+        light.levels
+    }
+
+    #[test]
+    fn removing_a_torch_leaves_no_light() {
+        let mut light = LightMap::new();
+        let is_solid = |_, _, _| false;
+
+        light.set_source(1, cgmath::vec3(10, 2, 10), MAX_LIGHT, &is_solid);
+        assert_eq!(light.get(10, 2, 10), MAX_LIGHT);
+        assert_eq!(light.get(13, 2, 10), MAX_LIGHT - 3);
+
+        light.remove_source(1, &is_solid);
+        assert!(light.levels().iter().all(|l| *l == 0));
+    }
+
+    #[test]
+    fn removing_one_of_two_overlapping_sources() {
+        let mut light = LightMap::new();
+        let is_solid = |_, _, _| false;
+        let other = (2, cgmath::vec3(14, 3, 12), 12);
+
+        light.set_source(1, cgmath::vec3(10, 2, 10), MAX_LIGHT, &is_solid);
+        light.set_source(other.0, other.1, other.2, &is_solid);
+        light.remove_source(1, &is_solid);
+
+        assert_eq!(light.levels(), recalculated(&[other], &is_solid));
+        assert_eq!(light.get(14, 3, 12), 12);
+    }
+
+    #[test]
+    fn closing_a_gap_darkens_the_far_side() {
+        // A wall across the whole chunk, with a gap in it.
+        let mut solid = HashSet::new();
+        for z in 0..CHUNK_SIZE as i32 {
+            for y in 0..CHUNK_HEIGHT as i32 {
+                if (y, z) != (2, 10) {
+                    solid.insert(cgmath::vec3(16, y, z));
+                }
+            }
+        }
+
+        let mut light = LightMap::new();
+        let source = (1, cgmath::vec3(12, 2, 10), MAX_LIGHT);
+        light.set_source(source.0, source.1, source.2, &|x, y, z| {
+            solid.contains(&cgmath::vec3(x, y, z))
+        });
+        assert!(light.get(18, 2, 10) > 0);
+
+        solid.insert(cgmath::vec3(16, 2, 10));
+        let is_solid = |x, y, z| solid.contains(&cgmath::vec3(x, y, z));
+        light.update_block(cgmath::vec3(16, 2, 10), &is_solid);
+
+        assert_eq!(light.get(18, 2, 10), 0);
+        assert_eq!(light.levels(), recalculated(&[source], &is_solid));
+    }
+
+    #[test]
+    fn recalculating_clears_the_changes() {
+        let mut light = LightMap::new();
+        let is_solid = |_, _, _| false;
+
+        light.set_source(1, cgmath::vec3(10, 2, 10), MAX_LIGHT, &is_solid);
+        assert!(!light.take_changes().is_empty());
+
+        light.set_source(2, cgmath::vec3(20, 2, 20), MAX_LIGHT, &is_solid);
+        light.recalculate(&is_solid);
+        assert!(light.take_changes().is_empty());
+    }
+}
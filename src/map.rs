@@ -0,0 +1,195 @@
+use image::{Rgba, RgbaImage};
+
+use crate::chunk::{Chunk, CHUNK_SIZE};
+
+// The width and height of each block on the map, in pixels.
+pub const MAP_BLOCK_PIXELS: u32 = 4;
+pub const MAP_PIXELS: u32 = CHUNK_SIZE as u32 * MAP_BLOCK_PIXELS;
+
+// Pixels with no alpha aren't drawn, so unexplored areas are left empty.
+const UNEXPLORED_COLOR: Rgba<u8> = Rgba([0, 0, 0, 0]);
+const FLOOR_COLOR: Rgba<u8> = Rgba([40, 40, 48, 255]);
+const WALL_COLOR: Rgba<u8> = Rgba([110, 110, 120, 255]);
+const VISIBLE_FLOOR_COLOR: Rgba<u8> = Rgba([70, 70, 84, 255]);
+const VISIBLE_WALL_COLOR: Rgba<u8> = Rgba([180, 180, 190, 255]);
+const PLAYER_COLOR: Rgba<u8> = Rgba([80, 220, 80, 255]);
+const HEADING_COLOR: Rgba<u8> = Rgba([200, 255, 200, 255]);
+const ITEM_COLOR: Rgba<u8> = Rgba([230, 200, 60, 255]);
+const ENEMY_COLOR: Rgba<u8> = Rgba([220, 60, 60, 255]);
+const TRAP_COLOR: Rgba<u8> = Rgba([200, 100, 220, 255]);
+
+#[derive(Copy, Clone, Debug)]
+pub enum MapMarkerKind {
+    // The heading is the direction that the player is facing on the x/z plane.
+    Player { heading: cgmath::Vector2<f32> },
+    Item,
+    Enemy,
+    Trap,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct MapMarker {
+    pub x: i32,
+    pub z: i32,
+    pub kind: MapMarkerKind,
+}
+
+// Draw a top down view of one layer of the chunk, only including areas that have been explored.
+// Each row of the image is a row of blocks along the z axis.
+pub fn build_map_image(chunk: &Chunk, layer: i32, markers: &[MapMarker]) -> RgbaImage {
+    let mut image = RgbaImage::from_pixel(MAP_PIXELS, MAP_PIXELS, UNEXPLORED_COLOR);
+
+    for z in 0..CHUNK_SIZE as i32 {
+        for x in 0..CHUNK_SIZE as i32 {
+            if !chunk.is_explored(x, z) {
+                continue;
+            }
+
+            let is_wall = chunk.get_block(x, layer, z);
+            let color = match (is_wall, chunk.is_visible(x, z)) {
+                (true, true) => VISIBLE_WALL_COLOR,
+                (true, false) => WALL_COLOR,
+                (false, true) => VISIBLE_FLOOR_COLOR,
+                (false, false) => FLOOR_COLOR,
+            };
+
+            fill_block(&mut image, x, z, color);
+        }
+    }
+
+    // Draw the player last so that it is always on top.
+    for marker in markers
+        .iter()
+        .filter(|m| !matches!(m.kind, MapMarkerKind::Player { .. }))
+        .chain(
+            markers
+                .iter()
+                .filter(|m| matches!(m.kind, MapMarkerKind::Player { .. })),
+        )
+    {
+        match marker.kind {
+            MapMarkerKind::Player { heading } => {
+                fill_block(&mut image, marker.x, marker.z, PLAYER_COLOR);
+                draw_heading(&mut image, marker.x, marker.z, heading);
+            }
+            MapMarkerKind::Item => fill_block(&mut image, marker.x, marker.z, ITEM_COLOR),
+            MapMarkerKind::Enemy => fill_block(&mut image, marker.x, marker.z, ENEMY_COLOR),
+            MapMarkerKind::Trap => fill_block(&mut image, marker.x, marker.z, TRAP_COLOR),
+        }
+    }
+
+    image
+}
+
+fn fill_block(image: &mut RgbaImage, x: i32, z: i32, color: Rgba<u8>) {
+    let i_chunk_size = CHUNK_SIZE as i32;
+    if x < 0 || x >= i_chunk_size || z < 0 || z >= i_chunk_size {
+        return;
+    }
+
+    let start_x = x as u32 * MAP_BLOCK_PIXELS;
+    let start_y = z as u32 * MAP_BLOCK_PIXELS;
+
+    for y in start_y..start_y + MAP_BLOCK_PIXELS {
+        for x in start_x..start_x + MAP_BLOCK_PIXELS {
+            image.put_pixel(x, y, color);
+        }
+    }
+}
+
+// Draw a short line from the center of the player's block towards where they are facing.
+fn draw_heading(image: &mut RgbaImage, x: i32, z: i32, heading: cgmath::Vector2<f32>) {
+    let center_x = (x as f32 + 0.5) * MAP_BLOCK_PIXELS as f32;
+    let center_y = (z as f32 + 0.5) * MAP_BLOCK_PIXELS as f32;
+
+    for step in 1..=MAP_BLOCK_PIXELS * 2 {
+        let pixel_x = (center_x + heading.x * step as f32).floor();
+        let pixel_y = (center_y + heading.y * step as f32).floor();
+
+        if pixel_x < 0.0
+            || pixel_y < 0.0
+            || pixel_x >= MAP_PIXELS as f32
+            || pixel_y >= MAP_PIXELS as f32
+        {
+            return;
+        }
+
+        image.put_pixel(pixel_x as u32, pixel_y as u32, HEADING_COLOR);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_pixel(image: &RgbaImage, x: i32, z: i32) -> Rgba<u8> {
+        *image.get_pixel(x as u32 * MAP_BLOCK_PIXELS, z as u32 * MAP_BLOCK_PIXELS)
+    }
+
+    fn is_empty(image: &RgbaImage) -> bool {
+        image.pixels().all(|p| *p == UNEXPLORED_COLOR)
+    }
+
+    #[test]
+    fn unexplored_columns_are_transparent() {
+        let mut chunk = Chunk::new();
+        assert!(is_empty(&build_map_image(&chunk, 1, &[])));
+
+        chunk.update_visibility(cgmath::vec3(3, 1, 3), 4);
+        let image = build_map_image(&chunk, 1, &[]);
+
+        assert_ne!(block_pixel(&image, 3, 3), UNEXPLORED_COLOR);
+        assert_eq!(block_pixel(&image, 20, 20), UNEXPLORED_COLOR);
+    }
+
+    #[test]
+    fn remembered_walls_are_darker_than_visible_ones() {
+        let mut chunk = Chunk::new();
+        chunk.set_block(true, 5, 1, 3);
+
+        chunk.update_visibility(cgmath::vec3(3, 1, 3), 4);
+        let visible = block_pixel(&build_map_image(&chunk, 1, &[]), 5, 3);
+
+        chunk.update_visibility(cgmath::vec3(25, 1, 25), 4);
+        let remembered = block_pixel(&build_map_image(&chunk, 1, &[]), 5, 3);
+
+        assert_eq!(visible, VISIBLE_WALL_COLOR);
+        assert_eq!(remembered, WALL_COLOR);
+        assert_ne!(visible, remembered);
+    }
+
+    #[test]
+    fn player_is_drawn_over_other_markers() {
+        let chunk = Chunk::new();
+        let marker = |kind| MapMarker { x: 7, z: 9, kind };
+        let player = MapMarkerKind::Player {
+            heading: cgmath::vec2(1.0, 0.0),
+        };
+
+        let markers = [
+            marker(player),
+            marker(MapMarkerKind::Item),
+            marker(MapMarkerKind::Enemy),
+        ];
+        let image = build_map_image(&chunk, 1, &markers);
+
+        assert_eq!(block_pixel(&image, 7, 9), PLAYER_COLOR);
+    }
+
+    #[test]
+    fn blocks_outside_of_the_chunk_are_ignored() {
+        let mut image = RgbaImage::from_pixel(MAP_PIXELS, MAP_PIXELS, UNEXPLORED_COLOR);
+        let size = CHUNK_SIZE as i32;
+
+        for (x, z) in [(-1, 0), (0, -1), (size, 0), (0, size), (-5, size + 5)] {
+            fill_block(&mut image, x, z, PLAYER_COLOR);
+            draw_heading(&mut image, x, z, cgmath::vec2(-1.0, -1.0));
+        }
+        assert!(is_empty(&image));
+
+        // Headings are cut off at the edge of the map.
+        draw_heading(&mut image, size - 1, size - 1, cgmath::vec2(1.0, 1.0));
+        let last = MAP_PIXELS - 1;
+        assert_eq!(*image.get_pixel(last, last), HEADING_COLOR);
+    }
+}
@@ -4,15 +4,6 @@ pub struct Ray {
 }
 
 impl Ray {
-    pub fn intersects(
-        &self,
-        target_position: cgmath::Vector3<f32>,
-        target_size: cgmath::Vector3<f32>,
-    ) -> bool {
-        self.intersection_distance(target_position, target_size)
-            .is_some()
-    }
-
     pub fn intersection_point(
         &self,
         target_position: cgmath::Vector3<f32>,
@@ -23,9 +14,9 @@ impl Ray {
         dist.map(|d| d * self.dir + self.position)
     }
 
-    // A ray will intersect a rect unless either:
-    // - The ray passes the rect on the z axis before reaching it on the x axis,
-    // - The ray passes the rect on the x axis before reaching it on the z axis,
+    // Slab test: a ray will intersect a box unless it leaves one of the box's axis aligned slabs
+    // before it has entered all of the others. Returns the distance along the ray where it enters
+    // the box, which is negative if the ray starts inside of it.
     pub fn intersection_distance(
         &self,
         target_position: cgmath::Vector3<f32>,
@@ -37,20 +28,19 @@ impl Ray {
         let target_min = target_position - target_size * 0.5;
         let target_max = target_position + target_size * 0.5;
 
-        if self.dir.x != 0.0 {
-            let tx1 = (target_min.x - self.position.x) / self.dir.x;
-            let tx2 = (target_max.x - self.position.x) / self.dir.x;
-
-            t_min = t_min.max(tx1.min(tx2));
-            t_max = t_max.min(tx1.max(tx2));
-        }
-
-        if self.dir.z != 0.0 {
-            let tz1 = (target_min.z - self.position.z) / self.dir.z;
-            let tz2 = (target_max.z - self.position.z) / self.dir.z;
-
-            t_min = t_min.max(tz1.min(tz2));
-            t_max = t_max.min(tz1.max(tz2));
+        for axis in 0..3 {
+            if self.dir[axis] != 0.0 {
+                let t1 = (target_min[axis] - self.position[axis]) / self.dir[axis];
+                let t2 = (target_max[axis] - self.position[axis]) / self.dir[axis];
+
+                t_min = t_min.max(t1.min(t2));
+                t_max = t_max.min(t1.max(t2));
+            } else if self.position[axis] < target_min[axis]
+                || self.position[axis] > target_max[axis]
+            {
+                // The ray is parallel to this slab and outside of it.
+                return None;
+            }
         }
 
         let hit = t_max >= 0.0 && t_max >= t_min;
@@ -16,4 +16,9 @@ impl Rng {
         self.state ^= self.state << 5;
         self.state % max
     }
+
+    // Returns true with the given probability, between 0 and 1.
+    pub fn chance(&mut self, probability: f32) -> bool {
+        (self.range(10000) as f32) < probability * 10000.0
+    }
 }
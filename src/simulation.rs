@@ -1,32 +1,58 @@
 use crate::chunk::Chunk;
-use crate::entities::actor::{Actor, ActorSystem};
-use crate::entities::chase_ai::{ChaseAi, ChaseAiSystem};
-use crate::entities::display::Display;
+use crate::entities::ability::AbilitySystem;
+use crate::entities::ability_display::AbilityDisplaySystem;
+use crate::entities::actor::Actor;
+use crate::entities::actor::ActorSystem;
+use crate::entities::ai::AiSystem;
+use crate::entities::attributes::AttributesSystem;
+use crate::entities::damage::DamageSystem;
+use crate::entities::death::DeathSystem;
+use crate::entities::door::DoorSystem;
 use crate::entities::ecs::{CommandQueue, Ecs, EntityManager, SystemManager};
+use crate::entities::editor::EditorSystem;
 use crate::entities::entity_instances_system::EntityInstancesSystem;
-use crate::entities::fighter::{Fighter, FighterSystem};
-use crate::entities::health::{Health, HealthSystem};
-use crate::entities::health_display::{HealthDisplay, HealthDisplaySystem};
-use crate::entities::inventory::{Inventory, InventorySystem};
-use crate::entities::inventory_display::{InventoryDisplay, InventoryDisplaySystem};
-use crate::entities::item::Item;
-use crate::entities::player::{Player, PlayerMovementSystem};
+use crate::entities::experience::ExperienceSystem;
+use crate::entities::field_of_view::FieldOfViewSystem;
+use crate::entities::fighter::FighterSystem;
+use crate::entities::health::HealthSystem;
+use crate::entities::health_display::HealthDisplaySystem;
+use crate::entities::inventory::InventorySystem;
+use crate::entities::inventory_display::InventoryDisplaySystem;
+use crate::entities::item::ItemKind;
+use crate::entities::level_up_display::LevelUpDisplaySystem;
+use crate::entities::light_source::LightingSystem;
+use crate::entities::map_display::MapDisplaySystem;
+use crate::entities::mining::MiningSystem;
+use crate::entities::perception::PerceptionSystem;
+use crate::entities::player::PlayerMovementSystem;
+use crate::entities::prefab::Prefab;
+use crate::entities::projectile::ProjectileSystem;
+use crate::entities::status_effect::StatusEffectSystem;
+use crate::entities::status_effect_display::StatusEffectDisplaySystem;
+use crate::entities::trigger::{Trigger, TriggerSystem};
 use crate::gfx::gui::Gui;
 use crate::gfx::instance::Instance;
 use crate::input::Input;
 use crate::rng::Rng;
 use cgmath::prelude::*;
 use std::time::{SystemTime, UNIX_EPOCH};
+use winit::event::VirtualKeyCode;
 
-const HUMANOID_SIZE: cgmath::Vector3<f32> = cgmath::vec3(1.0, 1.0, 1.0);
-const ITEM_SIZE: cgmath::Vector3<f32> = cgmath::vec3(1.0, 1.0, 1.0);
+const TRAP_COUNT: usize = 8;
+const TORCH_COUNT: usize = 6;
+const EDITOR_KEY: VirtualKeyCode = VirtualKeyCode::F2;
 
 pub struct Simulation {
     pub chunk: Chunk,
     ecs: Ecs,
     systems: SystemManager,
+    // Only the editor and what is needed to show the level run while editing.
+    editor_systems: SystemManager,
+    is_editing: bool,
     player: usize,
     gui: Gui,
+    hit_stop_timer: f32,
+    cause_of_death: Option<String>,
 }
 
 impl Simulation {
@@ -38,7 +64,7 @@ impl Simulation {
                 .as_millis() as u32,
         );
         let mut chunk = Chunk::new();
-        chunk.generate_blocks(&mut rng);
+        let level_items = chunk.generate_blocks(&mut rng);
 
         let mut ecs = Ecs {
             manager: EntityManager::new(),
@@ -46,60 +72,84 @@ impl Simulation {
             entity_cache: Vec::new(),
         };
 
-        let mut player_actor = Actor::new(cgmath::Vector3::zero(), HUMANOID_SIZE, 6.0);
+        let player_spawn = chunk
+            .get_spawn_position(&mut rng)
+            .unwrap_or(cgmath::Vector3::zero());
+        let player = Prefab::Player.spawn(&mut ecs.manager, player_spawn);
 
-        if let Some(player_spawn) = chunk.get_spawn_position(&mut rng) {
-            player_actor.teleport(player_spawn);
+        for prefab in [
+            Prefab::Chaser,
+            Prefab::Guard,
+            Prefab::Wanderer,
+            Prefab::Archer,
+            Prefab::Mage,
+        ] {
+            if let Some(enemy_spawn) = chunk.get_spawn_position(&mut rng) {
+                prefab.spawn(&mut ecs.manager, enemy_spawn);
+            }
         }
 
-        let mut enemy_actor = Actor::new(cgmath::Vector3::zero(), HUMANOID_SIZE, 6.0);
-
-        if let Some(enemy_spawn) = chunk.get_spawn_position(&mut rng) {
-            enemy_actor.teleport(enemy_spawn);
+        for (kind, position) in level_items {
+            Prefab::Item(kind).spawn(&mut ecs.manager, position);
         }
 
-        let player = ecs.manager.add_entity();
-        ecs.manager.add_component_to_entity(player, player_actor);
-        ecs.manager.add_component_to_entity(player, Player {});
-        ecs.manager
-            .add_component_to_entity(player, Fighter::new(25, 0.25));
-        ecs.manager
-            .add_component_to_entity(player, Health::new(100));
-        ecs.manager
-            .add_component_to_entity(player, HealthDisplay {});
-        ecs.manager
-            .add_component_to_entity(player, Inventory::new());
-        ecs.manager
-            .add_component_to_entity(player, InventoryDisplay {});
-        let enemy = ecs.manager.add_entity();
-        ecs.manager.add_component_to_entity(enemy, enemy_actor);
-        ecs.manager.add_component_to_entity(enemy, ChaseAi::new());
-        ecs.manager.add_component_to_entity(enemy, Display::new(1));
-        ecs.manager.add_component_to_entity(enemy, Health::new(50));
-        ecs.manager
-            .add_component_to_entity(enemy, Fighter::new(10, 0.5));
-
         for _ in 0..10 {
             if let Some(item_spawn) = chunk.get_spawn_position(&mut rng) {
-                let test_item = ecs.manager.add_entity();
-                ecs.manager
-                    .add_component_to_entity(test_item, Actor::new(item_spawn, ITEM_SIZE, 0.0));
-                ecs.manager
-                    .add_component_to_entity(test_item, Display::new(0));
-                ecs.manager.add_component_to_entity(test_item, Item {});
+                let kind = ItemKind::ALL[rng.range(ItemKind::ALL.len() as u32) as usize];
+                Prefab::Item(kind).spawn(&mut ecs.manager, item_spawn);
+            }
+        }
+
+        for _ in 0..TRAP_COUNT {
+            let trap = chunk.get_spawn_position(&mut rng).and_then(|trap_spawn| {
+                Trigger::random_trap(&chunk, &mut rng, trap_spawn).map(|trap| (trap, trap_spawn))
+            });
+
+            if let Some((trap, trap_spawn)) = trap {
+                trap.spawn(&mut ecs.manager, trap_spawn);
+            }
+        }
+
+        for _ in 0..TORCH_COUNT {
+            if let Some(torch_spawn) = chunk.get_spawn_position(&mut rng) {
+                Prefab::Torch.spawn(&mut ecs.manager, torch_spawn);
             }
         }
 
         let mut systems = SystemManager::new();
         systems.add_system(ActorSystem {});
-        systems.add_system(ChaseAiSystem {});
-        systems.add_system(PlayerMovementSystem {});
+        systems.add_system(StatusEffectSystem {});
+        systems.add_system(AttributesSystem {});
+        systems.add_system(AiSystem::new(rng.range(u32::MAX) + 1));
+        systems.add_system(PlayerMovementSystem::new());
+        systems.add_system(TriggerSystem::new(rng.range(u32::MAX) + 1));
+        systems.add_system(DoorSystem {});
+        systems.add_system(MiningSystem {});
+        systems.add_system(LightingSystem::new());
+        systems.add_system(FieldOfViewSystem {});
         systems.add_system(EntityInstancesSystem::new());
         systems.add_system(FighterSystem::new());
+        systems.add_system(AbilitySystem::new());
+        systems.add_system(ProjectileSystem::new());
+        systems.add_system(DamageSystem::new(rng.range(u32::MAX) + 1));
         systems.add_system(HealthDisplaySystem {});
+        systems.add_system(StatusEffectDisplaySystem {});
         systems.add_system(HealthSystem {});
+        systems.add_system(ExperienceSystem {});
+        systems.add_system(DeathSystem::new(rng.range(u32::MAX) + 1));
         systems.add_system(InventorySystem::new());
+        systems.add_system(PerceptionSystem {});
         systems.add_system(InventoryDisplaySystem::new());
+        systems.add_system(LevelUpDisplaySystem {});
+        systems.add_system(AbilityDisplaySystem {});
+        systems.add_system(MapDisplaySystem::new());
+
+        let mut editor_systems = SystemManager::new();
+        editor_systems.add_system(ActorSystem {});
+        editor_systems.add_system(PlayerMovementSystem::flying());
+        editor_systems.add_system(EditorSystem::new());
+        editor_systems.add_system(LightingSystem::new());
+        editor_systems.add_system(EntityInstancesSystem::new());
 
         let gui = Gui::new();
 
@@ -107,15 +157,68 @@ impl Simulation {
             chunk,
             ecs,
             systems,
+            editor_systems,
+            is_editing: false,
             player,
             gui,
+            hit_stop_timer: 0.0,
+            cause_of_death: None,
         }
     }
 
     pub fn update(&mut self, input: &mut Input, delta_time: f32) {
         self.gui.clear();
+
+        if let Some(ref cause_of_death) = self.cause_of_death {
+            // The game is frozen until the player restarts.
+            self.gui.write("Press R to restart");
+            self.gui.write(cause_of_death);
+            self.gui.write("You died!");
+
+            if input.was_key_pressed(VirtualKeyCode::R) {
+                *self = Self::new();
+            }
+
+            return;
+        }
+
+        if let Some(death) = self
+            .ecs
+            .queue
+            .deaths()
+            .iter()
+            .find(|death| death.entity == self.player)
+        {
+            self.cause_of_death = Some(death.cause_of_death(&self.ecs.manager));
+            return;
+        }
+
+        // Freeze the simulation for a moment after heavy hits.
+        self.hit_stop_timer = self.hit_stop_timer.max(self.ecs.queue.hit_stop_duration());
         self.ecs.flush_queue(&mut self.chunk);
 
+        if input.was_key_pressed(EDITOR_KEY) {
+            self.set_editing(!self.is_editing);
+        }
+
+        if self.is_editing {
+            self.editor_systems.update(
+                &mut self.ecs,
+                &mut self.chunk,
+                input,
+                &mut self.gui,
+                delta_time,
+            );
+            return;
+        }
+
+        let delta_time = if self.hit_stop_timer > 0.0 {
+            self.hit_stop_timer -= delta_time;
+            0.0
+        } else {
+            delta_time
+        };
+
         self.systems.update(
             &mut self.ecs,
             &mut self.chunk,
@@ -125,11 +228,33 @@ impl Simulation {
         );
     }
 
+    // The player flies around while editing, and the whole level is shown.
+    fn set_editing(&mut self, is_editing: bool) {
+        self.is_editing = is_editing;
+
+        if let Some(mut actors) = self.ecs.manager.borrow_components::<Actor>() {
+            if let Some(actor) = actors.get_mut(self.player) {
+                actor.set_gravity(!is_editing);
+            }
+        }
+
+        if is_editing {
+            self.chunk.reveal_all();
+        }
+    }
+
     pub fn entity_instances(&self) -> &Vec<Instance> {
-        self.systems
-            .get::<EntityInstancesSystem>()
-            .unwrap()
-            .instances()
+        let systems = if self.is_editing {
+            &self.editor_systems
+        } else {
+            &self.systems
+        };
+
+        systems.get::<EntityInstancesSystem>().unwrap().instances()
+    }
+
+    pub fn map_display(&self) -> &MapDisplaySystem {
+        self.systems.get::<MapDisplaySystem>().unwrap()
     }
 
     pub fn gui_instances(&self) -> &Vec<Instance> {
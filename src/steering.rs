@@ -0,0 +1,140 @@
+use cgmath::prelude::*;
+
+const SEPARATION_PADDING: f32 = 0.25;
+const SEPARATION_WEIGHT: f32 = 1.5;
+const AVOIDANCE_WEIGHT: f32 = 1.0;
+const AVOIDANCE_TIME_HORIZON: f32 = 1.0;
+
+pub struct Neighbor {
+    pub entity: usize,
+    pub position: cgmath::Vector3<f32>,
+    pub velocity: cgmath::Vector3<f32>,
+    pub radius: f32,
+    // Neighbors that steer themselves take half of the responsibility for avoiding a collision.
+    pub is_agent: bool,
+}
+
+fn flatten(vec: cgmath::Vector3<f32>) -> cgmath::Vector3<f32> {
+    cgmath::vec3(vec.x, 0.0, vec.z)
+}
+
+// Push away from neighbors that are closer than their combined radius. Entities on the exact
+// same point are split up along the x axis, ordered by their entity ids.
+pub fn separation(
+    entity: usize,
+    position: cgmath::Vector3<f32>,
+    radius: f32,
+    neighbors: &[Neighbor],
+) -> cgmath::Vector3<f32> {
+    let mut force = cgmath::Vector3::zero();
+
+    for neighbor in neighbors {
+        let offset = flatten(position - neighbor.position);
+        let distance = offset.magnitude();
+        let min_distance = radius + neighbor.radius + SEPARATION_PADDING;
+
+        if distance >= min_distance {
+            continue;
+        }
+
+        let away = if distance > f32::EPSILON {
+            offset / distance
+        } else if entity < neighbor.entity {
+            cgmath::Vector3::unit_x()
+        } else {
+            -cgmath::Vector3::unit_x()
+        };
+
+        force += away * (1.0 - distance / min_distance);
+    }
+
+    force
+}
+
+// Predict the closest approach to each neighbor given both velocities, and steer sideways
+// if it would result in a collision within the time horizon.
+pub fn avoidance(
+    position: cgmath::Vector3<f32>,
+    velocity: cgmath::Vector3<f32>,
+    radius: f32,
+    neighbors: &[Neighbor],
+) -> cgmath::Vector3<f32> {
+    let mut force = cgmath::Vector3::zero();
+
+    for neighbor in neighbors {
+        let relative_position = flatten(neighbor.position - position);
+        let relative_velocity = flatten(velocity - neighbor.velocity);
+        let relative_speed_squared = relative_velocity.magnitude2();
+
+        if relative_speed_squared <= f32::EPSILON {
+            continue;
+        }
+
+        let time = relative_position.dot(relative_velocity) / relative_speed_squared;
+
+        if time <= 0.0 || time > AVOIDANCE_TIME_HORIZON {
+            continue;
+        }
+
+        let closest_offset = relative_position - relative_velocity * time;
+        let closest_distance = closest_offset.magnitude();
+
+        if closest_distance >= radius + neighbor.radius {
+            continue;
+        }
+
+        // Head-on collisions don't have a natural side to dodge to, so always go right.
+        let away = if closest_distance > f32::EPSILON {
+            -closest_offset / closest_distance
+        } else {
+            cgmath::vec3(relative_velocity.z, 0.0, -relative_velocity.x).normalize()
+        };
+
+        let responsibility = if neighbor.is_agent { 0.5 } else { 1.0 };
+
+        force += away * (1.0 - time / AVOIDANCE_TIME_HORIZON) * responsibility;
+    }
+
+    force
+}
+
+pub fn steer(
+    entity: usize,
+    position: cgmath::Vector3<f32>,
+    desired_velocity: cgmath::Vector3<f32>,
+    radius: f32,
+    max_speed: f32,
+    neighbors: &[Neighbor],
+) -> cgmath::Vector3<f32> {
+    let separation_force = separation(entity, position, radius, neighbors);
+    let avoidance_force = avoidance(position, desired_velocity, radius, neighbors);
+
+    let velocity = flatten(
+        desired_velocity
+            + (separation_force * SEPARATION_WEIGHT + avoidance_force * AVOIDANCE_WEIGHT)
+                * max_speed,
+    );
+
+    if velocity.magnitude() > max_speed {
+        velocity.normalize_to(max_speed)
+    } else {
+        velocity
+    }
+}
+
+// Moving into a neighbor is only allowed if it increases the distance between them, so actors
+// that already overlap can still separate.
+pub fn is_blocked(
+    position: cgmath::Vector3<f32>,
+    new_position: cgmath::Vector3<f32>,
+    radius: f32,
+    neighbors: &[Neighbor],
+) -> bool {
+    neighbors.iter().any(|neighbor| {
+        let min_distance = radius + neighbor.radius;
+        let distance = flatten(position - neighbor.position).magnitude();
+        let new_distance = flatten(new_position - neighbor.position).magnitude();
+
+        new_distance < min_distance && new_distance < distance
+    })
+}